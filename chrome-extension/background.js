@@ -7,6 +7,11 @@ const API_URL = 'http://127.0.0.1:27190/status';
 const POLL_INTERVAL = 2000; // 2秒轮询
 
 let currentBlockedSites = [];
+// 'blocklist' 屏蔽 currentBlockedSites；'allowlist' 只放行 currentAllowedSites
+let currentMode = 'blocklist';
+let currentAllowedSites = [];
+// 带路径和查询条件的完整规则 [{ host, path_prefix, query }]
+let currentRules = [];
 let isFocusing = false;
 let consecutiveFailures = 0;
 const MAX_FAILURES_BEFORE_CLEAR = 30; // ~1分钟（30次 × 2秒轮询）
@@ -34,17 +39,70 @@ chrome.alarms.onAlarm.addListener((alarm) => {
   }
 });
 
-// 实时拦截专注期间新导航到黑名单的标签页
+// 白名单模式下始终放行的本机地址
+const LOOPBACK_HOSTS = ['localhost', '127.0.0.1', '[::1]'];
+
+function matchesSite(hostname, site) {
+  return hostname === site || hostname.endsWith('.' + site);
+}
+
+function ruleBaseHost(rule) {
+  return rule.host.replace(/^www\./, '');
+}
+
+function isHostOnlyRule(rule) {
+  return !rule.path_prefix && rule.query.length === 0;
+}
+
+/**
+ * 规则是否匹配 URL：主机名含子域名，路径按段匹配前缀，查询条件为 key 或 key=value 且全部满足
+ */
+function ruleMatches(rule, url) {
+  if (!matchesSite(url.hostname, ruleBaseHost(rule))) return false;
+
+  const path = url.pathname.toLowerCase();
+  const prefix = rule.path_prefix;
+  if (prefix && path !== prefix && !path.startsWith(prefix + '/')) return false;
+
+  const pairs = url.search.slice(1).toLowerCase().split('&');
+  return rule.query.every(pattern => pattern.includes('=')
+    ? pairs.includes(pattern)
+    : pairs.some(pair => pair.split('=')[0] === pattern));
+}
+
+/**
+ * 黑名单：命中任一规则时屏蔽
+ */
+function blocklistBlocks(url, rules) {
+  return rules.some(rule => ruleMatches(rule, url));
+}
+
+/**
+ * 白名单：只放行命中规则的地址和本机地址
+ */
+function allowlistAllows(url, rules) {
+  return LOOPBACK_HOSTS.includes(url.hostname) || rules.some(rule => ruleMatches(rule, url));
+}
+
+/**
+ * 当前规则下是否屏蔽该 URL（只处理 http/https）
+ */
+function isUrlBlocked(rawUrl) {
+  const url = new URL(rawUrl);
+  if (url.protocol !== 'http:' && url.protocol !== 'https:') return false;
+  if (currentMode === 'allowlist') {
+    return !allowlistAllows(url, currentRules);
+  }
+  return blocklistBlocks(url, currentRules);
+}
+
+// 实时拦截专注期间新导航到被屏蔽网站的标签页
+// 单页应用切换路径（如 YouTube 进入 Shorts）不会触发新请求，只能在这里拦截
 chrome.tabs.onUpdated.addListener((tabId, changeInfo, tab) => {
   if (!isFocusing || !changeInfo.url) return;
   if (changeInfo.url.startsWith('chrome-extension://')) return;
   try {
-    const url = new URL(changeInfo.url);
-    const hostname = url.hostname;
-    const isBlocked = currentBlockedSites.some(site =>
-      hostname === site || hostname.endsWith('.' + site)
-    );
-    if (isBlocked) {
+    if (isUrlBlocked(changeInfo.url)) {
       chrome.tabs.update(tabId, {
         url: chrome.runtime.getURL('blocked.html')
       });
@@ -73,19 +131,18 @@ async function pollStatus() {
       console.log('[PomodoroFocus] 连续失败', consecutiveFailures, '次，清除屏蔽规则');
       isFocusing = false;
       currentBlockedSites = [];
+      currentAllowedSites = [];
+      currentRules = [];
       await clearBlockRules();
     }
   }
 }
 
 /**
- * 处理状态更新
+ * 统一提取纯域名，兼容任何格式输入
  */
-function handleStatusUpdate(data) {
-  const { focusing, blocked_sites } = data;
-
-  // 统一提取纯域名，兼容任何格式输入
-  const normalizedSites = (blocked_sites || []).map(site => {
+function normalizeSites(sites) {
+  return (sites || []).map(site => {
     try {
       if (site.startsWith('http://') || site.startsWith('https://')) {
         return new URL(site).hostname;
@@ -95,42 +152,133 @@ function handleStatusUpdate(data) {
       return site.replace(/\/+$/, '');
     }
   });
+}
 
-  const sitesChanged = !arraysEqual(normalizedSites, currentBlockedSites);
+/**
+ * 旧版 App 没有 rules 字段，把网站列表当作只有主机名的规则
+ */
+function hostOnlyRules(sites) {
+  return sites.map(host => ({ host, path_prefix: '', query: [] }));
+}
+
+/**
+ * 处理状态更新
+ */
+function handleStatusUpdate(data) {
+  const { focusing, blocked_sites, allowed_sites } = data;
+  // 旧版 App 没有 mode 字段，按黑名单处理
+  const mode = data.mode === 'allowlist' ? 'allowlist' : 'blocklist';
+
+  const normalizedSites = normalizeSites(blocked_sites);
+  const normalizedAllowed = normalizeSites(allowed_sites);
+  const rules = Array.isArray(data.rules)
+    ? data.rules
+    : hostOnlyRules(mode === 'allowlist' ? normalizedAllowed : normalizedSites);
+
+  const sitesChanged = mode !== currentMode
+    || !arraysEqual(normalizedSites, currentBlockedSites)
+    || !arraysEqual(normalizedAllowed, currentAllowedSites)
+    || JSON.stringify(rules) !== JSON.stringify(currentRules);
   const focusChanged = focusing !== isFocusing;
 
   isFocusing = focusing;
+  currentMode = mode;
   currentBlockedSites = normalizedSites;
+  currentAllowedSites = normalizedAllowed;
+  currentRules = rules;
+
+  // 白名单为空时屏蔽所有网站，仍然需要规则
+  const hasRules = mode === 'allowlist' || currentRules.length > 0;
 
-  if (focusing && currentBlockedSites.length > 0) {
+  if (focusing && hasRules) {
     if (sitesChanged || focusChanged) {
-      updateBlockRules(currentBlockedSites);
+      updateBlockRules();
     }
-    // 只在专注刚开始时扫描一次已打开的标签页
-    if (focusChanged) {
-      redirectExistingTabs(currentBlockedSites);
+    // 专注刚开始或规则变化时扫描已打开的标签页
+    if (focusChanged || sitesChanged) {
+      redirectExistingTabs();
     }
-  } else if (!focusing && (sitesChanged || focusChanged)) {
+  } else if (sitesChanged || focusChanged) {
     clearBlockRules();
   }
 }
 
+function escapeRegex(text) {
+  return text.replace(/[.*+?^${}()|[\]\\]/g, '\\$&');
+}
+
+/**
+ * 带路径的规则对应的 declarativeNetRequest 正则（不含查询条件）
+ */
+function pathRegex(rule) {
+  return `^https?://([^/?#]*\\.)?${escapeRegex(ruleBaseHost(rule))}(:[0-9]+)?`
+    + `${escapeRegex(rule.path_prefix)}([/?#]|$)`;
+}
+
 /**
  * 更新屏蔽规则
+ *
+ * 只有主机名的规则用 requestDomains，带路径的规则用正则；
+ * 带查询条件的屏蔽规则无法用 declarativeNetRequest 准确表达，只由标签页监听拦截
  */
-async function updateBlockRules(sites) {
-  const rules = sites.map((site, index) => ({
-    id: index + 1,
-    priority: 1,
-    action: {
-      type: 'redirect',
-      redirect: { extensionPath: '/blocked.html' }
-    },
-    condition: {
-      requestDomains: [site],
+async function updateBlockRules() {
+  const redirect = {
+    type: 'redirect',
+    redirect: { extensionPath: '/blocked.html' }
+  };
+  const hostRules = currentRules.filter(isHostOnlyRule);
+  const pathRules = currentRules.filter(rule => !isHostOnlyRule(rule));
+  let rules;
+
+  if (currentMode === 'allowlist') {
+    // 优先级更高的 allow 规则放行白名单，其余主页面请求全部跳转
+    rules = [
+      {
+        id: 1,
+        priority: 1,
+        action: redirect,
+        condition: { regexFilter: '^https?://', resourceTypes: ['main_frame'] }
+      },
+      {
+        id: 2,
+        priority: 2,
+        action: { type: 'allow' },
+        condition: {
+          requestDomains: [...hostRules.map(ruleBaseHost), 'localhost', '127.0.0.1'],
+          resourceTypes: ['main_frame']
+        }
+      },
+      ...pathRules.map((rule, index) => ({
+        id: index + 3,
+        priority: 2,
+        action: { type: 'allow' },
+        condition: {
+          regexFilter: pathRegex(rule),
+          isUrlFilterCaseSensitive: false,
+          resourceTypes: ['main_frame']
+        }
+      }))
+    ];
+  } else {
+    // 导入的分类列表可能有数万个域名，合并成一条规则，避免超过动态规则数量上限
+    const domainRules = hostRules.length > 0 ? [{
+      requestDomains: [...new Set(hostRules.map(ruleBaseHost))],
       resourceTypes: ['main_frame']
-    }
-  }));
+    }] : [];
+    const regexRules = pathRules
+      .filter(rule => rule.query.length === 0)
+      .map(rule => ({
+        regexFilter: pathRegex(rule),
+        isUrlFilterCaseSensitive: false,
+        resourceTypes: ['main_frame']
+      }));
+    rules = [...domainRules, ...regexRules].map((condition, index) => ({
+      id: index + 1,
+      priority: 1,
+      action: redirect,
+      condition
+    }));
+  }
 
   await clearBlockRules();
 
@@ -138,7 +286,7 @@ async function updateBlockRules(sites) {
     await chrome.declarativeNetRequest.updateDynamicRules({
       addRules: rules
     });
-    console.log('[PomodoroFocus] 已添加屏蔽规则:', sites);
+    console.log('[PomodoroFocus] 已添加屏蔽规则:', currentMode, currentRules);
   }
 }
 
@@ -158,10 +306,10 @@ async function clearBlockRules() {
 }
 
 /**
- * 重定向已打开的黑名单标签页到 blocked.html
+ * 重定向已打开的被屏蔽标签页到 blocked.html
  */
-async function redirectExistingTabs(sites) {
-  console.log('[PomodoroFocus] 扫描已打开标签页, 屏蔽列表:', sites);
+async function redirectExistingTabs() {
+  console.log('[PomodoroFocus] 扫描已打开标签页, 模式:', currentMode);
   const tabs = await chrome.tabs.query({});
   const blockedUrl = chrome.runtime.getURL('blocked.html');
 
@@ -171,11 +319,7 @@ async function redirectExistingTabs(sites) {
     if (!tabUrl) continue;
     if (tabUrl.startsWith('chrome-extension://') || tabUrl.startsWith('chrome://')) continue;
     try {
-      const hostname = new URL(tabUrl).hostname;
-      const isBlocked = sites.some(site =>
-        hostname === site || hostname.endsWith('.' + site)
-      );
-      if (isBlocked) {
+      if (isUrlBlocked(tabUrl)) {
         console.log('[PomodoroFocus] 重定向标签页:', tabUrl);
         chrome.tabs.update(tab.id, { url: blockedUrl });
       }
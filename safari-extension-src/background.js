@@ -1,5 +1,8 @@
 const API_URL = 'http://127.0.0.1:27190/status';
-let currentBlockedSites = [];
+// 'blocklist' 屏蔽命中规则的地址；'allowlist' 只放行命中规则的地址
+let currentMode = 'blocklist';
+// 带路径和查询条件的规则 [{ host, path_prefix, query }]
+let currentRules = [];
 let isFocusing = false;
 let consecutiveFailures = 0;
 const MAX_FAILURES_BEFORE_CLEAR = 30;
@@ -20,39 +23,61 @@ async function pollStatus() {
 
     const wasFocusing = isFocusing;
     isFocusing = data.focusing;
-    currentBlockedSites = (data.blocked_sites || []).map(site => {
+    currentMode = data.mode === 'allowlist' ? 'allowlist' : 'blocklist';
+    // 旧版 App 没有 rules 字段，把网站列表当作只有主机名的规则
+    currentRules = Array.isArray(data.rules) ? data.rules : (data.blocked_sites || []).map(site => {
       try {
-        if (site.startsWith('http')) return new URL(site).hostname;
-        return site.replace(/\/+$/, '');
-      } catch { return site; }
+        if (site.startsWith('http')) site = new URL(site).hostname;
+      } catch {}
+      return { host: site.replace(/\/+$/, ''), path_prefix: '', query: [] };
     });
 
     // 专注刚开始时扫描已打开的标签页
-    if (isFocusing && !wasFocusing && currentBlockedSites.length > 0) {
-      redirectExistingTabs(currentBlockedSites);
+    if (isFocusing && !wasFocusing && (currentMode === 'allowlist' || currentRules.length > 0)) {
+      redirectExistingTabs();
     }
   } catch (error) {
     consecutiveFailures++;
     if (isFocusing && consecutiveFailures >= MAX_FAILURES_BEFORE_CLEAR) {
       console.log('[Safari] 连续失败', consecutiveFailures, '次，清除状态');
       isFocusing = false;
-      currentBlockedSites = [];
+      currentRules = [];
     }
   }
 }
 
-function isBlocked(url) {
-  if (!isFocusing || !url) return false;
+const LOOPBACK_HOSTS = ['localhost', '127.0.0.1', '[::1]'];
+
+// 主机名含子域名，路径按段匹配前缀，查询条件为 key 或 key=value 且全部满足
+function ruleMatches(rule, url) {
+  const host = rule.host.replace(/^www\./, '');
+  if (url.hostname !== host && !url.hostname.endsWith('.' + host)) return false;
+
+  const path = url.pathname.toLowerCase();
+  const prefix = rule.path_prefix;
+  if (prefix && path !== prefix && !path.startsWith(prefix + '/')) return false;
+
+  const pairs = url.search.slice(1).toLowerCase().split('&');
+  return rule.query.every(pattern => pattern.includes('=')
+    ? pairs.includes(pattern)
+    : pairs.some(pair => pair.split('=')[0] === pattern));
+}
+
+function isBlocked(rawUrl) {
+  if (!isFocusing || !rawUrl) return false;
   try {
-    const hostname = new URL(url).hostname;
-    return currentBlockedSites.some(site =>
-      hostname === site || hostname.endsWith('.' + site)
-    );
+    const url = new URL(rawUrl);
+    if (url.protocol !== 'http:' && url.protocol !== 'https:') return false;
+    const matched = currentRules.some(rule => ruleMatches(rule, url));
+    if (currentMode === 'allowlist') {
+      return !matched && !LOOPBACK_HOSTS.includes(url.hostname);
+    }
+    return matched;
   } catch { return false; }
 }
 
-async function redirectExistingTabs(sites) {
-  console.log('[Safari] 扫描已打开标签页, 屏蔽列表:', sites);
+async function redirectExistingTabs() {
+  console.log('[Safari] 扫描已打开标签页, 规则:', currentMode, currentRules);
   const tabs = await browser.tabs.query({});
   const blockedUrl = browser.runtime.getURL('blocked.html');
 
@@ -61,11 +86,7 @@ async function redirectExistingTabs(sites) {
     if (!tabUrl) continue;
     if (tabUrl.startsWith('safari-web-extension://')) continue;
     try {
-      const hostname = new URL(tabUrl).hostname;
-      const blocked = sites.some(site =>
-        hostname === site || hostname.endsWith('.' + site)
-      );
-      if (blocked) {
+      if (isBlocked(tabUrl)) {
         console.log('[Safari] 重定向标签页:', tabUrl);
         browser.tabs.update(tab.id, { url: blockedUrl });
       }
@@ -0,0 +1,53 @@
+use std::fs;
+use std::path::Path;
+use tauri::State;
+
+use crate::models::{BundleImportOptions, BundlePreview, BundleSection, ConfigBundle};
+use crate::state::AppState;
+use crate::storage;
+
+fn read_bundle(path: &str) -> Result<ConfigBundle, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("读取配置包失败: {}", e))?;
+    ConfigBundle::parse(&content).map_err(|e| e.to_string())
+}
+
+/// 将当前配置的指定分区导出为配置包
+#[tauri::command]
+pub fn export_config_bundle(
+    state: State<AppState>,
+    path: String,
+    sections: Vec<BundleSection>,
+) -> Result<(), String> {
+    let bundle = {
+        let config = state.config.lock().map_err(|e| e.to_string())?;
+        ConfigBundle::from_config(&config, &sections)
+    };
+    let content = serde_json::to_string_pretty(&bundle).map_err(|e| e.to_string())?;
+    storage::write_atomic(Path::new(&path), content.as_bytes()).map_err(|e| e.to_string())
+}
+
+/// 预览导入配置包后会发生的变化（不修改配置）
+#[tauri::command]
+pub fn preview_config_bundle(
+    state: State<AppState>,
+    path: String,
+    options: BundleImportOptions,
+) -> Result<BundlePreview, String> {
+    let bundle = read_bundle(&path)?;
+    let config = state.config.lock().map_err(|e| e.to_string())?;
+    let (_, preview) = bundle.plan_import(&config, &options);
+    Ok(preview)
+}
+
+/// 导入配置包，返回实际应用的变更
+#[tauri::command]
+pub fn import_config_bundle(
+    state: State<AppState>,
+    path: String,
+    options: BundleImportOptions,
+) -> Result<BundlePreview, String> {
+    let bundle = read_bundle(&path)?;
+    state
+        .update_config(|config| bundle.plan_import(config, &options))
+        .map_err(|e| e.to_string())
+}
@@ -1,9 +1,11 @@
 use std::fs;
 use base64::Engine;
 use base64::engine::general_purpose::STANDARD as BASE64;
-use tauri::State;
+use tauri::{AppHandle, State};
 
+use crate::errors::AppError;
 use crate::models::Config;
+use crate::paths;
 use crate::state::AppState;
 
 #[tauri::command]
@@ -12,22 +14,15 @@ pub fn get_config(state: State<AppState>) -> Result<Config, String> {
     Ok(config.clone())
 }
 
+/// 专注期间被承诺锁拒绝时返回结构化的 `AppError::CommitmentLocked`
 #[tauri::command]
-pub fn save_config(state: State<AppState>, config: Config) -> Result<(), String> {
-    config.save().map_err(|e| e.to_string())?;
-
-    let mut current_config = state.config.lock().map_err(|e| e.to_string())?;
-    *current_config = config.clone();
-
-    let mut app_blocker = state.app_blocker.lock().map_err(|e| e.to_string())?;
-    app_blocker.update_blocked_apps(config.blocked_apps.clone());
-
-    let mut site_blocker = state.site_blocker.lock().map_err(|e| e.to_string())?;
-    site_blocker.update_blocked_sites(config.blocked_sites.clone());
-
-    let mut scheduler = state.scheduler.lock().map_err(|e| e.to_string())?;
-    scheduler.update_schedules(config.schedules.clone());
-
+pub fn save_config(
+    app_handle: AppHandle,
+    state: State<AppState>,
+    config: Config,
+) -> Result<(), AppError> {
+    state.apply_config(config)?;
+    crate::commands::sync_scheduler(&app_handle);
     Ok(())
 }
 
@@ -39,12 +34,15 @@ pub fn get_config_path() -> Result<String, String> {
 }
 
 fn bg_path() -> Result<std::path::PathBuf, String> {
-    Config::config_dir().map(|d| d.join("bg.jpg")).map_err(|e| e.to_string())
+    paths::data_dir().map(|d| d.join("bg.jpg")).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub fn set_background(state: State<AppState>, source_path: String) -> Result<String, String> {
     let dest = bg_path()?;
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
     fs::copy(&source_path, &dest).map_err(|e| format!("复制图片失败: {}", e))?;
     let dest_str = dest.to_string_lossy().to_string();
 
@@ -0,0 +1,24 @@
+use chrono::{Duration as ChronoDuration, Local};
+
+use crate::errors::AppError;
+use crate::models::{HistoryEntry, HistoryStats, SessionHistory};
+
+/// 统计时默认和最多回溯的天数
+const DEFAULT_STATS_DAYS: u32 = 30;
+const MAX_STATS_DAYS: u32 = 366;
+
+/// 最近的历史记录，按时间倒序，最多 limit 条
+#[tauri::command]
+pub fn get_history(limit: Option<usize>) -> Result<Vec<HistoryEntry>, AppError> {
+    let history = SessionHistory::load()?;
+    let limit = limit.unwrap_or(history.entries.len());
+    Ok(history.entries.into_iter().rev().take(limit).collect())
+}
+
+/// 最近 days 天（默认 30 天）的统计
+#[tauri::command]
+pub fn get_history_stats(days: Option<u32>) -> Result<HistoryStats, AppError> {
+    let days = days.unwrap_or(DEFAULT_STATS_DAYS).clamp(1, MAX_STATS_DAYS);
+    let since = Local::now().naive_local() - ChronoDuration::days(days as i64);
+    Ok(SessionHistory::load()?.stats(since))
+}
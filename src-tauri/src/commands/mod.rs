@@ -3,9 +3,15 @@ pub mod timer;
 pub mod blocker;
 pub mod sites;
 pub mod apps;
+pub mod bundle;
+pub mod schedule;
+pub mod history;
 
 pub use config::*;
 pub use timer::*;
 pub use blocker::*;
 pub use sites::*;
 pub use apps::*;
+pub use bundle::*;
+pub use schedule::*;
+pub use history::*;
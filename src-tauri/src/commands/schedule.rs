@@ -0,0 +1,513 @@
+use chrono::{Duration as ChronoDuration, Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::commands::{begin_focus, end_focus};
+use crate::errors::AppError;
+use crate::models::{
+    CatchUpPolicy, EmergencyPolicy, ExceptionKind, FocusSession, HistoryEntry, HistoryKind,
+    ScheduleException, ScheduledWindow, SessionHistory,
+};
+use crate::services::{IcsCalendar, OccurrenceSource, ScheduleOccurrence, Scheduler, SystemClock};
+use crate::state::AppState;
+
+/// 定时触发的专注短于该秒数时不启动（时间段即将结束）
+const MIN_SCHEDULED_SECONDS: u64 = 60;
+/// 调度器在时间段结束时唤醒的误差
+const SCHEDULE_END_TOLERANCE_SECONDS: u64 = 5;
+/// 导入节假日时重复事件向后展开的天数
+const HOLIDAY_IMPORT_DAYS: i64 = 730;
+/// 查询即将到来的时间段时默认和最多查询的天数
+const DEFAULT_UPCOMING_DAYS: u32 = 7;
+const MAX_UPCOMING_DAYS: u32 = 62;
+/// 启动时最多往前记录多少天内错过的时间段
+const MAX_CATCH_UP_DAYS: i64 = 31;
+/// 返回给前端的本地时间格式
+const DATETIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// 切换手动/定时模式，立即启动或停止调度线程
+#[tauri::command]
+pub fn set_mode(
+    app_handle: AppHandle,
+    state: State<AppState>,
+    mode: String,
+) -> Result<(), AppError> {
+    switch_mode(&app_handle, &state, &mode)
+}
+
+pub fn switch_mode(app_handle: &AppHandle, state: &AppState, mode: &str) -> Result<(), AppError> {
+    let mut config = state.config.lock().unwrap().clone();
+    config.mode = mode.to_string();
+    state.apply_config(config)?;
+    sync_scheduler(app_handle);
+    Ok(())
+}
+
+/// 即将到来的专注时间段
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpcomingFocus {
+    /// 本地时间，格式 `YYYY-MM-DDTHH:MM:SS`
+    pub start: String,
+    pub end: String,
+    pub source: OccurrenceSource,
+    pub label: String,
+    /// 当前时刻已在该时间段内
+    pub in_progress: bool,
+    /// 到点时是否会自动开始专注：需要定时模式，进行中或与前一段连续的时间段不会再开始
+    pub will_start_session: bool,
+}
+
+impl UpcomingFocus {
+    fn new(occurrence: ScheduleOccurrence, in_progress: bool, will_start_session: bool) -> Self {
+        UpcomingFocus {
+            start: occurrence.start.format(DATETIME_FORMAT).to_string(),
+            end: occurrence.end.format(DATETIME_FORMAT).to_string(),
+            source: occurrence.source,
+            label: occurrence.label,
+            in_progress,
+            will_start_session,
+        }
+    }
+}
+
+/// 列出今天起 days 天内（默认 7 天）尚未结束的专注时间段
+#[tauri::command]
+pub fn get_upcoming_schedules(state: State<AppState>, days: Option<u32>) -> Vec<UpcomingFocus> {
+    let days = days
+        .unwrap_or(DEFAULT_UPCOMING_DAYS)
+        .clamp(1, MAX_UPCOMING_DAYS);
+    upcoming_focus(&state, Local::now().naive_local(), days)
+}
+
+pub fn upcoming_focus(state: &AppState, now: NaiveDateTime, days: u32) -> Vec<UpcomingFocus> {
+    annotate_upcoming(state, now, days)
+        .into_iter()
+        .map(|(o, in_progress, will_start_session)| UpcomingFocus::new(o, in_progress, will_start_session))
+        .collect()
+}
+
+/// days 天内第一个会自动开始专注的时间段的开始时间
+pub fn next_session_start(
+    state: &AppState,
+    now: NaiveDateTime,
+    days: u32,
+) -> Option<NaiveDateTime> {
+    annotate_upcoming(state, now, days)
+        .into_iter()
+        .find(|(_, _, will_start)| *will_start)
+        .map(|(o, _, _)| o.start)
+}
+
+/// 为每个时间段标注（是否进行中，是否会开始专注）
+fn annotate_upcoming(
+    state: &AppState,
+    now: NaiveDateTime,
+    days: u32,
+) -> Vec<(ScheduleOccurrence, bool, bool)> {
+    let scheduled = state.is_scheduled_mode();
+    let occurrences = state.scheduler.lock().unwrap().upcoming_at(now, days);
+
+    // 已经覆盖到的最晚结束时间，开始于其之前的时间段会与前面合并成一次专注
+    let mut covered_until: Option<NaiveDateTime> = None;
+
+    occurrences
+        .into_iter()
+        .map(|o| {
+            let in_progress = o.start <= now;
+            let continues_previous = covered_until.is_some_and(|until| o.start <= until);
+            covered_until = Some(covered_until.map_or(o.end, |until| until.max(o.end)));
+            let will_start = scheduled && !in_progress && !continues_previous;
+            (o, in_progress, will_start)
+        })
+        .collect()
+}
+
+/// 从 .ics 导入节假日的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HolidayImportReport {
+    /// 新增的跳过日期数
+    pub added: usize,
+    /// 已存在跳过例外、未重复添加的日期数
+    pub duplicates: usize,
+}
+
+/// 把本地 .ics 文件中的事件日期导入为跳过定时专注的例外日期
+#[tauri::command]
+pub fn import_holidays_ics(
+    state: State<AppState>,
+    path: String,
+) -> Result<HolidayImportReport, AppError> {
+    let calendar = IcsCalendar::load(Path::new(&path))?;
+
+    let mut config = state.config.lock().unwrap().clone();
+    let mut report = HolidayImportReport {
+        added: 0,
+        duplicates: 0,
+    };
+
+    // 每年重复的节日展开到两年后
+    let until = Local::now().date_naive() + ChronoDuration::days(HOLIDAY_IMPORT_DAYS);
+    for (date, name) in calendar.holiday_dates(until) {
+        let date = date.format("%Y-%m-%d").to_string();
+        let exists = config
+            .schedule_exceptions
+            .iter()
+            .any(|e| e.date == date && e.kind == ExceptionKind::Skip);
+        if exists {
+            report.duplicates += 1;
+            continue;
+        }
+        config.schedule_exceptions.push(ScheduleException {
+            date,
+            kind: ExceptionKind::Skip,
+            start: None,
+            end: None,
+            name: (!name.is_empty()).then_some(name),
+        });
+        report.added += 1;
+    }
+
+    config
+        .schedule_exceptions
+        .sort_by(|a, b| a.date.cmp(&b.date));
+    state.apply_config(config)?;
+
+    println!(
+        "[Scheduler] 已从 {} 导入 {} 个节假日（{} 个已存在）",
+        path, report.added, report.duplicates
+    );
+    Ok(report)
+}
+
+/// 按当前模式启动或停止调度线程，并刷新托盘菜单
+pub fn sync_scheduler(app_handle: &AppHandle) {
+    let state = app_handle.state::<AppState>();
+    let running = state.scheduler_running.load(Ordering::SeqCst);
+
+    if state.is_scheduled_mode() {
+        if !running {
+            start_scheduler(app_handle, &state, false);
+        }
+    } else if running {
+        println!("[Scheduler] 切换为手动模式，停止调度");
+        state.stop_scheduler();
+        // 已开始的定时专注按开始时确定的时间段由计时器结束，不再由调度器停止
+        state.pending_catch_up.lock().unwrap().take();
+        SessionHistory::clear_last_seen();
+    }
+
+    crate::update_tray_menu(app_handle);
+}
+
+/// 应用启动时的定时调度：记录未运行期间错过的时间段，按设置处理当前所在的时间段，再启动调度线程
+pub fn launch_scheduler(app_handle: &AppHandle) {
+    let state = app_handle.state::<AppState>();
+    if !state.is_scheduled_mode() {
+        SessionHistory::clear_last_seen();
+        crate::update_tray_menu(app_handle);
+        return;
+    }
+
+    let now = Local::now().naive_local();
+    record_missed_windows(&state, now);
+
+    let current = {
+        let scheduler = state.scheduler.lock().unwrap();
+        scheduler.current_occurrence_at(now).map(|occurrence| ScheduleOccurrence {
+            end: scheduler.current_schedule_end_at(now).unwrap_or(occurrence.end),
+            ..occurrence
+        })
+    };
+    let in_schedule = current.is_some();
+    if let Some(occurrence) = current {
+        catch_up(app_handle, &state, occurrence);
+    }
+
+    start_scheduler(app_handle, &state, in_schedule);
+    crate::update_tray_menu(app_handle);
+}
+
+/// 把上次运行之后、现在之前完整错过的时间段写入历史
+fn record_missed_windows(state: &AppState, now: NaiveDateTime) {
+    let Some(last_seen) = SessionHistory::load_last_seen() else {
+        return;
+    };
+    let from = last_seen.max(now - ChronoDuration::days(MAX_CATCH_UP_DAYS));
+    let missed = state.scheduler.lock().unwrap().occurrences_between(from, now);
+    if missed.is_empty() {
+        return;
+    }
+
+    println!("[Scheduler] 应用未运行期间错过 {} 个时间段", missed.len());
+    let entries = missed
+        .iter()
+        .map(|o| HistoryEntry::new(HistoryKind::MissedWindow, o.start, o.end, &o.label))
+        .collect();
+    if let Err(e) = SessionHistory::append(entries) {
+        eprintln!("记录错过的时间段失败: {}", e);
+    }
+}
+
+fn record_skipped_window(occurrence: &ScheduleOccurrence) {
+    let entry = HistoryEntry::new(
+        HistoryKind::SkippedWindow,
+        occurrence.start,
+        occurrence.end,
+        &occurrence.label,
+    );
+    if let Err(e) = SessionHistory::append(vec![entry]) {
+        eprintln!("记录跳过的时间段失败: {}", e);
+    }
+}
+
+/// 启动时已处于时间段内：已恢复的专注不受影响，否则按设置加入、跳过或询问
+fn catch_up(app_handle: &AppHandle, state: &AppState, occurrence: ScheduleOccurrence) {
+    if state.timer_running.load(Ordering::SeqCst) {
+        return;
+    }
+
+    let policy = state.config.lock().unwrap().catch_up;
+    match policy {
+        CatchUpPolicy::Join => {
+            println!("[Scheduler] 启动时处于时间段 {}，加入专注", occurrence.label);
+            if let Err(e) = start_scheduled_focus(app_handle, state) {
+                eprintln!("启动时加入定时专注失败: {}", e);
+            }
+        }
+        CatchUpPolicy::Skip => {
+            println!("[Scheduler] 启动时处于时间段 {}，按设置跳过", occurrence.label);
+            record_skipped_window(&occurrence);
+        }
+        CatchUpPolicy::Prompt => {
+            let _ = app_handle.emit(
+                "schedule-catch-up",
+                UpcomingFocus::new(occurrence.clone(), true, false),
+            );
+            *state.pending_catch_up.lock().unwrap() = Some(occurrence);
+        }
+    }
+}
+
+/// 启动时等待确认的时间段（catch_up 为 prompt 时）
+#[tauri::command]
+pub fn get_pending_catch_up(state: State<AppState>) -> Option<UpcomingFocus> {
+    state
+        .pending_catch_up
+        .lock()
+        .unwrap()
+        .clone()
+        .map(|occurrence| UpcomingFocus::new(occurrence, true, false))
+}
+
+/// 回应启动时的询问：加入当前时间段的剩余部分，或跳过并记入历史
+#[tauri::command]
+pub fn resolve_catch_up(
+    app_handle: AppHandle,
+    state: State<AppState>,
+    join: bool,
+) -> Result<(), AppError> {
+    let Some(occurrence) = state.pending_catch_up.lock().unwrap().take() else {
+        return Ok(());
+    };
+
+    if !join {
+        record_skipped_window(&occurrence);
+        return Ok(());
+    }
+    if state.timer_running.load(Ordering::SeqCst) {
+        return Ok(());
+    }
+    start_scheduled_focus(&app_handle, &state).map_err(AppError::TimerError)
+}
+
+fn start_scheduler(app_handle: &AppHandle, state: &AppState, initially_in_schedule: bool) {
+    let mut scheduler_thread = state.scheduler_thread.lock().unwrap();
+
+    scheduler_thread.running_flag.store(true, Ordering::SeqCst);
+    let running_flag = Arc::clone(&scheduler_thread.running_flag);
+    let scheduler = Arc::clone(&state.scheduler);
+    let handle_clone = app_handle.clone();
+
+    let waker = Arc::clone(&state.scheduler_waker);
+
+    let handle = Scheduler::spawn(
+        scheduler,
+        Arc::new(SystemClock),
+        running_flag,
+        waker,
+        initially_in_schedule,
+        move |is_in_schedule| {
+            on_schedule_change(&handle_clone, is_in_schedule);
+        },
+        SessionHistory::save_last_seen,
+    );
+
+    scheduler_thread.handle = Some(handle);
+    state.scheduler_running.store(true, Ordering::SeqCst);
+    println!("[Scheduler] 定时模式已启动");
+}
+
+/// 按当前所在时间段的设置开始专注；不在时间段内或即将结束时什么也不做
+fn start_scheduled_focus(app_handle: &AppHandle, state: &AppState) -> Result<(), String> {
+    let now_ts = unix_now();
+    let Some(session) = scheduled_session(state, Local::now().naive_local()) else {
+        return Ok(());
+    };
+
+    println!("定时触发：开始专注 {} 秒", session.work_seconds);
+    let minutes = (session.work_seconds / 60) as u32;
+    let seconds = (session.work_seconds % 60) as u32;
+    let status = begin_focus(
+        app_handle,
+        state,
+        Some(minutes),
+        Some(seconds),
+        session.profile,
+        session.break_minutes,
+        session.emergency,
+    )?;
+    remember_window(
+        state,
+        ScheduledWindow {
+            end_time: now_ts + session.window_seconds,
+            round_minutes: session.round_minutes,
+        },
+    );
+    let _ = app_handle.emit("timer-update", status);
+    Ok(())
+}
+
+/// 记下定时专注的时间段，并写入会话文件，重启恢复后仍按该时间段循环和结束
+fn remember_window(state: &AppState, window: ScheduledWindow) {
+    *state.scheduled_focus.lock().unwrap() = Some(window);
+    let mut session = match FocusSession::load() {
+        Ok(Some(session)) => session,
+        _ => return,
+    };
+    session.scheduled = Some(window);
+    if let Err(e) = session.save() {
+        eprintln!("保存定时专注时间段失败: {}", e);
+    }
+}
+
+/// 定时专注一轮的参数，来自当前所在时间段的设置
+struct ScheduledSession {
+    work_seconds: u64,
+    break_minutes: Option<u32>,
+    profile: Option<String>,
+    emergency: EmergencyPolicy,
+    /// 距时间段结束的秒数
+    window_seconds: u64,
+    /// 时间段设置的专注时长，设置时休息结束后继续下一轮
+    round_minutes: Option<u32>,
+}
+
+/// 按给定时刻所在的时间段计算本轮专注；不在时间段内或即将结束时返回 None
+///
+/// 日历事件和加开的时间段没有单独设置，使用全局设置，一次专注到结束
+fn scheduled_session(state: &AppState, now: NaiveDateTime) -> Option<ScheduledSession> {
+    let (occurrence, end) = {
+        let scheduler = state.scheduler.lock().unwrap();
+        (
+            scheduler.current_occurrence_at(now)?,
+            scheduler.current_schedule_end_at(now)?,
+        )
+    };
+    let remaining = end.signed_duration_since(now).num_seconds().max(0) as u64;
+    if remaining < MIN_SCHEDULED_SECONDS {
+        return None;
+    }
+
+    let schedule = match (occurrence.source, occurrence.index) {
+        (OccurrenceSource::Schedule, Some(index)) => {
+            state.config.lock().unwrap().schedules.get(index).cloned()
+        }
+        _ => None,
+    };
+    let Some(schedule) = schedule else {
+        return Some(ScheduledSession {
+            work_seconds: remaining,
+            break_minutes: None,
+            profile: None,
+            emergency: EmergencyPolicy::Quota,
+            window_seconds: remaining,
+            round_minutes: None,
+        });
+    };
+
+    Some(ScheduledSession {
+        work_seconds: schedule
+            .work_minutes
+            .map_or(remaining, |m| (m as u64 * 60).min(remaining)),
+        break_minutes: schedule.break_minutes,
+        profile: schedule.profile,
+        emergency: schedule.emergency,
+        window_seconds: remaining,
+        round_minutes: schedule.work_minutes,
+    })
+}
+
+/// 定时专注休息结束时，若开始时确定的时间段按节奏循环且未结束，返回下一轮的专注秒数
+///
+/// 只看开始时记下的时间段，专注期间修改或删除计划、日历不影响本次专注
+pub fn next_scheduled_round(state: &AppState) -> Option<u64> {
+    let window = (*state.scheduled_focus.lock().unwrap())?;
+    let round = window.round_minutes? as u64 * 60;
+    let left = window.seconds_left(unix_now());
+    (left >= MIN_SCHEDULED_SECONDS).then(|| round.min(left))
+}
+
+/// 定时专注一轮专注结束时的休息秒数：不超过时间段的剩余时间，时间段即将结束时返回 None（直接结束专注）
+pub fn scheduled_break_seconds(state: &AppState, break_seconds: u32) -> Option<u32> {
+    let Some(window) = *state.scheduled_focus.lock().unwrap() else {
+        return Some(break_seconds);
+    };
+    let left = window.seconds_left(unix_now());
+    (left >= MIN_SCHEDULED_SECONDS).then(|| break_seconds.min(left as u32))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// 进入时间段时按该时间段的设置开始专注（未设置专注时长时专注到时间段结束），到达开始时确定的结束时间时结束由调度器开始的专注
+fn on_schedule_change(app_handle: &AppHandle, is_in_schedule: bool) {
+    let state = app_handle.state::<AppState>();
+    // 跨过边界后“下次专注”随之变化
+    crate::update_tray_menu(app_handle);
+
+    if is_in_schedule {
+        if state.timer_running.load(Ordering::SeqCst) {
+            println!("定时触发：已有专注进行中，跳过");
+            return;
+        }
+
+        if let Err(e) = start_scheduled_focus(app_handle, &state) {
+            eprintln!("定时触发开始专注失败: {}", e);
+        }
+    } else if let Some(occurrence) = state.pending_catch_up.lock().unwrap().take() {
+        println!("定时触发：启动时询问的时间段已结束，未加入");
+        record_skipped_window(&occurrence);
+    } else {
+        let window = *state.scheduled_focus.lock().unwrap();
+        let Some(window) = window else {
+            return;
+        };
+        // 修改计划、添加例外或删除日历事件导致提前离开时间段时不结束，专注到开始时确定的时间
+        if window.seconds_left(unix_now()) > SCHEDULE_END_TOLERANCE_SECONDS {
+            println!("定时触发：计划变化导致提前离开时间段，专注继续到原定时间");
+            return;
+        }
+        println!("定时触发：离开专注时间段");
+        if let Err(e) = end_focus(app_handle, &state) {
+            eprintln!("定时触发结束专注失败: {}", e);
+        }
+    }
+}
@@ -1,9 +1,20 @@
-use tauri::State;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::sync::Mutex;
+use std::thread;
+use tauri::{AppHandle, Emitter, Manager, State};
 
+use crate::errors::AppError;
+use crate::models::SiteCategory;
+use crate::services::{BlocklistImporter, ResolveFailure, SkippedLine};
 use crate::state::AppState;
 
+/// 同一时间只进行一次导入，避免并发读改写同一个分类文件
+static IMPORT_LOCK: Mutex<()> = Mutex::new(());
+
+/// 返回解析 IP 失败的域名（只有 hosts 屏蔽）
 #[tauri::command]
-pub fn block_sites(state: State<AppState>) -> Result<(), String> {
+pub fn block_sites(state: State<AppState>) -> Result<Vec<ResolveFailure>, String> {
     let site_blocker = state.site_blocker.lock().map_err(|e| e.to_string())?;
     site_blocker.block_sites().map_err(|e| e.to_string())
 }
@@ -17,5 +28,95 @@ pub fn unblock_sites(state: State<AppState>) -> Result<(), String> {
 #[tauri::command]
 pub fn get_blocked_sites(state: State<AppState>) -> Result<Vec<String>, String> {
     let site_blocker = state.site_blocker.lock().map_err(|e| e.to_string())?;
-    Ok(site_blocker.get_blocked_sites().clone())
+    Ok(site_blocker.get_blocked_sites())
+}
+
+/// 数据目录中的网站分类列表（内置分类在前）
+#[tauri::command]
+pub fn get_site_categories(state: State<AppState>) -> Result<Vec<SiteCategory>, String> {
+    let categories = state.site_categories.lock().map_err(|e| e.to_string())?;
+    Ok(categories.clone())
+}
+
+/// 导入屏蔽列表的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlocklistImportReport {
+    pub list_id: String,
+    pub list_name: String,
+    /// 新加入列表的域名数
+    pub added: usize,
+    /// 文件内重复或列表中已有的域名数
+    pub duplicates: usize,
+    /// 导入后列表的条目总数
+    pub total: usize,
+    /// 跳过的行数；skipped 只含前若干行的详情
+    pub skipped_count: usize,
+    pub skipped: Vec<SkippedLine>,
+}
+
+/// 在后台导入 hosts 或 adblock 格式的屏蔽列表，合并到名为 name 的自定义分类（不存在时新建）
+///
+/// 立即返回；完成后发送 blocklist-import-finished（BlocklistImportReport）
+/// 或 blocklist-import-failed（错误文本）事件
+#[tauri::command]
+pub fn import_blocklist(app_handle: AppHandle, path: String, name: String) -> Result<(), AppError> {
+    if name.trim().is_empty() {
+        return Err(AppError::ConfigError("列表名称不能为空".to_string()));
+    }
+    thread::spawn(move || match run_blocklist_import(&app_handle, &path, &name) {
+        Ok(report) => {
+            println!(
+                "[import_blocklist] {} 新增 {} 条，跳过 {} 行",
+                report.list_name, report.added, report.skipped_count
+            );
+            let _ = app_handle.emit("blocklist-import-finished", report);
+        }
+        Err(e) => {
+            eprintln!("[import_blocklist] 导入 {} 失败: {}", path, e);
+            let _ = app_handle.emit("blocklist-import-failed", e.to_string());
+        }
+    });
+    Ok(())
+}
+
+fn run_blocklist_import(
+    app_handle: &AppHandle,
+    path: &str,
+    name: &str,
+) -> Result<BlocklistImportReport, AppError> {
+    let content = fs::read(path)?;
+    let parsed = BlocklistImporter::parse(&String::from_utf8_lossy(&content));
+
+    let _guard = IMPORT_LOCK.lock().unwrap();
+    let mut categories = SiteCategory::load_all()?;
+    let mut category = match categories.iter().find(|c| c.name == name.trim()) {
+        Some(c) if c.builtin => {
+            return Err(AppError::ConfigError(format!(
+                "不能导入到内置分类: {}",
+                c.name
+            )))
+        }
+        Some(c) => c.clone(),
+        None => SiteCategory::new_custom(name, &categories),
+    };
+
+    let (added, duplicates) = category.add_sites(parsed.domains);
+    category.save()?;
+
+    let report = BlocklistImportReport {
+        list_id: category.id.clone(),
+        list_name: category.name.clone(),
+        added,
+        duplicates: duplicates + parsed.duplicates,
+        total: category.sites.len(),
+        skipped_count: parsed.skipped_count,
+        skipped: parsed.skipped,
+    };
+
+    match categories.iter_mut().find(|c| c.id == category.id) {
+        Some(existing) => *existing = category,
+        None => categories.push(category),
+    }
+    *app_handle.state::<AppState>().site_categories.lock().unwrap() = categories;
+    Ok(report)
 }
@@ -1,11 +1,11 @@
-use std::sync::atomic::Ordering;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tauri::{AppHandle, Emitter, Manager, State};
 
-use crate::models::{FocusSession, TimerState, TimerStatus};
-use crate::services::SiteBlocker;
+use crate::models::{EmergencyPolicy, FocusSession, TimerState, TimerStatus};
+use crate::services::{SiteBlocker, SiteRules};
 use crate::state::AppState;
 
 fn start_timer_thread(
@@ -65,7 +65,21 @@ fn start_timer_thread(
 
             if remaining == 0 {
                 if status.state == TimerState::Working {
-                    let break_seconds = status.break_minutes * 60;
+                    // 定时专注的休息不超过开始时确定的时间段，时间段即将结束时直接结束专注
+                    let break_seconds = {
+                        let state = app_handle.state::<AppState>();
+                        crate::commands::scheduled_break_seconds(&state, status.break_minutes * 60)
+                    };
+                    let Some(break_seconds) = break_seconds else {
+                        status.state = TimerState::Idle;
+                        status.remaining_seconds = 0;
+                        status.total_seconds = 0;
+                        let _ = app_handle.emit("timer-work-complete", ());
+                        let _ = app_handle.emit("timer-update", status.clone());
+                        drop(status);
+                        finish_naturally(&app_handle, &timer_running);
+                        break;
+                    };
                     status.state = TimerState::Breaking;
                     status.remaining_seconds = break_seconds;
                     status.total_seconds = break_seconds;
@@ -75,34 +89,36 @@ fn start_timer_thread(
                     // 重新计算休息结束时间
                     end_time = SystemTime::now() + Duration::from_secs(break_seconds as u64);
                 } else if status.state == TimerState::Breaking {
+                    // 按节奏循环的定时专注：时间段未结束时直接开始下一轮，屏蔽保持不变
+                    drop(status);
+                    let next_round = {
+                        let state = app_handle.state::<AppState>();
+                        crate::commands::next_scheduled_round(&state)
+                    };
+                    let mut status = timer_status.lock().unwrap();
+                    if status.state == TimerState::Idle {
+                        break;
+                    }
+                    if let Some(work_seconds) = next_round {
+                        status.state = TimerState::Working;
+                        status.remaining_seconds = work_seconds as u32;
+                        status.total_seconds = work_seconds as u32;
+                        let _ = app_handle.emit("timer-break-complete", ());
+                        let _ = app_handle.emit("timer-update", status.clone());
+                        let break_minutes = status.break_minutes;
+                        drop(status);
+                        end_time = SystemTime::now() + Duration::from_secs(work_seconds);
+                        save_next_round(work_seconds, break_minutes);
+                        continue;
+                    }
+
                     status.state = TimerState::Idle;
                     status.remaining_seconds = 0;
                     status.total_seconds = 0;
                     let _ = app_handle.emit("timer-break-complete", ());
                     let _ = app_handle.emit("timer-update", status.clone());
                     drop(status);
-
-                    // 自然结束清理
-                    timer_running.store(false, Ordering::SeqCst);
-                    FocusSession::delete();
-
-                    if let Some(overlay) = app_handle.get_webview_window("overlay") {
-                        let _ = overlay.destroy();
-                    }
-
-                    let state = app_handle.state::<AppState>();
-                    state.stop_app_blocker();
-                    let blocked_sites = {
-                        let sb = state.site_blocker.lock().unwrap();
-                        sb.get_blocked_sites().clone()
-                    };
-                    thread::spawn(move || {
-                        let blocker = SiteBlocker::new(blocked_sites);
-                        if let Err(e) = blocker.unblock_sites() {
-                            eprintln!("自然结束后解除屏蔽失败: {}", e);
-                        }
-                    });
-
+                    finish_naturally(&app_handle, &timer_running);
                     break;
                 }
             }
@@ -110,21 +126,105 @@ fn start_timer_thread(
     });
 }
 
+/// 自然结束清理：删除会话、关闭覆盖层、停止拦截并解除网站屏蔽
+fn finish_naturally(app_handle: &AppHandle, timer_running: &AtomicBool) {
+    timer_running.store(false, Ordering::SeqCst);
+    FocusSession::delete();
+
+    if let Some(overlay) = app_handle.get_webview_window("overlay") {
+        let _ = overlay.destroy();
+    }
+
+    let state = app_handle.state::<AppState>();
+    *state.scheduled_focus.lock().unwrap() = None;
+    state.stop_app_blocker();
+    state.stop_dns_sinkhole();
+    state.stop_block_watchdog();
+    let site_rules = state.site_blocker.lock().unwrap().rules().clone();
+    state.reset_session_blocklists();
+    thread::spawn(move || {
+        let blocker = SiteBlocker::from_rules(site_rules);
+        if let Err(e) = blocker.unblock_sites() {
+            eprintln!("自然结束后解除屏蔽失败: {}", e);
+        }
+    });
+}
+
+/// 更新会话文件中下一轮的结束时间，保证重启后按新的一轮恢复
+fn save_next_round(work_seconds: u64, break_minutes: u32) {
+    let mut session = match FocusSession::load() {
+        Ok(Some(session)) => session,
+        _ => return,
+    };
+    let now_ts = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    session.state = "working".to_string();
+    session.work_end_time = now_ts + work_seconds;
+    session.break_end_time = session.work_end_time + (break_minutes * 60) as u64;
+    if let Err(e) = session.save() {
+        eprintln!("保存下一轮会话失败: {}", e);
+    }
+}
+
 #[tauri::command]
 pub fn start_focus(
     app_handle: AppHandle,
     state: State<AppState>,
     minutes: Option<u32>,
     seconds: Option<u32>,
+    profile: Option<String>,
+) -> Result<TimerStatus, String> {
+    begin_focus(
+        &app_handle,
+        &state,
+        minutes,
+        seconds,
+        profile,
+        None,
+        EmergencyPolicy::Quota,
+    )
+}
+
+/// 开始专注：屏蔽网站、启动计时线程和 App 拦截（供命令、托盘和定时调度共用）
+///
+/// break_override 为空时使用全局休息时长；emergency 为 Disabled 时本次专注不能紧急取消
+pub fn begin_focus(
+    app_handle: &AppHandle,
+    state: &AppState,
+    minutes: Option<u32>,
+    seconds: Option<u32>,
+    profile: Option<String>,
+    break_override: Option<u32>,
+    emergency: EmergencyPolicy,
 ) -> Result<TimerStatus, String> {
+    // 解析本次使用的屏蔽方案，方案不存在时直接拒绝，不影响当前状态
+    let (blocked_apps, site_rules) = {
+        let config = state.config.lock().map_err(|e| e.to_string())?;
+        let (apps, _) = config
+            .resolve_blocklists(profile.as_deref())
+            .map_err(|e| e.to_string())?;
+        let categories = state.site_categories.lock().map_err(|e| e.to_string())?;
+        let rules = config
+            .site_rules(profile.as_deref(), &categories)
+            .map_err(|e| e.to_string())?;
+        (apps, rules)
+    };
+
     state.stop_timer_thread();
+    // 定时专注由调用方在开始后记下时间段
+    *state.scheduled_focus.lock().unwrap() = None;
 
     let monthly_remaining = {
         let mut config = state.config.lock().map_err(|e| e.to_string())?;
         let work = minutes.unwrap_or(config.pomodoro.work_minutes);
         let extra = seconds.unwrap_or(0);
-        let brk = config.pomodoro.break_minutes;
-        let remaining = config.pomodoro.get_monthly_emergency_remaining();
+        let brk = break_override.unwrap_or(config.pomodoro.break_minutes);
+        let remaining = match emergency {
+            EmergencyPolicy::Quota => config.pomodoro.get_monthly_emergency_remaining(),
+            EmergencyPolicy::Disabled => {
+                config.pomodoro.get_monthly_emergency_remaining();
+                0
+            }
+        };
         // 持久化可能更新的 reset_month
         if let Err(e) = config.save() {
             eprintln!("保存月度重置信息失败: {}", e);
@@ -145,16 +245,35 @@ pub fn start_focus(
         }
     }
 
+    state.set_session_blocklists(profile.clone(), blocked_apps.clone(), site_rules.clone());
+
     // 先屏蔽网站（需要管理员权限，可能弹出密码框）
     // 必须在启动倒计时之前完成，避免密码输入时间被计入专注时长
     {
         let site_blocker = state.site_blocker.lock().map_err(|e| e.to_string())?;
-        site_blocker.block_sites().map_err(|e| {
-            format!("屏蔽网站失败: {}", e)
-        })?;
+        match site_blocker.block_sites() {
+            // 黑名单中解析失败的域名仍有 hosts 屏蔽，白名单中的会被防火墙拦截，只提示不中断
+            Ok(failures) if !failures.is_empty() => {
+                let payload = serde_json::json!({
+                    "allowlist": site_rules.is_allowlist(),
+                    "failures": failures,
+                });
+                let _ = app_handle.emit("site-resolve-failed", payload);
+            }
+            Ok(_) => {}
+            Err(e) => {
+                drop(site_blocker);
+                state.reset_session_blocklists();
+                return Err(format!("屏蔽网站失败: {}", e));
+            }
+        }
     }
 
     let total_seconds = work_minutes * 60 + extra_seconds;
+    let (blocked_sites, allowed_sites) = match site_rules {
+        SiteRules::Blocklist(sites) => (Some(sites), None),
+        SiteRules::Allowlist(sites) => (None, Some(sites)),
+    };
 
     // 持久化会话时间戳，用于重启恢复
     let now_ts = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
@@ -167,6 +286,11 @@ pub fn start_focus(
         work_minutes,
         break_minutes,
         emergency_remaining,
+        profile,
+        blocked_apps: Some(blocked_apps),
+        blocked_sites,
+        allowed_sites,
+        scheduled: None,
     };
     if let Err(e) = session.save() {
         eprintln!("保存会话失败: {}", e);
@@ -195,7 +319,9 @@ pub fn start_focus(
     start_timer_thread(app_handle.clone(), timer_status_clone, stop_signal, pause_signal, Arc::clone(&state.timer_running));
 
     // 启动 App 拦截
-    state.start_app_blocker(app_handle);
+    state.start_app_blocker(app_handle.clone());
+    state.start_dns_sinkhole();
+    state.start_block_watchdog();
 
     let status = state.timer_status.lock().map_err(|e| e.to_string())?;
     Ok(status.clone())
@@ -241,7 +367,13 @@ pub fn resume_focus(state: State<AppState>) -> Result<TimerStatus, String> {
 
 #[tauri::command]
 pub fn stop_focus(app_handle: AppHandle, state: State<AppState>) -> Result<TimerStatus, String> {
+    end_focus(&app_handle, &state)
+}
+
+/// 结束专注并解除所有屏蔽（供命令、托盘和定时调度共用）
+pub fn end_focus(app_handle: &AppHandle, state: &AppState) -> Result<TimerStatus, String> {
     state.stop_timer_thread();
+    *state.scheduled_focus.lock().unwrap() = None;
     FocusSession::delete();
 
     let mut timer_status = state.timer_status.lock().map_err(|e| e.to_string())?;
@@ -254,15 +386,18 @@ pub fn stop_focus(app_handle: AppHandle, state: State<AppState>) -> Result<Timer
 
     // 停止 App 拦截
     state.stop_app_blocker();
+    state.stop_dns_sinkhole();
+    state.stop_block_watchdog();
 
     // 主动广播 idle 状态给所有窗口
     let _ = app_handle.emit("timer-update", timer_status.clone());
 
     // 关闭覆盖窗口
-    close_overlay(&app_handle);
+    close_overlay(app_handle);
 
     // 解除网站屏蔽（后台线程，不阻塞 UI）
-    unblock_sites_async(&state);
+    unblock_sites_async(state);
+    state.reset_session_blocklists();
 
     Ok(timer_status.clone())
 }
@@ -301,6 +436,7 @@ pub fn emergency_cancel(app_handle: AppHandle, state: State<AppState>) -> Result
     }
 
     state.stop_timer_thread();
+    *state.scheduled_focus.lock().unwrap() = None;
     FocusSession::delete();
 
     let mut timer_status = state.timer_status.lock().map_err(|e| e.to_string())?;
@@ -315,6 +451,8 @@ pub fn emergency_cancel(app_handle: AppHandle, state: State<AppState>) -> Result
 
     // 停止 App 拦截
     state.stop_app_blocker();
+    state.stop_dns_sinkhole();
+    state.stop_block_watchdog();
 
     // 主动广播 idle 状态给所有窗口（确保主窗口立即收到）
     let _ = app_handle.emit("timer-update", timer_status.clone());
@@ -324,6 +462,7 @@ pub fn emergency_cancel(app_handle: AppHandle, state: State<AppState>) -> Result
 
     // 解除网站屏蔽（后台线程，不阻塞 UI）
     unblock_sites_async(&state);
+    state.reset_session_blocklists();
 
     Ok(timer_status.clone())
 }
@@ -337,13 +476,10 @@ fn close_overlay(app_handle: &AppHandle) {
 }
 
 /// 在后台线程中解除网站屏蔽，不阻塞 UI
-fn unblock_sites_async(state: &State<AppState>) {
-    let blocked_sites = {
-        let site_blocker = state.site_blocker.lock().unwrap();
-        site_blocker.get_blocked_sites().clone()
-    };
+fn unblock_sites_async(state: &AppState) {
+    let site_rules = state.site_blocker.lock().unwrap().rules().clone();
     thread::spawn(move || {
-        let blocker = SiteBlocker::new(blocked_sites);
+        let blocker = SiteBlocker::from_rules(site_rules);
         if let Err(e) = blocker.unblock_sites() {
             eprintln!("后台解除屏蔽失败: {}", e);
         }
@@ -420,16 +556,36 @@ pub fn restore_focus(app_handle: &AppHandle) {
     state
         .emergency_remaining
         .store(session.emergency_remaining, Ordering::SeqCst);
+    *state.scheduled_focus.lock().unwrap() = session.scheduled;
+
+    // 重新应用开始专注时的黑名单；旧版本会话没有快照，和开始专注时一样按方案和启用的分类重新合并
+    let (blocked_apps, site_rules) = {
+        let config = state.config.lock().unwrap();
+        let site_rules = match (session.allowed_sites.clone(), session.blocked_sites.clone()) {
+            (Some(allowed), _) => SiteRules::Allowlist(allowed),
+            (None, Some(blocked)) => SiteRules::Blocklist(blocked),
+            (None, None) => {
+                let categories = state.site_categories.lock().unwrap();
+                config
+                    .site_rules(session.profile.as_deref(), &categories)
+                    .or_else(|_| config.site_rules(None, &categories))
+                    .unwrap_or_default()
+            }
+        };
+        (
+            session.blocked_apps.clone().unwrap_or_else(|| config.blocked_apps.clone()),
+            site_rules,
+        )
+    };
+    println!("[restore_focus] 屏蔽方案: {:?}", session.profile);
+    state.set_session_blocklists(session.profile.clone(), blocked_apps, site_rules);
 
     // 重新屏蔽网站（异步执行，不阻塞 setup）
     if !SiteBlocker::is_blocking_active() {
         println!("[restore_focus] hosts 中无屏蔽记录，异步重新屏蔽...");
-        let blocked_sites = {
-            let sb = state.site_blocker.lock().unwrap();
-            sb.get_blocked_sites().clone()
-        };
+        let site_rules = state.site_blocker.lock().unwrap().rules().clone();
         thread::spawn(move || {
-            let blocker = SiteBlocker::new(blocked_sites);
+            let blocker = SiteBlocker::from_rules(site_rules);
             if let Err(e) = blocker.block_sites() {
                 eprintln!("[restore_focus] 重新屏蔽网站失败: {}", e);
             }
@@ -455,4 +611,6 @@ pub fn restore_focus(app_handle: &AppHandle) {
 
     // 启动 App 拦截
     state.start_app_blocker(app_handle.clone());
+    state.start_dns_sinkhole();
+    state.start_block_watchdog();
 }
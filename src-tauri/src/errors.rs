@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
+use crate::services::LockViolation;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum AppError {
     ConfigError(String),
@@ -8,6 +10,8 @@ pub enum AppError {
     PermissionDenied(String),
     TimerError(String),
     BlockerError(String),
+    /// 专注进行中，拒绝削弱屏蔽的配置变更
+    CommitmentLocked(Vec<LockViolation>),
 }
 
 impl fmt::Display for AppError {
@@ -18,6 +22,10 @@ impl fmt::Display for AppError {
             AppError::PermissionDenied(msg) => write!(f, "权限不足: {}", msg),
             AppError::TimerError(msg) => write!(f, "计时器错误: {}", msg),
             AppError::BlockerError(msg) => write!(f, "拦截器错误: {}", msg),
+            AppError::CommitmentLocked(violations) => {
+                let details: Vec<&str> = violations.iter().map(|v| v.detail.as_str()).collect();
+                write!(f, "专注进行中，不能削弱屏蔽: {}", details.join(", "))
+            }
         }
     }
 }
@@ -1,34 +1,85 @@
 pub mod commands;
 pub mod errors;
 pub mod models;
+pub mod paths;
 pub mod services;
 pub mod state;
+pub mod storage;
+#[cfg(test)]
+mod test_support;
 
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
-use models::{Config, FocusSession};
-use services::{LocalServer, Scheduler, ServerState, SiteBlocker};
+use chrono::Local;
+use models::{Config, EmergencyPolicy, FocusSession};
+use services::{CalendarWatcher, ConfigWatcher, LocalServer, ServerState, SiteBlocker};
 use state::AppState;
 use tauri::{
     image::Image,
     menu::{Menu, MenuItem},
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
-    AppHandle, Manager, RunEvent, WindowEvent,
+    AppHandle, Emitter, Manager, RunEvent, WindowEvent,
 };
 
+/// 托盘“下次专注”向后查找的天数
+const NEXT_FOCUS_LOOKAHEAD_DAYS: u32 = 7;
+
+/// 托盘中需要随状态刷新文字的菜单项
+struct TrayMenu {
+    mode_item: MenuItem<tauri::Wry>,
+    next_focus_item: MenuItem<tauri::Wry>,
+}
+
+fn mode_text(is_scheduled: bool) -> &'static str {
+    if is_scheduled {
+        "模式：定时"
+    } else {
+        "模式：手动"
+    }
+}
+
+/// “下次专注”菜单文字：定时模式下第一个会自动开始专注的时间段
+fn next_focus_text(state: &AppState) -> String {
+    if !state.is_scheduled_mode() {
+        return "下次专注：手动模式".to_string();
+    }
+    let now = Local::now().naive_local();
+    match commands::next_session_start(state, now, NEXT_FOCUS_LOOKAHEAD_DAYS) {
+        Some(start) => format!("下次专注：{}", start.format("%m-%d %H:%M")),
+        None => format!("下次专注：{} 天内无", NEXT_FOCUS_LOOKAHEAD_DAYS),
+    }
+}
+
 fn setup_tray(app: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
+    let state = app.state::<AppState>();
+    let is_scheduled = state.is_scheduled_mode();
+
     let show_item = MenuItem::with_id(app, "show", "打开主界面", true, None::<&str>)?;
     let start_item = MenuItem::with_id(app, "start", "开始专注", true, None::<&str>)?;
     let stop_item = MenuItem::with_id(app, "stop", "停止专注", true, None::<&str>)?;
-    let mode_item = MenuItem::with_id(app, "mode", "模式：手动", true, None::<&str>)?;
+    let mode_item = MenuItem::with_id(app, "mode", mode_text(is_scheduled), true, None::<&str>)?;
+    let next_focus_item =
+        MenuItem::with_id(app, "next_focus", next_focus_text(&state), false, None::<&str>)?;
     let quit_item = MenuItem::with_id(app, "quit", "退出", true, None::<&str>)?;
 
     let menu = Menu::with_items(
         app,
-        &[&show_item, &start_item, &stop_item, &mode_item, &quit_item],
+        &[
+            &show_item,
+            &start_item,
+            &stop_item,
+            &mode_item,
+            &next_focus_item,
+            &quit_item,
+        ],
     )?;
 
+    app.manage(TrayMenu {
+        mode_item,
+        next_focus_item,
+    });
+
     let icon = Image::from_path("icons/32x32.png").unwrap_or_else(|_| {
         Image::from_bytes(include_bytes!("../icons/32x32.png")).expect("Failed to load tray icon")
     });
@@ -49,27 +100,42 @@ fn setup_tray(app: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
                 }
                 "start" => {
                     if !state.timer_running.load(Ordering::SeqCst) {
-                        // 通过前端调用 start_focus
-                        if let Some(window) = app.get_webview_window("main") {
-                            let _ = window.eval("window.__TAURI__.core.invoke('start_focus')");
+                        match commands::begin_focus(
+                            app,
+                            &state,
+                            None,
+                            None,
+                            None,
+                            None,
+                            EmergencyPolicy::Quota,
+                        ) {
+                            Ok(status) => {
+                                let _ = app.emit("timer-update", status);
+                            }
+                            Err(e) => eprintln!("托盘开始专注失败: {}", e),
                         }
                     }
                 }
                 "stop" => {
                     if state.timer_running.load(Ordering::SeqCst) {
-                        // 通过前端调用 stop_focus
-                        if let Some(window) = app.get_webview_window("main") {
-                            let _ = window.eval("window.__TAURI__.core.invoke('stop_focus')");
+                        if let Err(e) = commands::end_focus(app, &state) {
+                            eprintln!("托盘停止专注失败: {}", e);
                         }
                     }
                 }
                 "mode" => {
-                    let current_mode = state.is_scheduled_mode();
-                    let new_mode = if current_mode { "manual" } else { "scheduled" };
-                    state.set_mode(new_mode);
-
-                    // 更新托盘菜单
-                    let _ = update_tray_menu(app);
+                    let new_mode = if state.is_scheduled_mode() {
+                        "manual"
+                    } else {
+                        "scheduled"
+                    };
+                    match commands::switch_mode(app, &state, new_mode) {
+                        Ok(()) => {
+                            let config = state.config.lock().unwrap().clone();
+                            let _ = app.emit("config-changed", config);
+                        }
+                        Err(e) => eprintln!("切换模式失败: {}", e),
+                    }
                 }
                 "quit" => {
                     // 清理并退出
@@ -98,82 +164,31 @@ fn setup_tray(app: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-fn update_tray_menu(app: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
-    let state = app.state::<AppState>();
-    let is_scheduled = state.is_scheduled_mode();
-    let _mode_text = if is_scheduled {
-        "模式：定时 ✓"
-    } else {
-        "模式：手动 ✓"
-    };
-
-    // 托盘菜单会在下次点击时更新
-    println!("模式切换为: {}", if is_scheduled { "定时" } else { "手动" });
-
-    Ok(())
-}
-
-fn start_scheduler_if_needed(app_handle: AppHandle, state: Arc<AppState>) {
-    if !state.is_scheduled_mode() {
+/// 按当前状态刷新托盘菜单文字
+pub fn update_tray_menu(app: &AppHandle) {
+    let Some(tray_menu) = app.try_state::<TrayMenu>() else {
         return;
+    };
+    let state = app.state::<AppState>();
+    let result = tray_menu
+        .mode_item
+        .set_text(mode_text(state.is_scheduled_mode()))
+        .and_then(|_| tray_menu.next_focus_item.set_text(next_focus_text(&state)));
+    if let Err(e) = result {
+        eprintln!("更新托盘菜单失败: {}", e);
     }
-
-    let config = state.config.lock().unwrap();
-    let schedules = config.schedules.clone();
-    drop(config);
-
-    let mut scheduler_thread = state.scheduler_thread.lock().unwrap();
-
-    // 如果已经在运行，先停止
-    if scheduler_thread.running_flag.load(Ordering::SeqCst) {
-        scheduler_thread.running_flag.store(false, Ordering::SeqCst);
-        if let Some(handle) = scheduler_thread.handle.take() {
-            let _ = handle.join();
-        }
-    }
-
-    scheduler_thread.running_flag.store(true, Ordering::SeqCst);
-    let running_flag = Arc::clone(&scheduler_thread.running_flag);
-
-    let state_clone = Arc::clone(&state);
-    let app_handle_clone = app_handle.clone();
-
-    let handle = Scheduler::start_polling(schedules, running_flag, move |is_in_schedule| {
-        let was_active = state_clone.scheduled_focus_active.load(Ordering::SeqCst);
-
-        if is_in_schedule && !was_active {
-            // 进入时间段，自动开始专注
-            println!("定时触发：进入专注时间段");
-            state_clone.scheduled_focus_active.store(true, Ordering::SeqCst);
-
-            // 触发开始专注
-            if let Some(window) = app_handle_clone.get_webview_window("main") {
-                let _ = window.eval("window.__TAURI__.core.invoke('start_focus')");
-            }
-        } else if !is_in_schedule && was_active {
-            // 离开时间段，自动停止专注
-            println!("定时触发：离开专注时间段");
-            state_clone.scheduled_focus_active.store(false, Ordering::SeqCst);
-
-            // 触发停止专注
-            if let Some(window) = app_handle_clone.get_webview_window("main") {
-                let _ = window.eval("window.__TAURI__.core.invoke('stop_focus')");
-            }
-        }
-    });
-
-    scheduler_thread.handle = Some(handle);
-    state.scheduler_running.store(true, Ordering::SeqCst);
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    paths::init_from_args(std::env::args());
+    paths::migrate_legacy_files();
+
     let config = Config::load().unwrap_or_else(|e| {
         eprintln!("加载配置失败: {}, 使用默认配置", e);
         Config::default()
     });
 
-    let is_scheduled_mode = config.mode == "scheduled";
     let app_state = AppState::new(config);
 
     tauri::Builder::default()
@@ -199,8 +214,20 @@ pub fn run() {
             commands::block_sites,
             commands::unblock_sites,
             commands::get_blocked_sites,
+            commands::get_site_categories,
+            commands::import_blocklist,
             commands::get_installed_apps,
             commands::get_app_icon,
+            commands::export_config_bundle,
+            commands::preview_config_bundle,
+            commands::import_config_bundle,
+            commands::set_mode,
+            commands::import_holidays_ics,
+            commands::get_upcoming_schedules,
+            commands::get_pending_catch_up,
+            commands::resolve_catch_up,
+            commands::get_history,
+            commands::get_history_stats,
         ])
         .setup(move |app| {
             // 启动时检查并清理残留的网站屏蔽（仅在没有活跃会话时清理）
@@ -220,19 +247,21 @@ pub fn run() {
             let state = app.state::<AppState>();
             let server_state = Arc::new(ServerState {
                 timer_running: Arc::clone(&state.timer_running),
-                config: Arc::clone(&state.config),
+                site_blocker: Arc::clone(&state.site_blocker),
             });
             LocalServer::start(server_state);
 
+            // 监听 config.json 的外部修改并热加载
+            ConfigWatcher::start(app.handle().clone());
+
+            // 监听日历来源的 .ics 文件，变化后重新解析专注时间段
+            CalendarWatcher::start(app.handle().clone());
+
             // 检查是否有未完成的专注会话，恢复计时
             commands::restore_focus(app.handle());
 
-            // 如果是定时模式，启动调度器
-            // 注意：定时调度功能暂时禁用，需要重构
-            // if is_scheduled_mode {
-            //     let app_handle = app.handle().clone();
-            //     start_scheduler_if_needed(app_handle, state);
-            // }
+            // 如果是定时模式，记录错过的时间段、处理当前所在的时间段并启动调度器
+            commands::launch_scheduler(app.handle());
 
             app.handle().plugin(tauri_plugin_dialog::init())?;
 
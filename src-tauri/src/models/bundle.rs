@@ -0,0 +1,533 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::AppError;
+use crate::models::{Config, Schedule};
+use crate::services::{AppBlocker, Scheduler, SiteBlocker, SiteRule};
+
+pub const BUNDLE_FORMAT: &str = "pomodoro-focus-bundle";
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// 可导出/导入的配置分区
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BundleSection {
+    BlockedSites,
+    BlockedApps,
+    Schedules,
+    Presets,
+}
+
+/// 每个分区的导入方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    /// 与现有条目合并去重
+    #[default]
+    Merge,
+    /// 用 bundle 中的内容覆盖
+    Replace,
+    /// 不导入该分区
+    Skip,
+}
+
+/// 番茄钟节奏预设
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PomodoroPreset {
+    pub work_minutes: u32,
+    pub break_minutes: u32,
+    pub emergency_cancel_limit: u32,
+}
+
+/// 可分享的配置包（Config 的子集 + 版本信息）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigBundle {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocked_sites: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocked_apps: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedules: Option<Vec<Schedule>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub presets: Option<PomodoroPreset>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BundleImportOptions {
+    #[serde(default)]
+    pub blocked_sites: ImportMode,
+    #[serde(default)]
+    pub blocked_apps: ImportMode,
+    #[serde(default)]
+    pub schedules: ImportMode,
+    #[serde(default)]
+    pub presets: ImportMode,
+}
+
+/// 单个分区的变更预览
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SectionPreview {
+    pub section: BundleSection,
+    pub mode: ImportMode,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub unchanged: usize,
+}
+
+/// 校验未通过、导入时会被丢弃的条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvalidEntry {
+    pub section: BundleSection,
+    pub value: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BundlePreview {
+    pub sections: Vec<SectionPreview>,
+    pub invalid: Vec<InvalidEntry>,
+}
+
+impl ConfigBundle {
+    /// 从当前配置中导出指定分区
+    pub fn from_config(config: &Config, sections: &[BundleSection]) -> Self {
+        let has = |section: BundleSection| sections.contains(&section);
+
+        ConfigBundle {
+            format: BUNDLE_FORMAT.to_string(),
+            version: BUNDLE_VERSION,
+            exported_at: Local::now().to_rfc3339(),
+            blocked_sites: has(BundleSection::BlockedSites).then(|| config.blocked_sites.clone()),
+            blocked_apps: has(BundleSection::BlockedApps).then(|| config.blocked_apps.clone()),
+            schedules: has(BundleSection::Schedules).then(|| config.schedules.clone()),
+            presets: has(BundleSection::Presets).then_some(PomodoroPreset {
+                work_minutes: config.pomodoro.work_minutes,
+                break_minutes: config.pomodoro.break_minutes,
+                emergency_cancel_limit: config.pomodoro.emergency_cancel_limit,
+            }),
+        }
+    }
+
+    /// 解析 bundle 文件内容并检查格式与版本
+    pub fn parse(content: &str) -> Result<Self, AppError> {
+        let bundle: ConfigBundle = serde_json::from_str(content)?;
+
+        if bundle.format != BUNDLE_FORMAT {
+            return Err(AppError::ConfigError(format!(
+                "不是有效的配置包: {}",
+                bundle.format
+            )));
+        }
+        if bundle.version > BUNDLE_VERSION {
+            return Err(AppError::ConfigError(format!(
+                "配置包版本过新: v{}（当前支持 v{}）",
+                bundle.version, BUNDLE_VERSION
+            )));
+        }
+
+        Ok(bundle)
+    }
+
+    /// 计算导入后的配置和变更预览；校验不通过的条目不会进入结果配置
+    pub fn plan_import(
+        &self,
+        current: &Config,
+        options: &BundleImportOptions,
+    ) -> (Config, BundlePreview) {
+        let mut config = current.clone();
+        let mut preview = BundlePreview::default();
+
+        // 跳过的分区不做校验，也不出现在预览中
+        if let Some(sites) = self
+            .blocked_sites
+            .as_ref()
+            .filter(|_| options.blocked_sites != ImportMode::Skip)
+        {
+            let valid = Self::validate_sites(sites, &mut preview.invalid);
+            if let Some(section) = Self::plan_list(
+                BundleSection::BlockedSites,
+                options.blocked_sites,
+                &mut config.blocked_sites,
+                valid,
+            ) {
+                preview.sections.push(section);
+            }
+        }
+
+        if let Some(apps) = self
+            .blocked_apps
+            .as_ref()
+            .filter(|_| options.blocked_apps != ImportMode::Skip)
+        {
+            let valid = Self::validate_apps(apps, &mut preview.invalid);
+            if let Some(section) = Self::plan_list(
+                BundleSection::BlockedApps,
+                options.blocked_apps,
+                &mut config.blocked_apps,
+                valid,
+            ) {
+                preview.sections.push(section);
+            }
+        }
+
+        if let Some(schedules) = self
+            .schedules
+            .as_ref()
+            .filter(|_| options.schedules != ImportMode::Skip)
+        {
+            let valid = Self::validate_schedules(schedules, &config, &mut preview.invalid);
+            if let Some(section) =
+                Self::plan_schedules(options.schedules, &mut config.schedules, valid)
+            {
+                preview.sections.push(section);
+            }
+        }
+
+        if let Some(preset) = self
+            .presets
+            .as_ref()
+            .filter(|_| options.presets != ImportMode::Skip)
+        {
+            match Self::validate_preset(preset) {
+                Ok(()) => {
+                    let before = Self::describe_preset(&PomodoroPreset {
+                        work_minutes: config.pomodoro.work_minutes,
+                        break_minutes: config.pomodoro.break_minutes,
+                        emergency_cancel_limit: config.pomodoro.emergency_cancel_limit,
+                    });
+                    let after = Self::describe_preset(preset);
+
+                    config.pomodoro.work_minutes = preset.work_minutes;
+                    config.pomodoro.break_minutes = preset.break_minutes;
+                    config.pomodoro.emergency_cancel_limit = preset.emergency_cancel_limit;
+
+                    let changed = before != after;
+                    preview.sections.push(SectionPreview {
+                        section: BundleSection::Presets,
+                        // 预设只有一份，合并等同于覆盖
+                        mode: ImportMode::Replace,
+                        added: if changed { vec![after] } else { Vec::new() },
+                        removed: if changed { vec![before] } else { Vec::new() },
+                        unchanged: usize::from(!changed),
+                    });
+                }
+                Err(reason) => preview.invalid.push(InvalidEntry {
+                    section: BundleSection::Presets,
+                    value: Self::describe_preset(preset),
+                    reason,
+                }),
+            }
+        }
+
+        (config, preview)
+    }
+
+    fn plan_list(
+        section: BundleSection,
+        mode: ImportMode,
+        target: &mut Vec<String>,
+        incoming: Vec<String>,
+    ) -> Option<SectionPreview> {
+        let merged = match mode {
+            ImportMode::Skip => return None,
+            ImportMode::Replace => incoming,
+            ImportMode::Merge => {
+                let mut merged = target.clone();
+                for entry in incoming {
+                    if !merged.contains(&entry) {
+                        merged.push(entry);
+                    }
+                }
+                merged
+            }
+        };
+
+        let added: Vec<String> = merged
+            .iter()
+            .filter(|e| !target.contains(e))
+            .cloned()
+            .collect();
+        let removed: Vec<String> = target
+            .iter()
+            .filter(|e| !merged.contains(e))
+            .cloned()
+            .collect();
+        let unchanged = merged.len() - added.len();
+
+        *target = merged;
+        Some(SectionPreview {
+            section,
+            mode,
+            added,
+            removed,
+            unchanged,
+        })
+    }
+
+    fn plan_schedules(
+        mode: ImportMode,
+        target: &mut Vec<Schedule>,
+        incoming: Vec<Schedule>,
+    ) -> Option<SectionPreview> {
+        // 比较整个时间段：时间相同但星期、日期范围或节奏不同的是不同的时间段
+        let same = |a: &Schedule, b: &Schedule| a == b;
+
+        let merged = match mode {
+            ImportMode::Skip => return None,
+            ImportMode::Replace => incoming,
+            ImportMode::Merge => {
+                let mut merged = target.clone();
+                for schedule in incoming {
+                    if !merged.iter().any(|s| same(s, &schedule)) {
+                        merged.push(schedule);
+                    }
+                }
+                merged
+            }
+        };
+
+        let added: Vec<String> = merged
+            .iter()
+            .filter(|s| !target.iter().any(|t| same(t, s)))
+            .map(Self::describe_schedule)
+            .collect();
+        let removed: Vec<String> = target
+            .iter()
+            .filter(|t| !merged.iter().any(|s| same(s, t)))
+            .map(Self::describe_schedule)
+            .collect();
+        let unchanged = merged.len() - added.len();
+
+        *target = merged;
+        Some(SectionPreview {
+            section: BundleSection::Schedules,
+            mode,
+            added,
+            removed,
+            unchanged,
+        })
+    }
+
+    fn validate_sites(sites: &[String], invalid: &mut Vec<InvalidEntry>) -> Vec<String> {
+        let mut valid = Vec::new();
+        for site in sites {
+            // 保留路径和查询条件，只校验主机名部分
+            let rule = SiteRule::parse(site);
+            let domain = rule.as_ref().map(|r| r.to_string()).unwrap_or_default();
+            if !rule.is_some_and(|r| SiteBlocker::is_valid_domain(&r.host)) {
+                invalid.push(InvalidEntry {
+                    section: BundleSection::BlockedSites,
+                    value: site.clone(),
+                    reason: "无效的域名".to_string(),
+                });
+            } else if !valid.contains(&domain) {
+                valid.push(domain);
+            }
+        }
+        valid
+    }
+
+    fn validate_apps(apps: &[String], invalid: &mut Vec<InvalidEntry>) -> Vec<String> {
+        let mut valid = Vec::new();
+        for app in apps {
+            let name = app.trim();
+            let reason = if name.is_empty() {
+                Some("应用名为空")
+            } else if AppBlocker::is_system_protected(name) {
+                Some("系统保护进程，不能拦截")
+            } else {
+                None
+            };
+
+            match reason {
+                Some(reason) => invalid.push(InvalidEntry {
+                    section: BundleSection::BlockedApps,
+                    value: app.clone(),
+                    reason: reason.to_string(),
+                }),
+                None => {
+                    if !valid.iter().any(|v: &String| v == name) {
+                        valid.push(name.to_string());
+                    }
+                }
+            }
+        }
+        valid
+    }
+
+    /// 时间段引用的屏蔽方案必须在本机已存在（配置包不包含屏蔽方案）
+    fn validate_schedules(
+        schedules: &[Schedule],
+        config: &Config,
+        invalid: &mut Vec<InvalidEntry>,
+    ) -> Vec<Schedule> {
+        let mut valid = Vec::new();
+        for schedule in schedules {
+            let result = Scheduler::validate_schedule(schedule).and_then(|()| {
+                match &schedule.profile {
+                    Some(name) if config.find_profile(name).is_none() => Err(
+                        AppError::ConfigError(format!("屏蔽方案不存在: {}", name)),
+                    ),
+                    _ => Ok(()),
+                }
+            });
+            match result {
+                Ok(()) => valid.push(schedule.clone()),
+                Err(e) => invalid.push(InvalidEntry {
+                    section: BundleSection::Schedules,
+                    value: Self::describe_schedule(schedule),
+                    reason: e.to_string(),
+                }),
+            }
+        }
+        valid
+    }
+
+    fn validate_preset(preset: &PomodoroPreset) -> Result<(), String> {
+        if !(1..=180).contains(&preset.work_minutes) {
+            return Err(format!("专注时长超出范围: {} 分钟", preset.work_minutes));
+        }
+        if preset.break_minutes > 60 {
+            return Err(format!("休息时长超出范围: {} 分钟", preset.break_minutes));
+        }
+        if preset.emergency_cancel_limit > 10 {
+            return Err(format!(
+                "紧急取消次数超出范围: {}",
+                preset.emergency_cancel_limit
+            ));
+        }
+        Ok(())
+    }
+
+    fn describe_schedule(schedule: &Schedule) -> String {
+        let mut text = format!("{}-{}", schedule.start, schedule.end);
+        if schedule.weekdays.len() < 7 {
+            let days: Vec<String> = schedule.weekdays.iter().map(|d| d.to_string()).collect();
+            text.push_str(&format!(" 星期 {}", days.join(",")));
+        }
+        if schedule.valid_from.is_some() || schedule.valid_until.is_some() {
+            text.push_str(&format!(
+                " {}~{}",
+                schedule.valid_from.as_deref().unwrap_or(""),
+                schedule.valid_until.as_deref().unwrap_or("")
+            ));
+        }
+        text
+    }
+
+    fn describe_preset(preset: &PomodoroPreset) -> String {
+        format!(
+            "专注 {} 分钟 / 休息 {} 分钟 / 紧急取消 {} 次",
+            preset.work_minutes, preset.break_minutes, preset.emergency_cancel_limit
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundle_with_sites(sites: &[&str]) -> ConfigBundle {
+        let mut bundle = ConfigBundle::from_config(&Config::default(), &[]);
+        bundle.blocked_sites = Some(sites.iter().map(|s| s.to_string()).collect());
+        bundle
+    }
+
+    #[test]
+    fn test_merge_and_replace_sites() {
+        let current = Config {
+            blocked_sites: vec!["a.com".to_string(), "b.com".to_string()],
+            ..Default::default()
+        };
+        let bundle = bundle_with_sites(&["https://B.com/", "c.com/Shorts/", "not a domain"]);
+
+        let merge = BundleImportOptions::default();
+        let (merged, preview) = bundle.plan_import(&current, &merge);
+        assert_eq!(merged.blocked_sites, vec!["a.com", "b.com", "c.com/shorts"]);
+        assert_eq!(preview.sections[0].added, vec!["c.com/shorts"]);
+        assert!(preview.sections[0].removed.is_empty());
+        assert_eq!(preview.invalid.len(), 1);
+        assert_eq!(preview.invalid[0].value, "not a domain");
+
+        let replace = BundleImportOptions {
+            blocked_sites: ImportMode::Replace,
+            ..Default::default()
+        };
+        let (replaced, preview) = bundle.plan_import(&current, &replace);
+        assert_eq!(replaced.blocked_sites, vec!["b.com", "c.com/shorts"]);
+        assert_eq!(preview.sections[0].removed, vec!["a.com"]);
+    }
+
+    #[test]
+    fn test_skip_and_invalid_entries() {
+        let current = Config::default();
+        let mut bundle = ConfigBundle::from_config(&current, &[]);
+        bundle.blocked_apps = Some(vec!["Finder".to_string(), "Steam".to_string()]);
+        bundle.schedules = Some(vec![Schedule {
+            enabled: true,
+            start: "25:00".to_string(),
+            end: "26:00".to_string(),
+            weekdays: crate::models::default_weekdays(),
+            valid_from: None,
+            valid_until: None,
+            work_minutes: None,
+            break_minutes: None,
+            profile: None,
+            emergency: crate::models::EmergencyPolicy::Quota,
+        }]);
+
+        let options = BundleImportOptions {
+            blocked_apps: ImportMode::Skip,
+            ..Default::default()
+        };
+        let (config, preview) = bundle.plan_import(&current, &options);
+        assert_eq!(config.blocked_apps, current.blocked_apps);
+        assert_eq!(config.schedules.len(), current.schedules.len());
+        assert_eq!(preview.invalid.len(), 1);
+        assert_eq!(preview.invalid[0].section, BundleSection::Schedules);
+    }
+
+    #[test]
+    fn test_schedule_with_unknown_profile_is_invalid() {
+        let current = Config::default();
+        let mut bundle = ConfigBundle::from_config(&current, &[BundleSection::Schedules]);
+        let mut schedule = current.schedules[0].clone();
+        schedule.start = "07:00".to_string();
+        schedule.profile = Some("Coding".to_string());
+        bundle.schedules = Some(vec![schedule]);
+
+        let (config, preview) = bundle.plan_import(&current, &BundleImportOptions::default());
+        assert_eq!(config.schedules.len(), current.schedules.len());
+        assert_eq!(preview.invalid.len(), 1);
+        assert!(preview.invalid[0].reason.contains("Coding"));
+    }
+
+    #[test]
+    fn test_merge_keeps_schedules_differing_beyond_times() {
+        let current = Config::default();
+        let mut bundle = ConfigBundle::from_config(&current, &[BundleSection::Schedules]);
+        let mut weekend = current.schedules[0].clone();
+        weekend.weekdays = vec![6, 7];
+        let mut rhythm = current.schedules[0].clone();
+        rhythm.work_minutes = Some(25);
+        bundle.schedules = Some(vec![current.schedules[0].clone(), weekend.clone(), rhythm]);
+
+        let (config, preview) = bundle.plan_import(&current, &BundleImportOptions::default());
+        assert_eq!(config.schedules.len(), current.schedules.len() + 2);
+        assert_eq!(config.schedules[current.schedules.len()], weekend);
+        let section = &preview.sections[0];
+        assert_eq!(section.added.len(), 2);
+        assert!(section.added[0].ends_with("星期 6,7"));
+    }
+
+    #[test]
+    fn test_parse_rejects_newer_version() {
+        let mut bundle = ConfigBundle::from_config(&Config::default(), &[BundleSection::Presets]);
+        bundle.version = BUNDLE_VERSION + 1;
+        let content = serde_json::to_string(&bundle).unwrap();
+        assert!(ConfigBundle::parse(&content).is_err());
+    }
+}
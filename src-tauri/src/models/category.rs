@@ -0,0 +1,426 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::errors::AppError;
+use crate::services::SiteRule;
+use crate::{paths, storage};
+
+/// 分类列表在数据目录下的子目录，每个分类一个 `<id>.json`
+const CATEGORY_DIR: &str = "blocklists";
+/// 每个分类文件保留的历史完好副本数
+const CATEGORY_BACKUP_COUNT: usize = 1;
+
+/// 内置分类：(id, 名称, 版本, 域名)
+///
+/// 修改域名时递增版本，启动时数据目录中版本更旧、且未被用户修改过的文件会被覆盖
+const BUILTIN_CATEGORIES: &[(&str, &str, u32, &[&str])] = &[
+    (
+        "video",
+        "视频",
+        1,
+        &[
+            "bilibili.com",
+            "m.bilibili.com",
+            "douyin.com",
+            "kuaishou.com",
+            "ixigua.com",
+            "iqiyi.com",
+            "youku.com",
+            "v.qq.com",
+            "youtube.com",
+            "tiktok.com",
+            "netflix.com",
+            "twitch.tv",
+            "huya.com",
+            "douyu.com",
+        ],
+    ),
+    (
+        "social",
+        "社交",
+        1,
+        &[
+            "weibo.com",
+            "zhihu.com",
+            "xiaohongshu.com",
+            "douban.com",
+            "tieba.baidu.com",
+            "twitter.com",
+            "x.com",
+            "facebook.com",
+            "instagram.com",
+            "reddit.com",
+        ],
+    ),
+    (
+        "news",
+        "新闻",
+        1,
+        &[
+            "toutiao.com",
+            "thepaper.cn",
+            "news.qq.com",
+            "news.sina.com.cn",
+            "news.163.com",
+            "news.ycombinator.com",
+            "cnn.com",
+            "bbc.com",
+            "nytimes.com",
+        ],
+    ),
+    (
+        "gaming",
+        "游戏",
+        1,
+        &[
+            "steampowered.com",
+            "steamcommunity.com",
+            "epicgames.com",
+            "taptap.cn",
+            "4399.com",
+            "7k7k.com",
+            "nga.cn",
+            "gamersky.com",
+            "ign.com",
+        ],
+    ),
+    (
+        "shopping",
+        "购物",
+        1,
+        &[
+            "taobao.com",
+            "tmall.com",
+            "jd.com",
+            "pinduoduo.com",
+            "amazon.com",
+        ],
+    ),
+    (
+        "adult",
+        "成人",
+        1,
+        &[
+            "pornhub.com",
+            "xvideos.com",
+            "xhamster.com",
+            "xnxx.com",
+            "91porn.com",
+            "javdb.com",
+            "missav.com",
+        ],
+    ),
+];
+
+/// 新配置默认启用的分类
+pub const DEFAULT_ENABLED_CATEGORIES: &[&str] = &["video", "adult"];
+
+/// 一个网站分类列表，保存在数据目录的 `blocklists/<id>.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SiteCategory {
+    /// 与文件名相同
+    pub id: String,
+    pub name: String,
+    /// 内置列表的版本，据此判断是否需要更新文件；自定义分类为 0
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub builtin: bool,
+    /// 用户在应用中修改过，内置分类不再随新版本覆盖
+    #[serde(default)]
+    pub modified: bool,
+    #[serde(default)]
+    pub sites: Vec<String>,
+}
+
+impl SiteCategory {
+    pub fn category_dir() -> Result<PathBuf, AppError> {
+        Ok(paths::data_dir()?.join(CATEGORY_DIR))
+    }
+
+    fn path_in(dir: &Path, id: &str) -> PathBuf {
+        dir.join(format!("{}.json", id))
+    }
+
+    /// 分类 id 只允许小写字母、数字、`-` 和 `_`，同时用作文件名
+    pub fn is_valid_id(id: &str) -> bool {
+        !id.is_empty()
+            && id.len() <= 64
+            && id
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+    }
+
+    fn builtin() -> Vec<SiteCategory> {
+        BUILTIN_CATEGORIES
+            .iter()
+            .map(|(id, name, version, sites)| SiteCategory {
+                id: id.to_string(),
+                name: name.to_string(),
+                version: *version,
+                builtin: true,
+                modified: false,
+                sites: sites.iter().map(|s| s.to_string()).collect(),
+            })
+            .collect()
+    }
+
+    /// 读取所有分类：内置分类在前（按内置顺序），其余按 id 排序
+    pub fn load_all() -> Result<Vec<SiteCategory>, AppError> {
+        Self::load_from(&Self::category_dir()?)
+    }
+
+    fn load_from(dir: &Path) -> Result<Vec<SiteCategory>, AppError> {
+        fs::create_dir_all(dir)?;
+        Self::install_builtin(dir)?;
+
+        let mut categories = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(id) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            if !Self::is_valid_id(id) {
+                continue;
+            }
+            match storage::load_json::<SiteCategory>(&path, CATEGORY_BACKUP_COUNT) {
+                Ok(Some(mut category)) => {
+                    category.id = id.to_string();
+                    categories.push(category);
+                }
+                Ok(None) => {}
+                Err(e) => eprintln!("[SiteCategory] 读取 {} 失败: {}", path.display(), e),
+            }
+        }
+
+        let order: Vec<&str> = BUILTIN_CATEGORIES.iter().map(|(id, ..)| *id).collect();
+        categories.sort_by_key(|c| {
+            let index = order.iter().position(|id| *id == c.id);
+            (index.unwrap_or(order.len()), c.id.clone())
+        });
+        Ok(categories)
+    }
+
+    /// 写入缺失或版本较旧的内置分类文件，用户修改过的文件保留不动
+    fn install_builtin(dir: &Path) -> Result<(), AppError> {
+        for builtin in Self::builtin() {
+            let path = Self::path_in(dir, &builtin.id);
+            let installed = storage::load_json::<SiteCategory>(&path, CATEGORY_BACKUP_COUNT)
+                .ok()
+                .flatten();
+            if installed.is_some_and(|c| c.modified || c.version >= builtin.version) {
+                continue;
+            }
+            println!(
+                "[SiteCategory] 写入内置分类 {} v{}",
+                builtin.id, builtin.version
+            );
+            storage::save_json(&path, &builtin, CATEGORY_BACKUP_COUNT)?;
+        }
+        Ok(())
+    }
+
+    /// 新的自定义分类；名称能转成合法 id 时用作文件名，否则使用 `list-N`
+    pub fn new_custom(name: &str, existing: &[SiteCategory]) -> Self {
+        let taken = |id: &str| existing.iter().any(|c| c.id == id);
+        let slug: String = name
+            .trim()
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_whitespace() { '-' } else { c })
+            .collect();
+        let id = if Self::is_valid_id(&slug) && !taken(&slug) {
+            slug
+        } else {
+            (1..)
+                .map(|n| format!("list-{}", n))
+                .find(|id| !taken(id))
+                .unwrap_or_default()
+        };
+        SiteCategory {
+            id,
+            name: name.trim().to_string(),
+            version: 0,
+            builtin: false,
+            modified: false,
+            sites: Vec::new(),
+        }
+    }
+
+    /// 追加条目，按规则跳过已有的条目；返回 (新增数, 重复数)
+    pub fn add_sites(&mut self, sites: Vec<String>) -> (usize, usize) {
+        let mut seen: HashSet<String> = self
+            .sites
+            .iter()
+            .filter_map(|site| SiteRule::parse(site))
+            .map(|rule| rule.to_string())
+            .collect();
+        let (mut added, mut duplicates) = (0, 0);
+        for site in sites {
+            let Some(rule) = SiteRule::parse(&site) else {
+                continue;
+            };
+            if seen.insert(rule.to_string()) {
+                self.sites.push(site);
+                added += 1;
+            } else {
+                duplicates += 1;
+            }
+        }
+        (added, duplicates)
+    }
+
+    /// 保存到数据目录并标记为用户修改过
+    pub fn save(&mut self) -> Result<(), AppError> {
+        self.save_in(&Self::category_dir()?)
+    }
+
+    fn save_in(&mut self, dir: &Path) -> Result<(), AppError> {
+        if !Self::is_valid_id(&self.id) {
+            return Err(AppError::ConfigError(format!("无效的分类 id: {}", self.id)));
+        }
+        self.modified = true;
+        storage::save_json(&Self::path_in(dir, &self.id), self, CATEGORY_BACKUP_COUNT)
+    }
+
+    /// 生效的网站列表：用户条目在前，再依次加入启用的分类，按规则去重
+    ///
+    /// 写法不同但规则相同的条目（如 `https://a.com/` 和 `a.com`）只保留第一个
+    pub fn merge_sites(
+        user_sites: &[String],
+        categories: &[SiteCategory],
+        enabled: &[String],
+    ) -> Vec<String> {
+        let category_sites = categories
+            .iter()
+            .filter(|c| enabled.contains(&c.id))
+            .flat_map(|c| c.sites.iter());
+
+        // 导入的列表可能有数万条，用 HashSet 去重
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut merged = Vec::new();
+        for site in user_sites.iter().chain(category_sites) {
+            let Some(rule) = SiteRule::parse(site) else {
+                continue;
+            };
+            if seen.insert(rule.to_string()) {
+                merged.push(site.clone());
+            }
+        }
+        merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn test_builtin_files_installed_and_upgraded() {
+        let dir = temp_dir("category", "install");
+        let categories = SiteCategory::load_from(&dir).unwrap();
+        let ids: Vec<&str> = categories.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec!["video", "social", "news", "gaming", "shopping", "adult"]
+        );
+        assert!(dir.join("video.json").exists());
+
+        // 旧版本的内置文件被覆盖
+        let mut stale = categories[0].clone();
+        stale.version = 0;
+        stale.sites = vec!["old.com".to_string()];
+        storage::save_json(&dir.join("video.json"), &stale, 0).unwrap();
+        let reloaded = SiteCategory::load_from(&dir).unwrap();
+        assert_eq!(reloaded[0], categories[0]);
+
+        // 在应用中修改过的文件保留，内置版本不变，之后内置列表升级也不覆盖
+        let mut edited = categories[1].clone();
+        edited.sites.push("mastodon.social".to_string());
+        edited.save_in(&dir).unwrap();
+        assert_eq!(edited.version, categories[1].version);
+        let mut older = edited.clone();
+        older.version = 0;
+        storage::save_json(&dir.join("social.json"), &older, 0).unwrap();
+        let reloaded = SiteCategory::load_from(&dir).unwrap();
+        assert!(reloaded[1].modified);
+        assert!(reloaded[1].sites.contains(&"mastodon.social".to_string()));
+
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_custom_categories_follow_builtin() {
+        let dir = temp_dir("category", "custom");
+        let mut custom = SiteCategory {
+            id: "forums".to_string(),
+            name: "论坛".to_string(),
+            version: 0,
+            builtin: false,
+            modified: false,
+            sites: vec!["v2ex.com".to_string()],
+        };
+        custom.save_in(&dir).unwrap();
+        assert_eq!(custom.version, 0);
+        assert!(custom.modified);
+        fs::write(dir.join("broken.json"), "{").unwrap();
+
+        let categories = SiteCategory::load_from(&dir).unwrap();
+        assert_eq!(categories.last(), Some(&custom));
+        assert!(!categories.iter().any(|c| c.id == "broken"));
+
+        custom.id = "../escape".to_string();
+        assert!(custom.save_in(&dir).is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_new_custom_and_add_sites() {
+        let existing = SiteCategory::builtin();
+        let mut list = SiteCategory::new_custom(" Ad Servers ", &existing);
+        assert_eq!(list.id, "ad-servers");
+        assert_eq!(list.name, "Ad Servers");
+        assert_eq!(SiteCategory::new_custom("视频", &existing).id, "list-1");
+        assert_eq!(SiteCategory::new_custom("video", &existing).id, "list-1");
+
+        assert_eq!(
+            list.add_sites(vec!["a.com".to_string(), "b.com".to_string()]),
+            (2, 0)
+        );
+        assert_eq!(
+            list.add_sites(vec!["https://A.com/".to_string(), "c.com".to_string()]),
+            (1, 1)
+        );
+        assert_eq!(list.sites, vec!["a.com", "b.com", "c.com"]);
+    }
+
+    #[test]
+    fn test_merge_sites_unions_enabled_categories() {
+        let categories = SiteCategory::builtin();
+        let user = vec!["https://YouTube.com/".to_string(), "v2ex.com".to_string()];
+        let enabled = vec!["video".to_string(), "missing".to_string()];
+
+        let merged = SiteCategory::merge_sites(&user, &categories, &enabled);
+        assert_eq!(
+            &merged[..3],
+            &["https://YouTube.com/", "v2ex.com", "bilibili.com"]
+        );
+        assert_eq!(
+            merged
+                .iter()
+                .filter(|s| s.to_lowercase().contains("youtube"))
+                .count(),
+            1
+        );
+        assert!(!merged.contains(&"pornhub.com".to_string()));
+        assert_eq!(merged.len(), 2 + categories[0].sites.len() - 1);
+    }
+}
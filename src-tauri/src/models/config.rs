@@ -1,9 +1,17 @@
 use chrono::Local;
 use serde::{Deserialize, Serialize};
-use std::fs;
+use std::net::SocketAddr;
 use std::path::PathBuf;
 
 use crate::errors::AppError;
+use crate::models::{SiteCategory, DEFAULT_ENABLED_CATEGORIES};
+use crate::services::{DnsSinkhole, IcsEvent, Scheduler, SiteRules};
+use crate::{paths, storage};
+
+/// config.json 保留的历史完好副本数
+const CONFIG_BACKUP_COUNT: usize = 3;
+/// session.json 保留的历史完好副本数
+const SESSION_BACKUP_COUNT: usize = 1;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -16,6 +24,86 @@ pub struct Config {
     pub play_completion_sound: bool,
     #[serde(default)]
     pub custom_bg_path: Option<String>,
+    #[serde(default)]
+    pub profiles: Vec<BlockProfile>,
+    #[serde(default)]
+    pub schedule_exceptions: Vec<ScheduleException>,
+    #[serde(default)]
+    pub calendar_sources: Vec<CalendarSource>,
+    /// 启动时已处于定时时间段内的处理方式
+    #[serde(default)]
+    pub catch_up: CatchUpPolicy,
+    /// 本地 DNS 转发，按通配子域名屏蔽
+    #[serde(default)]
+    pub dns_sinkhole: DnsSinkholeConfig,
+    /// 网站屏蔽方式：黑名单或白名单
+    #[serde(default)]
+    pub site_mode: SiteMode,
+    /// 白名单模式下专注期间允许访问的网站
+    #[serde(default)]
+    pub allowed_sites: Vec<String>,
+    /// 启用的网站分类 id，黑名单模式下与全局 blocked_sites 合并生效
+    #[serde(default)]
+    pub enabled_categories: Vec<String>,
+}
+
+/// 网站屏蔽方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SiteMode {
+    /// 只屏蔽黑名单（及屏蔽方案）中的网站
+    #[default]
+    Blocklist,
+    /// 只允许访问白名单中的网站，其余全部屏蔽
+    Allowlist,
+}
+
+/// 启用后在专注期间运行的本地 DNS 转发，按网站规则屏蔽；系统 DNS 需手动指向 listen 地址
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DnsSinkholeConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 监听地址，只允许本机回环地址
+    #[serde(default = "default_dns_listen")]
+    pub listen: String,
+    /// 上游 DNS（IP 或 IP:端口），为空时读取 /etc/resolv.conf
+    #[serde(default)]
+    pub upstream: Option<String>,
+}
+
+fn default_dns_listen() -> String {
+    "127.0.0.1:53".to_string()
+}
+
+impl Default for DnsSinkholeConfig {
+    fn default() -> Self {
+        DnsSinkholeConfig {
+            enabled: false,
+            listen: default_dns_listen(),
+            upstream: None,
+        }
+    }
+}
+
+/// 应用启动时已处于定时时间段内的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CatchUpPolicy {
+    /// 直接加入，专注到时间段结束
+    #[default]
+    Join,
+    /// 跳过本次时间段，等待下一个
+    Skip,
+    /// 询问是否加入
+    Prompt,
+}
+
+/// 命名的屏蔽方案，每个方案有独立的 App 和网站黑名单
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockProfile {
+    pub name: String,
+    pub blocked_apps: Vec<String>,
+    pub blocked_sites: Vec<String>,
 }
 
 fn default_play_completion_sound() -> bool {
@@ -55,11 +143,122 @@ impl PomodoroConfig {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Schedule {
     pub enabled: bool,
     pub start: String,
     pub end: String,
+    /// 生效的星期（1 = 周一 … 7 = 周日），旧配置缺省为每天
+    #[serde(default = "default_weekdays")]
+    pub weekdays: Vec<u32>,
+    /// 生效的起始日期（YYYY-MM-DD，含当天），为空表示不限
+    #[serde(default)]
+    pub valid_from: Option<String>,
+    /// 生效的截止日期（YYYY-MM-DD，含当天），为空表示不限
+    #[serde(default)]
+    pub valid_until: Option<String>,
+    /// 该时间段的专注时长（分钟）；设置后按“专注 + 休息”循环到时间段结束，为空时一次专注到结束
+    #[serde(default)]
+    pub work_minutes: Option<u32>,
+    /// 该时间段的休息时长（分钟），为空时使用全局设置
+    #[serde(default)]
+    pub break_minutes: Option<u32>,
+    /// 该时间段使用的屏蔽方案，为空时使用全局黑名单
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// 该时间段内的紧急取消策略
+    #[serde(default)]
+    pub emergency: EmergencyPolicy,
+}
+
+/// 专注期间的紧急取消策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum EmergencyPolicy {
+    /// 按每月剩余次数允许紧急取消
+    #[default]
+    Quota,
+    /// 不允许紧急取消
+    Disabled,
+}
+
+pub fn default_weekdays() -> Vec<u32> {
+    (1..=7).collect()
+}
+
+/// 从本地 .ics 文件读取专注时间段的日历来源
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CalendarSource {
+    pub enabled: bool,
+    /// .ics 文件路径
+    pub path: String,
+    /// 标题包含该文字（不区分大小写）的事件视为专注时间
+    #[serde(default)]
+    pub summary_filter: Option<String>,
+    /// 分类（CATEGORIES）等于该值（不区分大小写）的事件视为专注时间
+    #[serde(default)]
+    pub category_filter: Option<String>,
+}
+
+impl CalendarSource {
+    /// 事件是否命中过滤条件；两个条件都为空时接受所有事件，否则命中任一即可
+    pub fn matches(&self, event: &IcsEvent) -> bool {
+        let summary_filter = self
+            .summary_filter
+            .as_deref()
+            .map(str::trim)
+            .filter(|f| !f.is_empty());
+        let category_filter = self
+            .category_filter
+            .as_deref()
+            .map(str::trim)
+            .filter(|f| !f.is_empty());
+
+        if summary_filter.is_none() && category_filter.is_none() {
+            return true;
+        }
+
+        let summary_matches = summary_filter.is_some_and(|filter| {
+            event.summary.to_lowercase().contains(&filter.to_lowercase())
+        });
+        let category_matches = category_filter.is_some_and(|filter| {
+            event
+                .categories
+                .iter()
+                .any(|c| c.eq_ignore_ascii_case(filter))
+        });
+
+        summary_matches || category_matches
+    }
+}
+
+/// 例外日期的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ExceptionKind {
+    /// 当天不触发任何定时专注（节假日、休假）
+    #[default]
+    Skip,
+    /// 当天额外加开一个时间段
+    Add,
+}
+
+/// 定时计划的例外日期
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduleException {
+    /// 日期（YYYY-MM-DD）
+    pub date: String,
+    #[serde(default)]
+    pub kind: ExceptionKind,
+    /// 加开时间段的开始时间（HH:MM），仅 add 使用
+    #[serde(default)]
+    pub start: Option<String>,
+    /// 加开时间段的结束时间（HH:MM），仅 add 使用
+    #[serde(default)]
+    pub end: Option<String>,
+    /// 备注，例如节日名称
+    #[serde(default)]
+    pub name: Option<String>,
 }
 
 impl Default for Config {
@@ -77,60 +276,67 @@ impl Default for Config {
                 "bilibili".to_string(),
                 "QQ".to_string(),
             ],
-            blocked_sites: vec![
-                "bilibili.com".to_string(),
-                "www.bilibili.com".to_string(),
-                "m.bilibili.com".to_string(),
-                "douyin.com".to_string(),
-                "www.douyin.com".to_string(),
-                "pornhub.com".to_string(),
-                "www.pornhub.com".to_string(),
-                "xvideos.com".to_string(),
-                "www.xvideos.com".to_string(),
-                "xhamster.com".to_string(),
-                "www.xhamster.com".to_string(),
-                "xnxx.com".to_string(),
-                "www.xnxx.com".to_string(),
-                "91porn.com".to_string(),
-                "www.91porn.com".to_string(),
-                "javdb.com".to_string(),
-                "www.javdb.com".to_string(),
-                "missav.com".to_string(),
-                "www.missav.com".to_string(),
-            ],
+            // 常见网站由分类列表提供，这里只放用户自己添加的条目
+            blocked_sites: Vec::new(),
             schedules: vec![
                 Schedule {
                     enabled: true,
                     start: "09:00".to_string(),
                     end: "12:00".to_string(),
+                    weekdays: default_weekdays(),
+                    valid_from: None,
+                    valid_until: None,
+                    work_minutes: None,
+                    break_minutes: None,
+                    profile: None,
+                    emergency: EmergencyPolicy::Quota,
                 },
                 Schedule {
                     enabled: true,
                     start: "14:00".to_string(),
                     end: "17:00".to_string(),
+                    weekdays: default_weekdays(),
+                    valid_from: None,
+                    valid_until: None,
+                    work_minutes: None,
+                    break_minutes: None,
+                    profile: None,
+                    emergency: EmergencyPolicy::Quota,
                 },
                 Schedule {
                     enabled: false,
                     start: "19:00".to_string(),
                     end: "22:00".to_string(),
+                    weekdays: default_weekdays(),
+                    valid_from: None,
+                    valid_until: None,
+                    work_minutes: None,
+                    break_minutes: None,
+                    profile: None,
+                    emergency: EmergencyPolicy::Quota,
                 },
             ],
             mode: "manual".to_string(),
             play_completion_sound: true,
             custom_bg_path: None,
+            profiles: Vec::new(),
+            schedule_exceptions: Vec::new(),
+            calendar_sources: Vec::new(),
+            catch_up: CatchUpPolicy::Join,
+            dns_sinkhole: DnsSinkholeConfig::default(),
+            site_mode: SiteMode::Blocklist,
+            allowed_sites: Vec::new(),
+            enabled_categories: DEFAULT_ENABLED_CATEGORIES
+                .iter()
+                .map(|id| id.to_string())
+                .collect(),
         }
     }
 }
 
 impl Config {
     pub fn config_dir() -> Result<PathBuf, AppError> {
-        let home = dirs::home_dir()
-            .ok_or_else(|| AppError::ConfigError("无法获取用户目录".to_string()))?;
-        let config_dir = home
-            .join("Library")
-            .join("Application Support")
-            .join("pomodoro-focus");
-        Ok(config_dir)
+        paths::config_dir()
     }
 
     pub fn config_path() -> Result<PathBuf, AppError> {
@@ -140,28 +346,145 @@ impl Config {
     pub fn load() -> Result<Self, AppError> {
         let config_path = Self::config_path()?;
 
-        if !config_path.exists() {
-            let config = Config::default();
-            config.save()?;
-            return Ok(config);
+        match storage::load_json(&config_path, CONFIG_BACKUP_COUNT)? {
+            Some(config) => Ok(config),
+            None => {
+                let config = Config::default();
+                config.save()?;
+                Ok(config)
+            }
         }
+    }
 
-        let content = fs::read_to_string(&config_path)?;
-        let config: Config = serde_json::from_str(&content)?;
-        Ok(config)
+    /// 按名称查找屏蔽方案
+    pub fn find_profile(&self, name: &str) -> Option<&BlockProfile> {
+        self.profiles.iter().find(|p| p.name == name)
     }
 
-    pub fn save(&self) -> Result<(), AppError> {
-        let config_dir = Self::config_dir()?;
-        if !config_dir.exists() {
-            fs::create_dir_all(&config_dir)?;
+    /// 获取本次专注使用的 (App 黑名单, 网站黑名单)；未指定方案时使用全局列表
+    pub fn resolve_blocklists(
+        &self,
+        profile: Option<&str>,
+    ) -> Result<(Vec<String>, Vec<String>), AppError> {
+        match profile {
+            None => Ok((self.blocked_apps.clone(), self.blocked_sites.clone())),
+            Some(name) => self
+                .find_profile(name)
+                .map(|p| (p.blocked_apps.clone(), p.blocked_sites.clone()))
+                .ok_or_else(|| AppError::ConfigError(format!("屏蔽方案不存在: {}", name))),
+        }
+    }
+
+    /// 本次专注的网站规则：白名单模式使用 allowed_sites，否则使用屏蔽方案（或全局）的黑名单
+    ///
+    /// 未指定方案时，全局黑名单与启用的分类合并去重；屏蔽方案只使用自己的列表
+    pub fn site_rules(
+        &self,
+        profile: Option<&str>,
+        categories: &[SiteCategory],
+    ) -> Result<SiteRules, AppError> {
+        match self.site_mode {
+            SiteMode::Allowlist => Ok(SiteRules::Allowlist(self.allowed_sites.clone())),
+            SiteMode::Blocklist => {
+                let (_, sites) = self.resolve_blocklists(profile)?;
+                if profile.is_some() {
+                    return Ok(SiteRules::Blocklist(sites));
+                }
+                Ok(SiteRules::Blocklist(SiteCategory::merge_sites(
+                    &sites,
+                    categories,
+                    &self.enabled_categories,
+                )))
+            }
         }
+    }
 
-        let config_path = Self::config_path()?;
-        let content = serde_json::to_string_pretty(self)?;
-        fs::write(&config_path, content)?;
+    /// 校验配置内容，拒绝无法正常工作的取值
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.mode != "manual" && self.mode != "scheduled" {
+            return Err(AppError::ConfigError(format!("未知的模式: {}", self.mode)));
+        }
+        if self.pomodoro.work_minutes == 0 {
+            return Err(AppError::ConfigError("专注时长不能为 0".to_string()));
+        }
+        for schedule in &self.schedules {
+            Scheduler::validate_schedule(schedule)?;
+            if let Some(name) = &schedule.profile {
+                if self.find_profile(name).is_none() {
+                    return Err(AppError::ConfigError(format!(
+                        "定时计划 {}-{} 使用的屏蔽方案不存在: {}",
+                        schedule.start, schedule.end, name
+                    )));
+                }
+            }
+        }
+        for exception in &self.schedule_exceptions {
+            Scheduler::validate_exception(exception)?;
+        }
+        if self.calendar_sources.iter().any(|s| s.path.trim().is_empty()) {
+            return Err(AppError::ConfigError("日历文件路径不能为空".to_string()));
+        }
+        let listen = match self.dns_sinkhole.listen.parse::<SocketAddr>() {
+            Ok(addr) if addr.ip().is_loopback() => addr,
+            _ => {
+                return Err(AppError::ConfigError(format!(
+                    "本地 DNS 监听地址必须是本机回环地址: {}",
+                    self.dns_sinkhole.listen
+                )))
+            }
+        };
+        if let Some(upstream) = &self.dns_sinkhole.upstream {
+            match DnsSinkhole::parse_upstream(upstream) {
+                None => {
+                    return Err(AppError::ConfigError(format!("上游 DNS 地址无效: {}", upstream)));
+                }
+                Some(addr) if DnsSinkhole::loops_back(listen, addr) => {
+                    return Err(AppError::ConfigError(format!(
+                        "上游 DNS 不能是本地 DNS 的监听地址: {}",
+                        upstream
+                    )));
+                }
+                Some(_) => {}
+            }
+        }
+        if let Some(id) = self
+            .enabled_categories
+            .iter()
+            .find(|id| !SiteCategory::is_valid_id(id))
+        {
+            return Err(AppError::ConfigError(format!("无效的分类 id: {}", id)));
+        }
+        for (index, profile) in self.profiles.iter().enumerate() {
+            if profile.name.trim().is_empty() {
+                return Err(AppError::ConfigError("屏蔽方案名称不能为空".to_string()));
+            }
+            if self.profiles[..index].iter().any(|p| p.name == profile.name) {
+                return Err(AppError::ConfigError(format!("屏蔽方案重名: {}", profile.name)));
+            }
+        }
         Ok(())
     }
+
+    pub fn save(&self) -> Result<(), AppError> {
+        let config_path = Self::config_path()?;
+        storage::save_json(&config_path, self, CONFIG_BACKUP_COUNT)
+    }
+}
+
+/// 定时专注所在的时间段，开始专注时确定；之后修改计划、例外日期或日历不会提前结束本次专注
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledWindow {
+    /// 时间段结束的 Unix 时间戳（秒）
+    pub end_time: u64,
+    /// 时间段设置的专注时长：休息结束后继续下一轮；None 表示一次专注到结束
+    pub round_minutes: Option<u32>,
+}
+
+impl ScheduledWindow {
+    /// 距离时间段结束的秒数
+    pub fn seconds_left(&self, now_ts: u64) -> u64 {
+        self.end_time.saturating_sub(now_ts)
+    }
 }
 
 /// 专注会话持久化，用于应用重启后恢复计时
@@ -173,36 +496,40 @@ pub struct FocusSession {
     pub work_minutes: u32,
     pub break_minutes: u32,
     pub emergency_remaining: u32,
+    /// 本次专注使用的屏蔽方案（None 为全局黑名单）
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// 开始专注时的黑名单快照，恢复时原样重新应用
+    #[serde(default)]
+    pub blocked_apps: Option<Vec<String>>,
+    #[serde(default)]
+    pub blocked_sites: Option<Vec<String>>,
+    /// 白名单模式的会话记录放行的网站（此时 blocked_sites 为 None）
+    #[serde(default)]
+    pub allowed_sites: Option<Vec<String>>,
+    /// 定时触发的专注记录开始时确定的时间段，恢复后仍按该时间段循环和结束
+    #[serde(default)]
+    pub scheduled: Option<ScheduledWindow>,
 }
 
 impl FocusSession {
     pub fn session_path() -> Result<PathBuf, AppError> {
-        Ok(Config::config_dir()?.join("session.json"))
+        Ok(paths::state_dir()?.join("session.json"))
     }
 
     pub fn save(&self) -> Result<(), AppError> {
         let path = Self::session_path()?;
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent)?;
-        }
-        let content = serde_json::to_string_pretty(self)?;
-        fs::write(&path, content)?;
-        Ok(())
+        storage::save_json(&path, self, SESSION_BACKUP_COUNT)
     }
 
     pub fn load() -> Result<Option<Self>, AppError> {
         let path = Self::session_path()?;
-        if !path.exists() {
-            return Ok(None);
-        }
-        let content = fs::read_to_string(&path)?;
-        let session: FocusSession = serde_json::from_str(&content)?;
-        Ok(Some(session))
+        storage::load_json(&path, SESSION_BACKUP_COUNT)
     }
 
     pub fn delete() {
         if let Ok(path) = Self::session_path() {
-            let _ = fs::remove_file(path);
+            storage::remove_with_backups(&path, SESSION_BACKUP_COUNT);
         }
     }
 }
@@ -0,0 +1,226 @@
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::errors::AppError;
+use crate::{paths, storage};
+
+/// history.json 保留的历史完好副本数
+const HISTORY_BACKUP_COUNT: usize = 1;
+/// 最多保留的记录条数，超出时丢弃最早的记录
+const MAX_HISTORY_ENTRIES: usize = 2000;
+/// 历史记录中的本地时间格式
+pub const HISTORY_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// 多个线程都会追加记录，读改写期间持有该锁
+static HISTORY_LOCK: Mutex<()> = Mutex::new(());
+
+/// 历史记录的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryKind {
+    /// 应用未运行期间完整错过的定时时间段
+    MissedWindow,
+    /// 启动时已在进行中、按设置跳过或未确认加入的时间段
+    SkippedWindow,
+    /// 专注期间 hosts 屏蔽区块或防火墙规则被改动（开始和结束时间均为发现时间）
+    BlockTampered,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub kind: HistoryKind,
+    /// 本地时间，格式 `YYYY-MM-DDTHH:MM:SS`
+    pub start: String,
+    pub end: String,
+    /// 时间段文字、事件标题、例外备注或篡改详情
+    pub label: String,
+    pub recorded_at: String,
+}
+
+impl HistoryEntry {
+    pub fn new(kind: HistoryKind, start: NaiveDateTime, end: NaiveDateTime, label: &str) -> Self {
+        HistoryEntry {
+            kind,
+            start: start.format(HISTORY_TIME_FORMAT).to_string(),
+            end: end.format(HISTORY_TIME_FORMAT).to_string(),
+            label: label.to_string(),
+            recorded_at: Local::now()
+                .naive_local()
+                .format(HISTORY_TIME_FORMAT)
+                .to_string(),
+        }
+    }
+
+    /// 记录覆盖的分钟数，时间格式错误时为 0
+    fn minutes(&self) -> i64 {
+        let parse = |s: &str| NaiveDateTime::parse_from_str(s, HISTORY_TIME_FORMAT).ok();
+        match (parse(&self.start), parse(&self.end)) {
+            (Some(start), Some(end)) => (end - start).num_minutes().max(0),
+            _ => 0,
+        }
+    }
+}
+
+/// 一段时间内的统计
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryStats {
+    pub missed_windows: u32,
+    pub missed_minutes: i64,
+    pub skipped_windows: u32,
+    pub skipped_minutes: i64,
+    pub tamper_events: u32,
+}
+
+/// 会话历史，保存在数据目录的 history.json
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionHistory {
+    #[serde(default)]
+    pub entries: Vec<HistoryEntry>,
+}
+
+impl SessionHistory {
+    pub fn history_path() -> Result<PathBuf, AppError> {
+        Ok(paths::data_dir()?.join("history.json"))
+    }
+
+    /// 调度器最近一次运行时间的记录文件，用于计算应用未运行期间错过的时间段
+    fn last_seen_path() -> Result<PathBuf, AppError> {
+        Ok(paths::state_dir()?.join("scheduler_last_seen"))
+    }
+
+    pub fn load() -> Result<Self, AppError> {
+        let path = Self::history_path()?;
+        Ok(storage::load_json(&path, HISTORY_BACKUP_COUNT)?.unwrap_or_default())
+    }
+
+    /// 追加记录并保存
+    pub fn append(entries: Vec<HistoryEntry>) -> Result<(), AppError> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let _guard = HISTORY_LOCK.lock().unwrap();
+        let mut history = Self::load()?;
+        history.push(entries);
+        storage::save_json(&Self::history_path()?, &history, HISTORY_BACKUP_COUNT)
+    }
+
+    fn push(&mut self, entries: Vec<HistoryEntry>) {
+        self.entries.extend(entries);
+        if self.entries.len() > MAX_HISTORY_ENTRIES {
+            let overflow = self.entries.len() - MAX_HISTORY_ENTRIES;
+            self.entries.drain(..overflow);
+        }
+    }
+
+    /// 统计开始时间不早于 since 的记录
+    pub fn stats(&self, since: NaiveDateTime) -> HistoryStats {
+        let since = since.format(HISTORY_TIME_FORMAT).to_string();
+        let mut stats = HistoryStats::default();
+        // 时间格式固定宽度，可以直接按字符串比较
+        for entry in self.entries.iter().filter(|e| e.start >= since) {
+            match entry.kind {
+                HistoryKind::MissedWindow => {
+                    stats.missed_windows += 1;
+                    stats.missed_minutes += entry.minutes();
+                }
+                HistoryKind::SkippedWindow => {
+                    stats.skipped_windows += 1;
+                    stats.skipped_minutes += entry.minutes();
+                }
+                HistoryKind::BlockTampered => stats.tamper_events += 1,
+            }
+        }
+        stats
+    }
+
+    /// 读取调度器最近一次运行的时间
+    pub fn load_last_seen() -> Option<NaiveDateTime> {
+        let content = fs::read_to_string(Self::last_seen_path().ok()?).ok()?;
+        NaiveDateTime::parse_from_str(content.trim(), HISTORY_TIME_FORMAT).ok()
+    }
+
+    pub fn save_last_seen(now: NaiveDateTime) {
+        let Ok(path) = Self::last_seen_path() else {
+            return;
+        };
+        let content = now.format(HISTORY_TIME_FORMAT).to_string();
+        if let Err(e) = storage::write_atomic(&path, content.as_bytes()) {
+            eprintln!("保存调度运行时间失败: {}", e);
+        }
+    }
+
+    /// 切换为手动模式时清除，之后的时间段不算错过
+    pub fn clear_last_seen() {
+        if let Ok(path) = Self::last_seen_path() {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_stats_counts_entries_since() {
+        let mut history = SessionHistory::default();
+        history.push(vec![
+            HistoryEntry::new(
+                HistoryKind::MissedWindow,
+                at("2026-09-01 09:00"),
+                at("2026-09-01 12:00"),
+                "09:00-12:00",
+            ),
+            HistoryEntry::new(
+                HistoryKind::MissedWindow,
+                at("2026-10-19 09:00"),
+                at("2026-10-19 12:00"),
+                "09:00-12:00",
+            ),
+            HistoryEntry::new(
+                HistoryKind::SkippedWindow,
+                at("2026-10-19 22:00"),
+                at("2026-10-20 01:30"),
+                "22:00-01:30",
+            ),
+            HistoryEntry::new(
+                HistoryKind::BlockTampered,
+                at("2026-10-20 10:15"),
+                at("2026-10-20 10:15"),
+                "hosts 屏蔽区块被删除或改动",
+            ),
+        ]);
+
+        assert_eq!(
+            history.stats(at("2026-10-01 00:00")),
+            HistoryStats {
+                missed_windows: 1,
+                missed_minutes: 180,
+                skipped_windows: 1,
+                skipped_minutes: 210,
+                tamper_events: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_push_drops_oldest_entries() {
+        let mut history = SessionHistory::default();
+        let entry = |day: u32| {
+            let start = at("2026-01-01 09:00") + chrono::Duration::days(day as i64);
+            HistoryEntry::new(HistoryKind::MissedWindow, start, start, "")
+        };
+        history.push((0..MAX_HISTORY_ENTRIES as u32).map(entry).collect());
+        history.push(vec![entry(MAX_HISTORY_ENTRIES as u32)]);
+
+        assert_eq!(history.entries.len(), MAX_HISTORY_ENTRIES);
+        assert_eq!(history.entries[0].start, entry(1).start);
+    }
+}
@@ -1,5 +1,11 @@
+pub mod bundle;
+pub mod category;
 pub mod config;
+pub mod history;
 pub mod timer;
 
+pub use bundle::*;
+pub use category::*;
 pub use config::*;
+pub use history::*;
 pub use timer::*;
@@ -0,0 +1,201 @@
+use std::ffi::OsString;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use crate::errors::AppError;
+
+const APP_DIR_NAME: &str = "pomodoro-focus";
+
+/// 覆盖数据根目录的环境变量
+pub const ROOT_ENV_VAR: &str = "POMODORO_FOCUS_HOME";
+/// 覆盖数据根目录的命令行参数（`--data-dir <path>` 或 `--data-dir=<path>`）
+pub const ROOT_CLI_FLAG: &str = "--data-dir";
+
+static ROOT_OVERRIDE: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// 设置数据根目录覆盖（优先级高于环境变量），传 None 取消覆盖
+pub fn set_root_override(root: Option<PathBuf>) {
+    *ROOT_OVERRIDE.write().unwrap() = root;
+}
+
+/// 从命令行参数读取 `--data-dir`，找到则设为根目录覆盖
+pub fn init_from_args<I: IntoIterator<Item = String>>(args: I) {
+    if let Some(root) = parse_root_arg(args) {
+        println!("[paths] 使用命令行指定的数据目录: {}", root.display());
+        set_root_override(Some(root));
+    }
+}
+
+fn parse_root_arg<I: IntoIterator<Item = String>>(args: I) -> Option<PathBuf> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == ROOT_CLI_FLAG {
+            return args.next().filter(|v| !v.is_empty()).map(PathBuf::from);
+        }
+        if let Some(value) = arg.strip_prefix(&format!("{}=", ROOT_CLI_FLAG)) {
+            if !value.is_empty() {
+                return Some(PathBuf::from(value));
+            }
+        }
+    }
+    None
+}
+
+/// 覆盖的根目录：显式设置 > 环境变量。覆盖时配置、状态、数据都放在同一目录下
+fn root_override() -> Option<PathBuf> {
+    pick_root(
+        ROOT_OVERRIDE.read().unwrap().clone(),
+        std::env::var_os(ROOT_ENV_VAR),
+    )
+}
+
+fn pick_root(explicit: Option<PathBuf>, env: Option<OsString>) -> Option<PathBuf> {
+    explicit.or_else(|| env.filter(|v| !v.is_empty()).map(PathBuf::from))
+}
+
+#[derive(Debug, Clone, Copy)]
+enum DirKind {
+    Config,
+    State,
+    Data,
+}
+
+/// 按根目录覆盖和平台计算目录；macOS 上三者都是旧版本的目录
+fn resolve_dir(kind: DirKind, root: Option<PathBuf>) -> Result<PathBuf, AppError> {
+    if let Some(root) = root {
+        return Ok(root);
+    }
+    if cfg!(target_os = "macos") {
+        return legacy_dir().ok_or_else(home_dir_error);
+    }
+    match kind {
+        DirKind::Config => platform_dir(dirs::config_dir()),
+        DirKind::State => platform_dir(dirs::state_dir().or_else(dirs::data_local_dir)),
+        DirKind::Data => platform_dir(dirs::data_dir()),
+    }
+}
+
+/// 旧版本在所有平台上使用的目录（macOS 上即为当前目录）
+fn legacy_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| {
+        home.join("Library")
+            .join("Application Support")
+            .join(APP_DIR_NAME)
+    })
+}
+
+fn home_dir_error() -> AppError {
+    AppError::ConfigError("无法获取用户目录".to_string())
+}
+
+fn platform_dir(base: Option<PathBuf>) -> Result<PathBuf, AppError> {
+    base.map(|dir| dir.join(APP_DIR_NAME)).ok_or_else(home_dir_error)
+}
+
+/// 配置目录（config.json）
+///
+/// - macOS: `~/Library/Application Support/pomodoro-focus`
+/// - Linux: `$XDG_CONFIG_HOME/pomodoro-focus`（默认 `~/.config`）
+/// - Windows: `%APPDATA%\pomodoro-focus`
+pub fn config_dir() -> Result<PathBuf, AppError> {
+    resolve_dir(DirKind::Config, root_override())
+}
+
+/// 运行状态目录（session.json、hosts 备份等可丢弃的状态）
+///
+/// - macOS: 与配置目录相同
+/// - Linux: `$XDG_STATE_HOME/pomodoro-focus`（默认 `~/.local/state`）
+/// - Windows: `%LOCALAPPDATA%\pomodoro-focus`
+pub fn state_dir() -> Result<PathBuf, AppError> {
+    resolve_dir(DirKind::State, root_override())
+}
+
+/// 用户数据目录（背景图、专注历史等）
+///
+/// - macOS: 与配置目录相同
+/// - Linux: `$XDG_DATA_HOME/pomodoro-focus`（默认 `~/.local/share`）
+/// - Windows: `%APPDATA%\pomodoro-focus`
+pub fn data_dir() -> Result<PathBuf, AppError> {
+    resolve_dir(DirKind::Data, root_override())
+}
+
+/// 非 macOS 平台上，旧版本把所有文件写在 `~/Library/Application Support` 下，
+/// 新目录中还没有对应文件时迁移过去
+pub fn migrate_legacy_files() {
+    if cfg!(target_os = "macos") || root_override().is_some() {
+        return;
+    }
+    let Some(legacy) = legacy_dir().filter(|d| d.is_dir()) else {
+        return;
+    };
+
+    let targets = [
+        ("config.json", config_dir()),
+        ("session.json", state_dir()),
+        ("hosts.backup", state_dir()),
+        ("bg.jpg", data_dir()),
+    ];
+
+    for (name, dir) in targets {
+        let Ok(dir) = dir else {
+            continue;
+        };
+        let from = legacy.join(name);
+        let to = dir.join(name);
+        if !from.exists() || to.exists() {
+            continue;
+        }
+        if let Err(e) = fs::create_dir_all(&dir).and_then(|_| fs::copy(&from, &to)) {
+            eprintln!("[paths] 迁移 {} 失败: {}", from.display(), e);
+        } else {
+            println!("[paths] 已迁移 {} -> {}", from.display(), to.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_root_arg() {
+        assert_eq!(
+            parse_root_arg(args(&["app", "--data-dir", "/tmp/pf"])),
+            Some(PathBuf::from("/tmp/pf"))
+        );
+        assert_eq!(
+            parse_root_arg(args(&["app", "--data-dir=/tmp/pf"])),
+            Some(PathBuf::from("/tmp/pf"))
+        );
+        assert_eq!(parse_root_arg(args(&["app", "--data-dir"])), None);
+        assert_eq!(parse_root_arg(args(&["app"])), None);
+    }
+
+    // 不修改全局的 ROOT_OVERRIDE，避免影响并行运行的其他测试
+    #[test]
+    fn test_root_override_applies_to_all_dirs() {
+        let root = PathBuf::from("/tmp/pomodoro_paths_override");
+        for kind in [DirKind::Config, DirKind::State, DirKind::Data] {
+            assert_eq!(resolve_dir(kind, Some(root.clone())).unwrap(), root);
+        }
+    }
+
+    #[test]
+    fn test_explicit_root_wins_over_env() {
+        let explicit = PathBuf::from("/tmp/explicit");
+        assert_eq!(
+            pick_root(Some(explicit.clone()), Some(OsString::from("/tmp/env"))),
+            Some(explicit)
+        );
+        assert_eq!(
+            pick_root(None, Some(OsString::from("/tmp/env"))),
+            Some(PathBuf::from("/tmp/env"))
+        );
+        assert_eq!(pick_root(None, Some(OsString::new())), None);
+    }
+}
@@ -149,7 +149,7 @@ impl AppBlocker {
     }
 
     /// 检查是否为系统保护进程
-    fn is_system_protected(process_name: &str) -> bool {
+    pub fn is_system_protected(process_name: &str) -> bool {
         let name_lower = process_name.to_lowercase();
 
         for prefix in SYSTEM_PROTECTED_PREFIXES {
@@ -0,0 +1,278 @@
+use crate::errors::AppError;
+use crate::models::{HistoryEntry, HistoryKind, SessionHistory};
+use crate::services::{BlockDrift, SiteBlocker, SiteRules};
+use chrono::Local;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// 两次检查之间的间隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+/// 轮询停止信号的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 重新应用失败后第一次重试前等待的时间，之后每次失败加倍
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(60);
+/// 重试间隔的上限
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(15 * 60);
+
+/// 专注期间定期检查 hosts 屏蔽区块和防火墙规则，被改动时重新应用并记入会话历史
+pub struct BlockWatchdog {
+    running: Arc<AtomicBool>,
+}
+
+/// 检查线程在两次检查之间保留的状态
+#[derive(Debug, Default)]
+struct WatchState {
+    /// 上次重新应用失败时的失效项，同样的失效只记录一次
+    reported: Vec<BlockDrift>,
+    /// 重新应用失败后，同样的失效在此之前不再重试（重新应用可能弹出授权）
+    retry_at: Option<Instant>,
+    retry_delay: Duration,
+    /// 用户取消或拒绝了授权，同样的失效不再重试
+    denied: bool,
+    /// 已记录过无法检查防火墙
+    firewall_unknown: bool,
+}
+
+impl WatchState {
+    fn reset(&mut self) {
+        let firewall_unknown = self.firewall_unknown;
+        *self = WatchState {
+            firewall_unknown,
+            ..WatchState::default()
+        };
+    }
+
+    /// 同样的失效上次重新应用失败，且还没到重试时间或授权被拒绝
+    fn should_wait(&self, drift: &[BlockDrift], now: Instant) -> bool {
+        self.reported == drift && (self.denied || self.retry_at.is_some_and(|at| now < at))
+    }
+
+    /// 记录一次失败的重新应用：授权被拒绝时停止重试，否则按加倍的间隔重试
+    fn record_failure(&mut self, drift: Vec<BlockDrift>, error: &AppError, now: Instant) {
+        if self.reported != drift {
+            self.retry_delay = Duration::ZERO;
+        }
+        self.denied = matches!(error, AppError::PermissionDenied(_));
+        self.retry_delay = if self.retry_delay.is_zero() {
+            INITIAL_RETRY_DELAY
+        } else {
+            (self.retry_delay * 2).min(MAX_RETRY_DELAY)
+        };
+        self.retry_at = Some(now + self.retry_delay);
+        self.reported = drift;
+    }
+}
+
+impl BlockWatchdog {
+    pub fn start(rules: SiteRules) -> Self {
+        let blocker = SiteBlocker::from_rules(rules);
+        let running = Arc::new(AtomicBool::new(true));
+        let flag = Arc::clone(&running);
+        thread::spawn(move || {
+            println!("[BlockWatchdog] 已启动");
+            let mut watch = WatchState::default();
+            while Self::wait(&flag) {
+                if let Some(entry) = Self::check_once(&blocker, &flag, &mut watch, Instant::now()) {
+                    if let Err(e) = SessionHistory::append(vec![entry]) {
+                        eprintln!("[BlockWatchdog] 记录篡改事件失败: {}", e);
+                    }
+                }
+            }
+        });
+        BlockWatchdog { running }
+    }
+
+    /// 通知线程退出，不等待：重新应用时可能正在等待管理员授权
+    pub fn stop(self) {
+        self.running.store(false, Ordering::SeqCst);
+        println!("[BlockWatchdog] 已停止");
+    }
+
+    /// 等待一个检查间隔，期间收到停止信号时返回 false
+    fn wait(running: &AtomicBool) -> bool {
+        let deadline = Instant::now() + CHECK_INTERVAL;
+        while Instant::now() < deadline {
+            if !running.load(Ordering::SeqCst) {
+                return false;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        running.load(Ordering::SeqCst)
+    }
+
+    /// 检查一次，发现失效时重新应用，返回需要记录的篡改事件
+    ///
+    /// 重新应用失败后同样的失效按加倍的间隔重试，授权被拒绝时不再重试，直到失效项变化
+    fn check_once(
+        blocker: &SiteBlocker,
+        running: &AtomicBool,
+        watch: &mut WatchState,
+        now: Instant,
+    ) -> Option<HistoryEntry> {
+        let report = match blocker.check_integrity() {
+            Ok(report) => report,
+            Err(e) => {
+                eprintln!("[BlockWatchdog] 检查屏蔽失败: {}", e);
+                return None;
+            }
+        };
+        if report.firewall_unknown && !watch.firewall_unknown {
+            println!("[BlockWatchdog] 无法查询防火墙状态（需要管理员权限），只检查 hosts");
+        }
+        watch.firewall_unknown = report.firewall_unknown;
+
+        let drift = report.drift;
+        if drift.is_empty() {
+            watch.reset();
+            return None;
+        }
+        if watch.should_wait(&drift, now) {
+            return None;
+        }
+
+        let description = drift
+            .iter()
+            .map(BlockDrift::description)
+            .collect::<Vec<_>>()
+            .join("、");
+        println!("[BlockWatchdog] {}，重新应用屏蔽", description);
+        let result = blocker.repair(&drift);
+
+        // 重新应用期间专注已结束，撤销刚写入的屏蔽
+        if !running.load(Ordering::SeqCst) {
+            if let Err(e) = blocker.unblock_sites() {
+                eprintln!("[BlockWatchdog] 撤销屏蔽失败: {}", e);
+            }
+            return None;
+        }
+
+        let repeated = watch.reported == drift;
+        let label = match &result {
+            Ok(_) => {
+                watch.reset();
+                format!("{}，已重新应用", description)
+            }
+            Err(e) => {
+                eprintln!("[BlockWatchdog] 重新应用屏蔽失败: {}", e);
+                let label = format!("{}，重新应用失败: {}", description, e);
+                watch.record_failure(drift, e, now);
+                label
+            }
+        };
+        if repeated {
+            return None;
+        }
+
+        let now = Local::now().naive_local();
+        Some(HistoryEntry::new(
+            HistoryKind::BlockTampered,
+            now,
+            now,
+            &label,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::{FirewallStatus, SandboxBackend};
+    use crate::test_support::{hosts_sandbox, Fault, FaultyBackend};
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// 已写入屏蔽区块、但防火墙规则被清空的沙盒，重新应用时返回给定的错误
+    fn flushed(name: &str, error: fn() -> AppError) -> (PathBuf, SiteBlocker, Arc<FaultyBackend>) {
+        let dir = hosts_sandbox("block_watchdog", name, "127.0.0.1 localhost\n");
+        let sites = vec!["example.com".to_string()];
+        SiteBlocker::with_backend(sites.clone(), Arc::new(SandboxBackend::new(dir.clone())))
+            .block_sites()
+            .unwrap();
+        let backend = Arc::new(
+            FaultyBackend::new(dir.clone(), Fault::Fail(error))
+                .with_firewall(FirewallStatus::Tampered),
+        );
+        let blocker = SiteBlocker::with_backend(sites, backend.clone());
+        (dir, blocker, backend)
+    }
+
+    #[test]
+    fn test_tamper_is_repaired_and_recorded() {
+        let dir = hosts_sandbox("block_watchdog", "repair", "127.0.0.1 localhost\n");
+        let backend = Arc::new(SandboxBackend::new(dir.clone()));
+        let blocker = SiteBlocker::with_backend(vec!["example.com".to_string()], backend);
+        let running = AtomicBool::new(true);
+        let mut watch = WatchState::default();
+        let now = Instant::now();
+
+        blocker.block_sites().unwrap();
+        let blocked = fs::read_to_string(dir.join("hosts")).unwrap();
+        assert!(BlockWatchdog::check_once(&blocker, &running, &mut watch, now).is_none());
+
+        fs::write(dir.join("hosts"), "127.0.0.1 localhost\n").unwrap();
+        let entry = BlockWatchdog::check_once(&blocker, &running, &mut watch, now).unwrap();
+        assert_eq!(entry.kind, HistoryKind::BlockTampered);
+        assert_eq!(entry.start, entry.end);
+        assert_eq!(entry.label, "hosts 屏蔽区块被删除或改动，已重新应用");
+        assert_eq!(fs::read_to_string(dir.join("hosts")).unwrap(), blocked);
+
+        // 修复后再次被删除，算作新的篡改
+        fs::write(dir.join("hosts"), "127.0.0.1 localhost\n").unwrap();
+        assert!(BlockWatchdog::check_once(&blocker, &running, &mut watch, now).is_some());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_denied_repair_is_recorded_once_and_not_retried() {
+        let (dir, blocker, backend) = flushed("denied", || {
+            AppError::PermissionDenied("用户取消了管理员权限授权".to_string())
+        });
+        let blocked = fs::read_to_string(dir.join("hosts")).unwrap();
+        let running = AtomicBool::new(true);
+        let mut watch = WatchState::default();
+        let now = Instant::now();
+
+        let entry = BlockWatchdog::check_once(&blocker, &running, &mut watch, now).unwrap();
+        assert_eq!(
+            entry.label,
+            "防火墙规则被清空或防火墙被停用，重新应用失败: 权限不足: 用户取消了管理员权限授权"
+        );
+        assert_eq!(watch.reported, vec![BlockDrift::Firewall]);
+
+        // 用户取消授权后不再弹出授权，也不重复记录
+        let later = now + MAX_RETRY_DELAY * 2;
+        assert!(BlockWatchdog::check_once(&blocker, &running, &mut watch, later).is_none());
+        assert_eq!(backend.applied.load(Ordering::SeqCst), 1);
+        assert_eq!(fs::read_to_string(dir.join("hosts")).unwrap(), blocked);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_failed_repair_backs_off() {
+        let (dir, blocker, backend) = flushed("backoff", || {
+            AppError::BlockerError("屏蔽命令执行失败".to_string())
+        });
+        let running = AtomicBool::new(true);
+        let mut watch = WatchState::default();
+        let now = Instant::now();
+
+        assert!(BlockWatchdog::check_once(&blocker, &running, &mut watch, now).is_some());
+        // 等待期间不重试
+        let soon = now + CHECK_INTERVAL;
+        assert!(BlockWatchdog::check_once(&blocker, &running, &mut watch, soon).is_none());
+        assert_eq!(backend.applied.load(Ordering::SeqCst), 1);
+
+        // 到时间后重试，失败时不重复记录，间隔加倍
+        let retry = now + INITIAL_RETRY_DELAY;
+        assert!(BlockWatchdog::check_once(&blocker, &running, &mut watch, retry).is_none());
+        assert_eq!(backend.applied.load(Ordering::SeqCst), 2);
+        assert_eq!(watch.retry_delay, INITIAL_RETRY_DELAY * 2);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,228 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::net::IpAddr;
+
+use crate::services::SiteBlocker;
+
+/// hosts 文件中指向本机的常见名称，导入时忽略
+const LOCAL_HOSTNAMES: &[&str] = &[
+    "localhost",
+    "localhost.localdomain",
+    "local",
+    "broadcasthost",
+    "ip6-localhost",
+    "ip6-loopback",
+    "ip6-localnet",
+    "ip6-mcastprefix",
+    "ip6-allnodes",
+    "ip6-allrouters",
+    "ip6-allhosts",
+    "0.0.0.0",
+];
+/// 跳过的行只保留前若干条的详情，其余只计数
+const MAX_REPORTED_SKIPPED: usize = 100;
+/// 报告中每行最多保留的字符数
+const MAX_LINE_CHARS: usize = 200;
+
+/// 未导入的行
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedLine {
+    /// 行号，从 1 开始
+    pub line: usize,
+    pub text: String,
+    pub reason: String,
+}
+
+/// 解析结果：去重后的域名和跳过的行
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParsedBlocklist {
+    pub domains: Vec<String>,
+    /// 文件内重复出现的域名数
+    pub duplicates: usize,
+    /// 跳过的行数（含未保留详情的行）
+    pub skipped_count: usize,
+    pub skipped: Vec<SkippedLine>,
+}
+
+/// 屏蔽列表导入：支持 hosts 格式（`0.0.0.0 example.com`）、
+/// adblock 的 `||example.com^` 以及每行一个域名
+pub struct BlocklistImporter;
+
+impl BlocklistImporter {
+    pub fn parse(content: &str) -> ParsedBlocklist {
+        let mut parsed = ParsedBlocklist::default();
+        let mut seen: HashSet<String> = HashSet::new();
+
+        for (index, raw) in content.lines().enumerate() {
+            let line = raw.trim_start_matches('\u{feff}').trim();
+            if Self::is_comment(line) {
+                continue;
+            }
+            match Self::parse_line(line) {
+                Ok(domains) => {
+                    for domain in domains {
+                        if seen.insert(domain.clone()) {
+                            parsed.domains.push(domain);
+                        } else {
+                            parsed.duplicates += 1;
+                        }
+                    }
+                }
+                Err(reason) => {
+                    parsed.skipped_count += 1;
+                    if parsed.skipped.len() < MAX_REPORTED_SKIPPED {
+                        parsed.skipped.push(SkippedLine {
+                            line: index + 1,
+                            text: line.chars().take(MAX_LINE_CHARS).collect(),
+                            reason: reason.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+
+        parsed
+    }
+
+    /// 空行、`#` 和 `!` 注释、`[Adblock Plus 2.0]` 这样的文件头
+    fn is_comment(line: &str) -> bool {
+        line.is_empty()
+            || line.starts_with('#')
+            || line.starts_with('!')
+            || (line.starts_with('[') && line.ends_with(']'))
+    }
+
+    /// 解析一行，返回其中的域名；指向本机的 hosts 条目返回空列表
+    fn parse_line(line: &str) -> Result<Vec<String>, &'static str> {
+        if let Some(rule) = line.strip_prefix("||") {
+            return Self::parse_adblock(rule).map(|domain| vec![domain]);
+        }
+        if line.starts_with("@@") {
+            return Err("例外规则");
+        }
+        if line.contains("##") || line.contains("#@#") {
+            return Err("元素隐藏规则");
+        }
+
+        // 去掉行尾注释
+        let line = line.split('#').next().unwrap_or("").trim();
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            [ip, names @ ..] if ip.parse::<IpAddr>().is_ok() => {
+                if names.is_empty() {
+                    return Err("缺少域名");
+                }
+                let names: Vec<&str> = names
+                    .iter()
+                    .copied()
+                    .filter(|name| !LOCAL_HOSTNAMES.contains(&name.to_lowercase().as_str()))
+                    .collect();
+                let domains: Vec<String> = names
+                    .iter()
+                    .filter_map(|name| Self::normalize(name).ok())
+                    .collect();
+                if domains.is_empty() && !names.is_empty() {
+                    return Err("无效的域名");
+                }
+                Ok(domains)
+            }
+            [domain] => Self::normalize(domain).map(|domain| vec![domain]),
+            _ => Err("无法识别的格式"),
+        }
+    }
+
+    /// `||example.com^`；带 `$` 选项、路径或通配符的规则只屏蔽部分请求，不导入
+    fn parse_adblock(rule: &str) -> Result<String, &'static str> {
+        let Some((domain, rest)) = rule.split_once('^') else {
+            return Err("不支持的 adblock 规则");
+        };
+        if !rest.is_empty() && rest != "|" {
+            return Err("带选项的 adblock 规则");
+        }
+        if domain.contains(['/', '*']) {
+            return Err("带路径或通配符的 adblock 规则");
+        }
+        Self::normalize(domain)
+    }
+
+    /// 与网站黑名单相同的清理规则，再校验域名格式
+    fn normalize(entry: &str) -> Result<String, &'static str> {
+        let domain = SiteBlocker::clean_domain(entry);
+        let domain = domain.trim_end_matches('.');
+        if SiteBlocker::is_valid_domain(domain) {
+            Ok(domain.to_string())
+        } else {
+            Err("无效的域名")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hosts_format() {
+        let content = "\u{feff}# StevenBlack hosts\n\
+            127.0.0.1 localhost\n\
+            ::1 localhost ip6-localhost\n\
+            0.0.0.0 0.0.0.0\n\
+            0.0.0.0 Ads.Example.com tracker.example.com # 注释\n\
+            :: ads.example.com\n\
+            0.0.0.0 bad_domain\n\
+            0.0.0.0\n";
+        let parsed = BlocklistImporter::parse(content);
+        assert_eq!(
+            parsed.domains,
+            vec!["ads.example.com", "tracker.example.com"]
+        );
+        assert_eq!(parsed.duplicates, 1);
+        assert_eq!(parsed.skipped_count, 2);
+        assert_eq!(parsed.skipped[0].line, 7);
+        assert_eq!(parsed.skipped[0].reason, "无效的域名");
+        assert_eq!(parsed.skipped[1].reason, "缺少域名");
+    }
+
+    #[test]
+    fn test_parse_adblock_format() {
+        let content = "[Adblock Plus 2.0]\n\
+            ! Title: test\n\
+            ||doubleclick.net^\n\
+            ||Video.Example.org.^\n\
+            ||ads.example.com^$third-party\n\
+            ||example.com/banner^\n\
+            @@||good.example.com^\n\
+            example.com##.ad\n\
+            plain.example.net\n";
+        let parsed = BlocklistImporter::parse(content);
+        assert_eq!(
+            parsed.domains,
+            vec!["doubleclick.net", "video.example.org", "plain.example.net"]
+        );
+        let reasons: Vec<&str> = parsed.skipped.iter().map(|s| s.reason.as_str()).collect();
+        assert_eq!(
+            reasons,
+            vec![
+                "带选项的 adblock 规则",
+                "带路径或通配符的 adblock 规则",
+                "例外规则",
+                "元素隐藏规则",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_large_list_keeps_limited_skip_details() {
+        let mut content = String::new();
+        for i in 0..50_000 {
+            content.push_str(&format!("0.0.0.0 host{}.example.com\n", i));
+            if i % 100 == 0 {
+                content.push_str("not a rule line\n");
+            }
+        }
+        let parsed = BlocklistImporter::parse(&content);
+        assert_eq!(parsed.domains.len(), 50_000);
+        assert_eq!(parsed.skipped_count, 500);
+        assert_eq!(parsed.skipped.len(), MAX_REPORTED_SKIPPED);
+    }
+}
@@ -0,0 +1,76 @@
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Manager};
+
+use crate::models::CalendarSource;
+use crate::services::{IcsCalendar, IcsEvent};
+use crate::state::AppState;
+
+const POLL_INTERVAL_SECS: u64 = 5;
+
+/// 监听日历来源的 .ics 文件，内容变化后重新解析并更新调度器
+pub struct CalendarWatcher;
+
+impl CalendarWatcher {
+    /// 启动监听线程（随应用运行，不会退出）
+    pub fn start(app_handle: AppHandle) -> thread::JoinHandle<()> {
+        thread::spawn(move || {
+            println!("[CalendarWatcher] 日历文件监听已启动");
+            let state = app_handle.state::<AppState>();
+            let mut last_seen = Self::fingerprint(&state);
+
+            loop {
+                thread::sleep(Duration::from_secs(POLL_INTERVAL_SECS));
+
+                let current = Self::fingerprint(&state);
+                if current == last_seen {
+                    continue;
+                }
+                last_seen = current;
+
+                println!("[CalendarWatcher] 日历文件已变化，重新解析");
+                state.refresh_calendar_events();
+                crate::update_tray_menu(&app_handle);
+            }
+        })
+    }
+
+    /// 读取所有启用的来源，返回命中过滤条件的事件；单个文件失败不影响其他来源
+    pub fn load_events(sources: &[CalendarSource]) -> Vec<IcsEvent> {
+        let mut events = Vec::new();
+
+        for source in sources.iter().filter(|s| s.enabled) {
+            match IcsCalendar::load(Path::new(&source.path)) {
+                Ok(calendar) => {
+                    let before = events.len();
+                    events.extend(calendar.events.into_iter().filter(|e| source.matches(e)));
+                    println!(
+                        "[CalendarWatcher] {} 中有 {} 个专注事件",
+                        source.path,
+                        events.len() - before
+                    );
+                }
+                Err(e) => eprintln!("[CalendarWatcher] 读取日历 {} 失败: {}", source.path, e),
+            }
+        }
+
+        events
+    }
+
+    /// 各来源文件的路径、修改时间和大小，任一变化即视为被修改
+    fn fingerprint(state: &AppState) -> Vec<(String, Option<(SystemTime, u64)>)> {
+        let sources = state.config.lock().unwrap().calendar_sources.clone();
+        sources
+            .into_iter()
+            .filter(|s| s.enabled)
+            .map(|s| {
+                let meta = fs::metadata(&s.path)
+                    .ok()
+                    .and_then(|m| Some((m.modified().ok()?, m.len())));
+                (s.path, meta)
+            })
+            .collect()
+    }
+}
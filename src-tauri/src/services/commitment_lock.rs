@@ -0,0 +1,315 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Config, SiteMode};
+use crate::services::SiteRule;
+
+/// 专注期间被拒绝的配置变更类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ViolationKind {
+    /// 从网站黑名单移除了条目
+    SiteRemoved,
+    /// 从 App 黑名单移除了条目
+    AppRemoved,
+    /// 提高了每月紧急取消次数上限
+    EmergencyLimitRaised,
+    /// 缩短了专注时长
+    WorkShortened,
+    /// 从白名单模式切换回黑名单模式
+    AllowlistDisabled,
+    /// 向白名单添加了网站
+    AllowedSiteAdded,
+    /// 停用了网站分类
+    CategoryDisabled,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockViolation {
+    pub kind: ViolationKind,
+    pub detail: String,
+}
+
+/// 专注承诺锁：计时进行中只允许加强屏蔽的配置变更
+pub struct CommitmentLock;
+
+impl CommitmentLock {
+    /// 对比新旧配置，返回所有削弱屏蔽的变更；为空表示允许保存
+    ///
+    /// 除全局黑名单外，还会检查本次专注使用的屏蔽方案（active_profile）
+    pub fn check(
+        current: &Config,
+        proposed: &Config,
+        active_profile: Option<&str>,
+    ) -> Vec<LockViolation> {
+        let mut violations = Vec::new();
+
+        Self::check_lists(
+            &current.blocked_sites,
+            &current.blocked_apps,
+            &proposed.blocked_sites,
+            &proposed.blocked_apps,
+            "",
+            &mut violations,
+        );
+
+        for id in &current.enabled_categories {
+            if !proposed.enabled_categories.contains(id) {
+                violations.push(LockViolation {
+                    kind: ViolationKind::CategoryDisabled,
+                    detail: format!("分类 {}", id),
+                });
+            }
+        }
+
+        if let Some(old_profile) = active_profile.and_then(|name| current.find_profile(name)) {
+            let (new_sites, new_apps) = proposed
+                .find_profile(&old_profile.name)
+                .map(|p| (p.blocked_sites.as_slice(), p.blocked_apps.as_slice()))
+                .unwrap_or((&[], &[]));
+            Self::check_lists(
+                &old_profile.blocked_sites,
+                &old_profile.blocked_apps,
+                new_sites,
+                new_apps,
+                &format!("[{}] ", old_profile.name),
+                &mut violations,
+            );
+        }
+
+        // 白名单模式下，放行更多网站或退回黑名单都会削弱屏蔽
+        if current.site_mode == SiteMode::Allowlist {
+            if proposed.site_mode != SiteMode::Allowlist {
+                violations.push(LockViolation {
+                    kind: ViolationKind::AllowlistDisabled,
+                    detail: "白名单模式".to_string(),
+                });
+            } else {
+                // 新放行的地址必须已经被原有规则放行
+                let old_allowed = Self::parse_sites(&current.allowed_sites);
+                for rule in Self::parse_sites(&proposed.allowed_sites) {
+                    if !old_allowed.iter().any(|old| rule.is_covered_by(old)) {
+                        violations.push(LockViolation {
+                            kind: ViolationKind::AllowedSiteAdded,
+                            detail: format!("白名单 +{}", rule),
+                        });
+                    }
+                }
+            }
+        }
+
+        let (old, new) = (&current.pomodoro, &proposed.pomodoro);
+
+        if new.emergency_cancel_limit > old.emergency_cancel_limit {
+            violations.push(LockViolation {
+                kind: ViolationKind::EmergencyLimitRaised,
+                detail: format!("{} -> {}", old.emergency_cancel_limit, new.emergency_cancel_limit),
+            });
+        }
+
+        if new.work_minutes < old.work_minutes {
+            violations.push(LockViolation {
+                kind: ViolationKind::WorkShortened,
+                detail: format!("{} -> {} 分钟", old.work_minutes, new.work_minutes),
+            });
+        }
+
+        violations
+    }
+
+    fn check_lists(
+        old_sites: &[String],
+        old_apps: &[String],
+        new_sites: &[String],
+        new_apps: &[String],
+        label: &str,
+        violations: &mut Vec<LockViolation>,
+    ) {
+        // 原有规则屏蔽的地址必须仍被新规则屏蔽，如 youtube.com/shorts 可以换成 youtube.com
+        let new_sites = Self::parse_sites(new_sites);
+        for rule in Self::parse_sites(old_sites) {
+            if !new_sites.iter().any(|new| rule.is_covered_by(new)) {
+                violations.push(LockViolation {
+                    kind: ViolationKind::SiteRemoved,
+                    detail: format!("{}{}", label, rule),
+                });
+            }
+        }
+
+        let new_apps: Vec<String> = new_apps.iter().map(|a| a.trim().to_lowercase()).collect();
+        for app in old_apps {
+            if !new_apps.contains(&app.trim().to_lowercase()) {
+                violations.push(LockViolation {
+                    kind: ViolationKind::AppRemoved,
+                    detail: format!("{}{}", label, app),
+                });
+            }
+        }
+    }
+
+    fn parse_sites(sites: &[String]) -> Vec<SiteRule> {
+        sites.iter().filter_map(|site| SiteRule::parse(site)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::BlockProfile;
+
+    fn kinds(violations: &[LockViolation]) -> Vec<ViolationKind> {
+        violations.iter().map(|v| v.kind).collect()
+    }
+
+    #[test]
+    fn test_adding_entries_is_allowed() {
+        let current = Config::default();
+        let mut proposed = current.clone();
+        proposed.blocked_sites.push("youtube.com".to_string());
+        proposed.blocked_apps.push("Steam".to_string());
+        proposed.pomodoro.work_minutes += 10;
+        proposed.pomodoro.emergency_cancel_limit = 0;
+
+        assert!(CommitmentLock::check(&current, &proposed, None).is_empty());
+    }
+
+    fn config_with_sites() -> Config {
+        Config {
+            blocked_sites: vec!["bilibili.com".to_string(), "douyin.com".to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_removing_entries_is_rejected() {
+        let current = config_with_sites();
+        let mut proposed = current.clone();
+        proposed.blocked_sites.retain(|s| s != "douyin.com");
+        proposed.blocked_apps.retain(|a| a != "QQ");
+
+        let violations = CommitmentLock::check(&current, &proposed, None);
+        assert_eq!(
+            kinds(&violations),
+            vec![ViolationKind::SiteRemoved, ViolationKind::AppRemoved]
+        );
+        assert_eq!(violations[0].detail, "douyin.com");
+        assert_eq!(violations[1].detail, "QQ");
+    }
+
+    #[test]
+    fn test_equivalent_spelling_is_not_a_removal() {
+        let current = config_with_sites();
+        let mut proposed = current.clone();
+        proposed.blocked_sites = current
+            .blocked_sites
+            .iter()
+            .map(|s| format!("https://{}/", s.to_uppercase()))
+            .collect();
+        proposed.blocked_apps = current.blocked_apps.iter().map(|a| a.to_lowercase()).collect();
+
+        assert!(CommitmentLock::check(&current, &proposed, None).is_empty());
+    }
+
+    #[test]
+    fn test_path_rules_can_only_widen() {
+        let current = Config {
+            blocked_sites: vec!["youtube.com/shorts".to_string()],
+            ..Default::default()
+        };
+
+        let mut proposed = current.clone();
+        proposed.blocked_sites = vec!["youtube.com".to_string()];
+        assert!(CommitmentLock::check(&current, &proposed, None).is_empty());
+
+        proposed.blocked_sites = vec!["youtube.com/shorts/abc".to_string()];
+        let violations = CommitmentLock::check(&current, &proposed, None);
+        assert_eq!(kinds(&violations), vec![ViolationKind::SiteRemoved]);
+        assert_eq!(violations[0].detail, "youtube.com/shorts");
+    }
+
+    #[test]
+    fn test_disabling_category_is_rejected() {
+        let current = Config::default();
+        let mut proposed = current.clone();
+        proposed.enabled_categories.push("social".to_string());
+        assert!(CommitmentLock::check(&current, &proposed, None).is_empty());
+
+        proposed.enabled_categories.retain(|id| id != "video");
+        let violations = CommitmentLock::check(&current, &proposed, None);
+        assert_eq!(kinds(&violations), vec![ViolationKind::CategoryDisabled]);
+        assert_eq!(violations[0].detail, "分类 video");
+    }
+
+    #[test]
+    fn test_active_profile_is_locked() {
+        let mut current = Config::default();
+        current.profiles.push(BlockProfile {
+            name: "Coding".to_string(),
+            blocked_apps: vec!["Discord".to_string()],
+            blocked_sites: vec!["youtube.com".to_string()],
+        });
+        current.profiles.push(BlockProfile {
+            name: "Writing".to_string(),
+            blocked_apps: vec!["Xcode".to_string()],
+            blocked_sites: Vec::new(),
+        });
+
+        let mut proposed = current.clone();
+        proposed.profiles[0].blocked_sites.clear();
+        proposed.profiles.remove(1);
+
+        // 未使用的方案可以随意修改
+        let violations = CommitmentLock::check(&current, &proposed, Some("Writing"));
+        assert_eq!(kinds(&violations), vec![ViolationKind::AppRemoved]);
+        assert_eq!(violations[0].detail, "[Writing] Xcode");
+
+        let violations = CommitmentLock::check(&current, &proposed, Some("Coding"));
+        assert_eq!(kinds(&violations), vec![ViolationKind::SiteRemoved]);
+        assert_eq!(violations[0].detail, "[Coding] youtube.com");
+    }
+
+    #[test]
+    fn test_allowlist_can_only_shrink() {
+        let current = Config {
+            site_mode: SiteMode::Allowlist,
+            allowed_sites: vec!["docs.rs".to_string(), "canvas.edu".to_string()],
+            ..Default::default()
+        };
+
+        // 移除放行的网站是加强屏蔽
+        let mut proposed = current.clone();
+        proposed.allowed_sites.pop();
+        assert!(CommitmentLock::check(&current, &proposed, None).is_empty());
+
+        proposed.allowed_sites.push("https://YouTube.com/".to_string());
+        let violations = CommitmentLock::check(&current, &proposed, None);
+        assert_eq!(kinds(&violations), vec![ViolationKind::AllowedSiteAdded]);
+        assert_eq!(violations[0].detail, "白名单 +youtube.com");
+
+        // 只放行原有网站的一部分路径不算新增
+        proposed.allowed_sites = vec!["docs.rs/std".to_string()];
+        assert!(CommitmentLock::check(&current, &proposed, None).is_empty());
+
+        proposed.site_mode = SiteMode::Blocklist;
+        assert_eq!(
+            kinds(&CommitmentLock::check(&current, &proposed, None)),
+            vec![ViolationKind::AllowlistDisabled]
+        );
+
+        // 从黑名单切换到白名单是加强屏蔽
+        assert!(CommitmentLock::check(&proposed, &current, None).is_empty());
+    }
+
+    #[test]
+    fn test_loosening_quota_and_shortening_session() {
+        let current = Config::default();
+
+        let mut proposed = current.clone();
+        proposed.pomodoro.emergency_cancel_limit += 1;
+        proposed.pomodoro.work_minutes -= 5;
+
+        assert_eq!(
+            kinds(&CommitmentLock::check(&current, &proposed, None)),
+            vec![ViolationKind::EmergencyLimitRaised, ViolationKind::WorkShortened]
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::models::Config;
+use crate::state::AppState;
+
+const POLL_INTERVAL_SECS: u64 = 2;
+/// 被拒绝的外部修改另存的文件名（与 config.json 同目录）
+const REJECTED_FILE_NAME: &str = "config.json.rejected";
+
+/// 监听 config.json 的外部修改（手动编辑、dotfiles 同步等）并热加载
+pub struct ConfigWatcher;
+
+impl ConfigWatcher {
+    /// 启动监听线程（随应用运行，不会退出）
+    pub fn start(app_handle: AppHandle) -> thread::JoinHandle<()> {
+        thread::spawn(move || {
+            println!("[ConfigWatcher] 配置文件监听已启动");
+            let mut last_seen = Self::fingerprint();
+
+            loop {
+                thread::sleep(Duration::from_secs(POLL_INTERVAL_SECS));
+
+                let current = Self::fingerprint();
+                if current == last_seen {
+                    continue;
+                }
+                last_seen = current;
+
+                Self::reload(&app_handle);
+            }
+        })
+    }
+
+    /// 文件的修改时间和大小，任一变化即视为被修改
+    fn fingerprint() -> Option<(SystemTime, u64)> {
+        let path = Config::config_path().ok()?;
+        let meta = fs::metadata(path).ok()?;
+        Some((meta.modified().ok()?, meta.len()))
+    }
+
+    fn reload(app_handle: &AppHandle) {
+        let path = match Config::config_path() {
+            Ok(p) => p,
+            Err(_) => return,
+        };
+        let content = match fs::read_to_string(&path) {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("[ConfigWatcher] 读取配置失败: {}", e);
+                return;
+            }
+        };
+
+        let state = app_handle.state::<AppState>();
+
+        let new_config: Config = match serde_json::from_str(&content) {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("[ConfigWatcher] 配置文件格式错误，保持原配置: {}", e);
+                Self::restore_rejected(&state, &path);
+                let _ = app_handle.emit("config-reload-failed", e.to_string());
+                return;
+            }
+        };
+
+        // 应用自身保存产生的变化，内容与内存中一致，无需处理
+        let unchanged = {
+            let current = state.config.lock().unwrap();
+            serde_json::to_value(&*current).ok() == serde_json::to_value(&new_config).ok()
+        };
+        if unchanged {
+            return;
+        }
+
+        match state.reload_config(new_config) {
+            Ok(()) => {
+                println!("[ConfigWatcher] 已热加载外部修改的配置");
+                crate::commands::sync_scheduler(app_handle);
+                // 广播实际生效的配置（后端维护的字段不取文件中的值）
+                let config = state.config.lock().unwrap().clone();
+                let _ = app_handle.emit("config-changed", config);
+            }
+            Err(e) => {
+                eprintln!("[ConfigWatcher] 拒绝外部修改的配置，保持原配置: {}", e);
+                Self::restore_rejected(&state, &path);
+                let _ = app_handle.emit("config-reload-failed", e.to_string());
+            }
+        }
+    }
+
+    /// 被拒绝或无法解析的文件另存为 config.json.rejected，再写回内存中的配置，
+    /// 避免重启后读到被拒绝的修改（如专注期间削弱屏蔽），或因文件损坏回退到备份和默认值
+    fn restore_rejected(state: &AppState, path: &Path) {
+        let rejected = path.with_file_name(REJECTED_FILE_NAME);
+        if let Err(e) = fs::rename(path, &rejected) {
+            eprintln!("[ConfigWatcher] 保存被拒绝的配置失败: {}", e);
+        }
+        let config = state.config.lock().unwrap().clone();
+        match config.save() {
+            Ok(()) => println!(
+                "[ConfigWatcher] 已恢复配置文件，被拒绝的修改保存在 {}",
+                rejected.display()
+            ),
+            Err(e) => eprintln!("[ConfigWatcher] 恢复配置文件失败: {}", e),
+        }
+    }
+}
@@ -0,0 +1,305 @@
+//! DNS 报文的最小实现：只处理单个问题的标准查询，够本地转发、屏蔽和解析 IP 使用
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// 记录类型
+pub const DNS_TYPE_A: u16 = 1;
+pub const DNS_TYPE_CNAME: u16 = 5;
+pub const DNS_TYPE_AAAA: u16 = 28;
+
+/// 响应码
+pub const DNS_RCODE_NOERROR: u8 = 0;
+pub const DNS_RCODE_NXDOMAIN: u8 = 3;
+
+const HEADER_LEN: usize = 12;
+const CLASS_IN: u16 = 1;
+/// 屏蔽应答的 TTL（秒），较短以便专注结束后尽快恢复
+const BLOCKED_TTL: u32 = 60;
+
+/// 按标签长度前缀写入域名，以根标签结尾
+pub(crate) fn encode_name(name: &str, out: &mut Vec<u8>) {
+    for label in name.trim_end_matches('.').split('.') {
+        out.push(label.len() as u8);
+        out.extend_from_slice(label.as_bytes());
+    }
+    out.push(0);
+}
+
+/// 解析出的查询（只含第一个问题）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsQuery {
+    pub id: u16,
+    /// 小写、不含末尾的点
+    pub name: String,
+    pub qtype: u16,
+    /// 头部的 OPCODE 和 RD 位，应答时原样带回
+    flags: u16,
+    /// 问题部分在报文中的结束位置
+    question_end: usize,
+}
+
+impl DnsQuery {
+    /// 生成请求递归解析（RD=1）的查询报文
+    pub fn encode(id: u16, name: &str, qtype: u16) -> Vec<u8> {
+        let mut packet = Vec::with_capacity(HEADER_LEN + name.len() + 6);
+        packet.extend_from_slice(&id.to_be_bytes());
+        packet.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+        encode_name(name, &mut packet);
+        packet.extend_from_slice(&qtype.to_be_bytes());
+        packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+        packet
+    }
+
+    /// 解析客户端发来的查询；应答报文、非标准查询或格式错误时返回 None
+    pub fn parse(packet: &[u8]) -> Option<Self> {
+        if packet.len() < HEADER_LEN {
+            return None;
+        }
+        let id = read_u16(packet, 0)?;
+        let flags = read_u16(packet, 2)?;
+        let qdcount = read_u16(packet, 4)?;
+        // QR=0 且 OPCODE=0（标准查询）
+        if flags & 0xF800 != 0 || qdcount != 1 {
+            return None;
+        }
+
+        let (name, offset) = read_name(packet, HEADER_LEN)?;
+        let qtype = read_u16(packet, offset)?;
+        read_u16(packet, offset + 2)?;
+
+        Some(DnsQuery {
+            id,
+            name,
+            qtype,
+            flags: flags & 0x0100,
+            question_end: offset + 4,
+        })
+    }
+
+    /// 屏蔽应答：A 返回 0.0.0.0，AAAA 返回 ::，其他类型返回 NXDOMAIN
+    pub fn blocked_response(&self, packet: &[u8]) -> Vec<u8> {
+        let rdata: &[u8] = match self.qtype {
+            DNS_TYPE_A => &[0; 4],
+            DNS_TYPE_AAAA => &[0; 16],
+            _ => return self.response(packet, DNS_RCODE_NXDOMAIN, None),
+        };
+        self.response(packet, DNS_RCODE_NOERROR, Some(rdata))
+    }
+
+    /// 带原问题的应答，answer 为 None 时不含记录
+    fn response(&self, packet: &[u8], rcode: u8, answer: Option<&[u8]>) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.question_end + 32);
+        out.extend_from_slice(&self.id.to_be_bytes());
+        // QR=1，RA=1，保留 RD
+        let flags = 0x8080 | self.flags | rcode as u16;
+        out.extend_from_slice(&flags.to_be_bytes());
+        out.extend_from_slice(&1u16.to_be_bytes());
+        out.extend_from_slice(&(answer.is_some() as u16).to_be_bytes());
+        out.extend_from_slice(&[0, 0, 0, 0]);
+        out.extend_from_slice(&packet[HEADER_LEN..self.question_end]);
+
+        if let Some(rdata) = answer {
+            // 名称用指向问题部分的压缩指针
+            out.extend_from_slice(&[0xC0, HEADER_LEN as u8]);
+            out.extend_from_slice(&self.qtype.to_be_bytes());
+            out.extend_from_slice(&CLASS_IN.to_be_bytes());
+            out.extend_from_slice(&BLOCKED_TTL.to_be_bytes());
+            out.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+            out.extend_from_slice(rdata);
+        }
+        out
+    }
+}
+
+/// 应答记录的数据，只解析地址和 CNAME
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DnsRecordData {
+    A(Ipv4Addr),
+    Aaaa(Ipv6Addr),
+    Cname(String),
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsRecord {
+    /// 小写、不含末尾的点
+    pub name: String,
+    pub data: DnsRecordData,
+}
+
+/// 解析出的应答（只含回答部分）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsResponse {
+    pub id: u16,
+    pub rcode: u8,
+    pub answers: Vec<DnsRecord>,
+}
+
+impl DnsResponse {
+    /// 解析上游返回的应答；查询报文或格式错误时返回 None
+    pub fn parse(packet: &[u8]) -> Option<Self> {
+        let id = read_u16(packet, 0)?;
+        let flags = read_u16(packet, 2)?;
+        if flags & 0x8000 == 0 {
+            return None;
+        }
+        let qdcount = read_u16(packet, 4)?;
+        let ancount = read_u16(packet, 6)?;
+
+        let mut offset = HEADER_LEN;
+        for _ in 0..qdcount {
+            let (_, end) = read_name(packet, offset)?;
+            offset = end + 4;
+        }
+
+        let mut answers = Vec::with_capacity(ancount as usize);
+        for _ in 0..ancount {
+            let (name, end) = read_name(packet, offset)?;
+            let rtype = read_u16(packet, end)?;
+            let rdlen = read_u16(packet, end + 8)? as usize;
+            let rdata_start = end + 10;
+            let rdata = packet.get(rdata_start..rdata_start + rdlen)?;
+            let data = match (rtype, rdlen) {
+                (DNS_TYPE_A, 4) => {
+                    DnsRecordData::A(Ipv4Addr::new(rdata[0], rdata[1], rdata[2], rdata[3]))
+                }
+                (DNS_TYPE_AAAA, 16) => {
+                    let bytes: [u8; 16] = rdata.try_into().ok()?;
+                    DnsRecordData::Aaaa(Ipv6Addr::from(bytes))
+                }
+                (DNS_TYPE_CNAME, _) => DnsRecordData::Cname(read_name(packet, rdata_start)?.0),
+                _ => DnsRecordData::Other,
+            };
+            answers.push(DnsRecord { name, data });
+            offset = rdata_start + rdlen;
+        }
+
+        Some(DnsResponse {
+            id,
+            rcode: (flags & 0x000F) as u8,
+            answers,
+        })
+    }
+}
+
+/// 报文中的应答 ID，长度不足时返回 None
+pub fn response_id(packet: &[u8]) -> Option<u16> {
+    read_u16(packet, 0)
+}
+
+fn read_u16(packet: &[u8], offset: usize) -> Option<u16> {
+    let bytes = packet.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+/// 读取域名，支持压缩指针；返回小写域名和名称之后的位置
+fn read_name(packet: &[u8], start: usize) -> Option<(String, usize)> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut offset = start;
+    let mut end = None;
+    // 防止指针成环
+    let mut jumps = 0;
+
+    loop {
+        let len = *packet.get(offset)? as usize;
+        if len & 0xC0 == 0xC0 {
+            let pointer = (read_u16(packet, offset)? & 0x3FFF) as usize;
+            end.get_or_insert(offset + 2);
+            jumps += 1;
+            if jumps > 16 {
+                return None;
+            }
+            offset = pointer;
+            continue;
+        }
+        if len & 0xC0 != 0 {
+            return None;
+        }
+        if len == 0 {
+            let name = labels.join(".");
+            return Some((name, end.unwrap_or(offset + 1)));
+        }
+        let label = packet.get(offset + 1..offset + 1 + len)?;
+        labels.push(String::from_utf8_lossy(label).to_ascii_lowercase());
+        offset += 1 + len;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query() {
+        let packet = DnsQuery::encode(0x1234, "Live.Bilibili.COM", DNS_TYPE_AAAA);
+        let parsed = DnsQuery::parse(&packet).unwrap();
+        assert_eq!(parsed.id, 0x1234);
+        assert_eq!(parsed.name, "live.bilibili.com");
+        assert_eq!(parsed.qtype, DNS_TYPE_AAAA);
+
+        // 应答报文和截断的报文不当作查询
+        let mut response = packet.clone();
+        response[2] |= 0x80;
+        assert!(DnsQuery::parse(&response).is_none());
+        assert!(DnsQuery::parse(&packet[..packet.len() - 1]).is_none());
+    }
+
+    #[test]
+    fn test_blocked_response() {
+        let packet = DnsQuery::encode(7, "a.example.com", DNS_TYPE_A);
+        let response = DnsQuery::parse(&packet).unwrap().blocked_response(&packet);
+        assert_eq!(response_id(&response), Some(7));
+        // QR、RD、RA 置位，NOERROR，一条记录
+        assert_eq!(&response[2..8], &[0x81, 0x80, 0, 1, 0, 1]);
+        assert_eq!(&response[12..packet.len()], &packet[12..]);
+        assert_eq!(&response[response.len() - 6..], &[0, 4, 0, 0, 0, 0]);
+
+        let packet = DnsQuery::encode(8, "a.example.com", 16);
+        let response = DnsQuery::parse(&packet).unwrap().blocked_response(&packet);
+        assert_eq!(response[3] & 0x0F, DNS_RCODE_NXDOMAIN);
+        assert_eq!(&response[6..8], &[0, 0]);
+    }
+
+    #[test]
+    fn test_parse_response_with_cname_chain() {
+        let mut packet = DnsQuery::encode(9, "www.example.com", DNS_TYPE_A);
+        packet[2] |= 0x80;
+        packet[7] = 2;
+        // www.example.com CNAME edge.example.com（后缀用指针指回问题中的 example.com）
+        packet.extend_from_slice(&[0xC0, 12, 0, 5, 0, 1, 0, 0, 0, 60, 0, 7]);
+        let edge = packet.len();
+        packet.extend_from_slice(&[4, b'e', b'd', b'g', b'e', 0xC0, 16]);
+        // edge.example.com A 93.184.216.34
+        packet.extend_from_slice(&[
+            0xC0, edge as u8, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4, 93, 184, 216, 34,
+        ]);
+
+        let response = DnsResponse::parse(&packet).unwrap();
+        assert_eq!(response.id, 9);
+        assert_eq!(response.rcode, DNS_RCODE_NOERROR);
+        assert_eq!(
+            response.answers,
+            vec![
+                DnsRecord {
+                    name: "www.example.com".to_string(),
+                    data: DnsRecordData::Cname("edge.example.com".to_string()),
+                },
+                DnsRecord {
+                    name: "edge.example.com".to_string(),
+                    data: DnsRecordData::A(Ipv4Addr::new(93, 184, 216, 34)),
+                },
+            ]
+        );
+
+        // 查询报文不是应答
+        assert!(DnsResponse::parse(&DnsQuery::encode(1, "a.com", DNS_TYPE_A)).is_none());
+    }
+
+    #[test]
+    fn test_read_name_rejects_pointer_loop() {
+        let mut packet = DnsQuery::encode(1, "a.com", DNS_TYPE_A);
+        packet.truncate(HEADER_LEN);
+        packet.extend_from_slice(&[0xC0, HEADER_LEN as u8]);
+        assert!(read_name(&packet, HEADER_LEN).is_none());
+    }
+}
@@ -0,0 +1,430 @@
+use crate::services::{
+    DnsQuery, DnsRecordData, DnsResponse, DNS_RCODE_NOERROR, DNS_RCODE_NXDOMAIN, DNS_TYPE_A,
+    DNS_TYPE_AAAA,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicU16, AtomicUsize, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// 单次查询等待应答的超时
+const QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+/// 同时解析的域名数上限
+const MAX_WORKERS: usize = 16;
+/// CNAME 链最多跟随的层数
+const MAX_CNAME_DEPTH: usize = 8;
+const MAX_PACKET_LEN: usize = 4096;
+const RESOLV_CONF_PATH: &str = "/etc/resolv.conf";
+
+/// 查询 ID，进程内递增即可
+static NEXT_QUERY_ID: AtomicU16 = AtomicU16::new(1);
+
+/// 设置后 system() 使用这些服务器，不读取 /etc/resolv.conf
+static SYSTEM_OVERRIDE: RwLock<Option<Vec<SocketAddr>>> = RwLock::new(None);
+
+/// 单个域名的解析结果：地址或失败原因
+type DomainResult = Result<Vec<IpAddr>, String>;
+
+/// 解析失败的域名及原因
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResolveFailure {
+    pub domain: String,
+    pub reason: String,
+}
+
+/// 批量解析的结果：成功解析到的全部 IP 和各域名的失败原因
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResolveReport {
+    pub ips: HashSet<IpAddr>,
+    pub failures: Vec<ResolveFailure>,
+}
+
+/// 进程内的 DNS 解析：直接向上游发 UDP 查询，不依赖 dig 等外部命令
+///
+/// 有多个服务器时按顺序尝试，超时或出错时换下一个，之后的查询从上次应答的服务器开始
+pub struct DnsResolver {
+    upstreams: Vec<SocketAddr>,
+    preferred: AtomicUsize,
+    timeout: Duration,
+}
+
+impl DnsResolver {
+    pub fn new(upstream: SocketAddr) -> Self {
+        DnsResolver {
+            upstreams: vec![upstream],
+            preferred: AtomicUsize::new(0),
+            timeout: QUERY_TIMEOUT,
+        }
+    }
+
+    /// 依次使用多个服务器，列表为空时返回 None
+    pub fn with_servers(upstreams: Vec<SocketAddr>) -> Option<Self> {
+        (!upstreams.is_empty()).then(|| DnsResolver {
+            upstreams,
+            preferred: AtomicUsize::new(0),
+            timeout: QUERY_TIMEOUT,
+        })
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 使用系统的 DNS 服务器：设置了覆盖时用覆盖的列表，否则为 /etc/resolv.conf 中的全部服务器
+    pub fn system() -> Option<Self> {
+        let servers = SYSTEM_OVERRIDE
+            .read()
+            .unwrap()
+            .clone()
+            .unwrap_or_else(Self::system_nameservers);
+        Self::with_servers(servers)
+    }
+
+    /// 覆盖 system() 使用的服务器，传 None 取消覆盖
+    ///
+    /// 启用本地 DNS 转发时系统 DNS 指向转发本身，它对屏蔽的域名只返回 0.0.0.0，需直接查询它的上游
+    pub fn set_system_override(servers: Option<Vec<SocketAddr>>) {
+        *SYSTEM_OVERRIDE.write().unwrap() = servers;
+    }
+
+    /// /etc/resolv.conf 中的 DNS 服务器（端口 53），读取失败时为空
+    pub fn system_nameservers() -> Vec<SocketAddr> {
+        fs::read_to_string(RESOLV_CONF_PATH)
+            .map(|content| Self::parse_resolv_conf(&content))
+            .unwrap_or_default()
+    }
+
+    fn parse_resolv_conf(content: &str) -> Vec<SocketAddr> {
+        content
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("nameserver"))
+            .filter_map(|rest| rest.trim().parse::<IpAddr>().ok())
+            .map(|ip| SocketAddr::new(ip, 53))
+            .collect()
+    }
+
+    /// 并发解析所有域名（最多 MAX_WORKERS 个线程），结果按输入顺序汇总
+    pub fn resolve_all(&self, domains: &[String]) -> ResolveReport {
+        let results: Mutex<Vec<Option<DomainResult>>> = Mutex::new(vec![None; domains.len()]);
+        let next = AtomicUsize::new(0);
+
+        thread::scope(|scope| {
+            for _ in 0..domains.len().min(MAX_WORKERS) {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(domain) = domains.get(index) else {
+                        break;
+                    };
+                    let result = self.resolve(domain);
+                    results.lock().unwrap()[index] = Some(result);
+                });
+            }
+        });
+
+        let mut report = ResolveReport::default();
+        for (domain, result) in domains.iter().zip(results.into_inner().unwrap()) {
+            match result {
+                Some(Ok(ips)) => report.ips.extend(ips),
+                Some(Err(reason)) => report.failures.push(ResolveFailure {
+                    domain: domain.clone(),
+                    reason,
+                }),
+                None => {}
+            }
+        }
+        report
+    }
+
+    /// 解析单个域名的 IPv4 和 IPv6 地址；两类查询都失败或都没有地址时返回原因
+    pub fn resolve(&self, domain: &str) -> DomainResult {
+        let mut ips = Vec::new();
+        let mut errors = Vec::new();
+        for qtype in [DNS_TYPE_A, DNS_TYPE_AAAA] {
+            match self.lookup(domain, qtype) {
+                Ok(found) => ips.extend(found),
+                Err(e) => errors.push(e),
+            }
+        }
+
+        // 0.0.0.0 和 :: 是其他屏蔽工具（或本地 DNS 转发）的应答，不是真实地址
+        ips.retain(|ip| !ip.is_unspecified());
+        if !ips.is_empty() {
+            return Ok(ips);
+        }
+        match errors.first() {
+            Some(e) => Err(e.clone()),
+            None => Err("没有地址记录".to_string()),
+        }
+    }
+
+    /// 查询一种地址记录，CNAME 的目标不在同一应答中时继续查询目标
+    fn lookup(&self, domain: &str, qtype: u16) -> Result<Vec<IpAddr>, String> {
+        let mut name = domain.trim_end_matches('.').to_lowercase();
+
+        for _ in 0..MAX_CNAME_DEPTH {
+            let response = self.query(&name, qtype)?;
+            match response.rcode {
+                DNS_RCODE_NOERROR => {}
+                DNS_RCODE_NXDOMAIN => return Err("域名不存在".to_string()),
+                rcode => return Err(format!("DNS 服务器返回错误 {}", rcode)),
+            }
+
+            let (target, ips) = Self::follow_chain(&name, &response);
+            if !ips.is_empty() || target == name {
+                return Ok(ips);
+            }
+            name = target;
+        }
+
+        Err("CNAME 层数过多".to_string())
+    }
+
+    /// 在应答中沿 CNAME 链从 name 走到最终名称，返回最终名称和它的地址
+    fn follow_chain(name: &str, response: &DnsResponse) -> (String, Vec<IpAddr>) {
+        let mut current = name.to_string();
+        let mut visited = vec![current.clone()];
+
+        loop {
+            let next = response
+                .answers
+                .iter()
+                .find_map(|record| match &record.data {
+                    DnsRecordData::Cname(target) if record.name == current => Some(target.clone()),
+                    _ => None,
+                });
+            match next {
+                Some(target) if !visited.contains(&target) => {
+                    visited.push(target.clone());
+                    current = target;
+                }
+                _ => break,
+            }
+        }
+
+        let ips = response
+            .answers
+            .iter()
+            .filter(|record| record.name == current)
+            .filter_map(|record| match record.data {
+                DnsRecordData::A(ip) => Some(IpAddr::V4(ip)),
+                DnsRecordData::Aaaa(ip) => Some(IpAddr::V6(ip)),
+                _ => None,
+            })
+            .collect();
+        (current, ips)
+    }
+
+    /// 从上次应答的服务器开始依次查询，返回第一个应答；全部失败时返回最后一个错误
+    fn query(&self, name: &str, qtype: u16) -> Result<DnsResponse, String> {
+        let start = self.preferred.load(Ordering::Relaxed);
+        let mut last_error = String::new();
+        for offset in 0..self.upstreams.len() {
+            let index = (start + offset) % self.upstreams.len();
+            match self.query_server(self.upstreams[index], name, qtype) {
+                Ok(response) => {
+                    self.preferred.store(index, Ordering::Relaxed);
+                    return Ok(response);
+                }
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+
+    /// 向一个服务器发送一次查询并等待同 ID 的应答
+    fn query_server(
+        &self,
+        upstream: SocketAddr,
+        name: &str,
+        qtype: u16,
+    ) -> Result<DnsResponse, String> {
+        let bind: SocketAddr = match upstream {
+            SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+        };
+        let socket = UdpSocket::bind(bind).map_err(|e| format!("创建查询端口失败: {}", e))?;
+        socket
+            .connect(upstream)
+            .map_err(|e| format!("连接 DNS 服务器失败: {}", e))?;
+
+        let id = NEXT_QUERY_ID.fetch_add(1, Ordering::Relaxed);
+        socket
+            .send(&DnsQuery::encode(id, name, qtype))
+            .map_err(|e| format!("发送查询失败: {}", e))?;
+
+        let deadline = Instant::now() + self.timeout;
+        let mut buf = [0u8; MAX_PACKET_LEN];
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err("查询超时".to_string());
+            }
+            socket
+                .set_read_timeout(Some(remaining))
+                .map_err(|e| e.to_string())?;
+            let len = match socket.recv(&mut buf) {
+                Ok(len) => len,
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    return Err("查询超时".to_string());
+                }
+                Err(e) => return Err(format!("接收应答失败: {}", e)),
+            };
+            match DnsResponse::parse(&buf[..len]) {
+                Some(response) if response.id == id => return Ok(response),
+                _ => continue,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::dns::encode_name;
+
+    fn answer(name_ptr: u8, rtype: u16, rdata: &[u8]) -> Vec<u8> {
+        let mut record = vec![0xC0, name_ptr];
+        record.extend_from_slice(&rtype.to_be_bytes());
+        record.extend_from_slice(&[0, 1, 0, 0, 0, 60]);
+        record.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        record.extend_from_slice(rdata);
+        record
+    }
+
+    fn name(name: &str) -> Vec<u8> {
+        let mut out = Vec::new();
+        encode_name(name, &mut out);
+        out
+    }
+
+    /// 本地桩 DNS：
+    /// - cdn.example.com 是指向 edge.example.net 的 CNAME，应答中不带目标地址
+    /// - edge.example.net 有 A 和 AAAA 记录
+    /// - v4.example.com 只有 A 记录
+    /// - missing.example.com 不存在，slow.example.com 不应答
+    fn stub_upstream() -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut buf = [0u8; MAX_PACKET_LEN];
+            while let Ok((len, client)) = socket.recv_from(&mut buf) {
+                let query = DnsQuery::parse(&buf[..len]).unwrap();
+                let mut response = buf[..len].to_vec();
+                response[2] |= 0x80;
+                let records: Vec<Vec<u8>> = match (query.name.as_str(), query.qtype) {
+                    ("slow.example.com", _) => continue,
+                    ("missing.example.com", _) => {
+                        response[3] |= DNS_RCODE_NXDOMAIN;
+                        Vec::new()
+                    }
+                    ("cdn.example.com", _) => {
+                        vec![answer(12, 5, &name("edge.example.net"))]
+                    }
+                    ("edge.example.net", DNS_TYPE_A) => vec![answer(12, 1, &[1, 2, 3, 4])],
+                    ("edge.example.net", DNS_TYPE_AAAA) => {
+                        let ip: Ipv6Addr = "2001:db8::1".parse().unwrap();
+                        vec![answer(12, 28, &ip.octets())]
+                    }
+                    ("v4.example.com", DNS_TYPE_A) => vec![answer(12, 1, &[5, 6, 7, 8])],
+                    _ => Vec::new(),
+                };
+                response[7] = records.len() as u8;
+                response.extend(records.concat());
+                let _ = socket.send_to(&response, client);
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn test_resolve_follows_cname_for_both_families() {
+        let resolver = DnsResolver::new(stub_upstream());
+        let ips = resolver.resolve("cdn.example.com").unwrap();
+        assert_eq!(
+            ips,
+            vec![
+                "1.2.3.4".parse::<IpAddr>().unwrap(),
+                "2001:db8::1".parse().unwrap()
+            ]
+        );
+        assert_eq!(
+            resolver.resolve("v4.example.com").unwrap(),
+            vec!["5.6.7.8".parse::<IpAddr>().unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_resolve_all_reports_failures() {
+        let resolver = DnsResolver::new(stub_upstream()).with_timeout(Duration::from_millis(200));
+        let domains: Vec<String> = [
+            "cdn.example.com",
+            "missing.example.com",
+            "slow.example.com",
+            "empty.example.com",
+            "v4.example.com",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        let report = resolver.resolve_all(&domains);
+        assert_eq!(report.ips.len(), 3);
+        assert!(report.ips.contains(&"5.6.7.8".parse().unwrap()));
+        assert_eq!(
+            report.failures,
+            vec![
+                ResolveFailure {
+                    domain: "missing.example.com".to_string(),
+                    reason: "域名不存在".to_string(),
+                },
+                ResolveFailure {
+                    domain: "slow.example.com".to_string(),
+                    reason: "查询超时".to_string(),
+                },
+                ResolveFailure {
+                    domain: "empty.example.com".to_string(),
+                    reason: "没有地址记录".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_falls_back_to_next_server() {
+        // 没有服务在监听的端口：发送后收不到应答
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let resolver =
+            DnsResolver::with_servers(vec![silent.local_addr().unwrap(), stub_upstream()])
+                .unwrap()
+                .with_timeout(Duration::from_millis(200));
+        assert_eq!(
+            resolver.resolve("v4.example.com").unwrap(),
+            vec!["5.6.7.8".parse::<IpAddr>().unwrap()]
+        );
+        // 之后从应答过的服务器开始
+        assert_eq!(resolver.preferred.load(Ordering::Relaxed), 1);
+        assert!(DnsResolver::with_servers(Vec::new()).is_none());
+    }
+
+    #[test]
+    fn test_parse_resolv_conf() {
+        let content = "# generated\nnameserver 192.168.1.1\nsearch lan\nnameserver fe80::1\n";
+        assert_eq!(
+            DnsResolver::parse_resolv_conf(content),
+            vec![
+                "192.168.1.1:53".parse::<SocketAddr>().unwrap(),
+                "[fe80::1]:53".parse().unwrap()
+            ]
+        );
+    }
+}
@@ -0,0 +1,363 @@
+use crate::errors::AppError;
+use crate::services::{response_id, DnsQuery, DnsResolver, HostMatcher, SiteRules};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// 轮询停止信号的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+/// 等待上游应答的超时
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(3);
+/// UDP DNS 报文（含 EDNS）的最大长度
+const MAX_PACKET_LEN: usize = 4096;
+/// 转发查询的工作线程数
+const FORWARD_WORKERS: usize = 8;
+/// 等待转发的查询上限，超过时丢弃（客户端会重试）
+const FORWARD_QUEUE_LEN: usize = 256;
+
+/// 等待转发的查询：原始报文、解析结果和客户端地址
+type ForwardJob = (Vec<u8>, DnsQuery, SocketAddr);
+
+/// 本地 DNS 转发：按网站规则屏蔽域名及其所有子域名，其余查询转发给上游
+///
+/// hosts 无法表达 `*.example.com` 或"只允许某些网站"，系统 DNS 指向本服务后两者都能生效。
+/// 启用后常驻运行，专注之外规则为空、只做转发，系统 DNS 不会因专注结束而失效
+pub struct DnsSinkhole {
+    listen: SocketAddr,
+    upstream: SocketAddr,
+    local_addr: SocketAddr,
+    matcher: Arc<RwLock<HostMatcher>>,
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl DnsSinkhole {
+    /// 监听 listen 并在后台线程处理查询
+    pub fn start(
+        listen: SocketAddr,
+        upstream: SocketAddr,
+        rules: &SiteRules,
+    ) -> Result<Self, AppError> {
+        let socket = UdpSocket::bind(listen)
+            .map_err(|e| AppError::BlockerError(format!("本地 DNS 监听 {} 失败: {}", listen, e)))?;
+        socket.set_read_timeout(Some(POLL_INTERVAL))?;
+        let local_addr = socket.local_addr()?;
+
+        let matcher = Arc::new(RwLock::new(rules.host_matcher()));
+        let running = Arc::new(AtomicBool::new(true));
+        println!("[DnsSinkhole] 监听 {}，上游 {}", local_addr, upstream);
+        Self::log_rules(&matcher.read().unwrap());
+
+        let flag = Arc::clone(&running);
+        let rules = Arc::clone(&matcher);
+        let handle = thread::spawn(move || Self::serve(socket, upstream, &rules, &flag));
+
+        Ok(DnsSinkhole {
+            listen,
+            upstream,
+            local_addr,
+            matcher,
+            running,
+            handle: Some(handle),
+        })
+    }
+
+    /// 替换屏蔽规则，之后的查询立即按新规则判断
+    pub fn set_rules(&self, rules: &SiteRules) {
+        let matcher = rules.host_matcher();
+        Self::log_rules(&matcher);
+        *self.matcher.write().unwrap() = matcher;
+    }
+
+    fn log_rules(matcher: &HostMatcher) {
+        if matcher.is_allowlist() {
+            println!("[DnsSinkhole] 只放行 {} 个域名", matcher.host_count());
+        } else if matcher.host_count() == 0 {
+            println!("[DnsSinkhole] 无屏蔽规则，只转发");
+        } else {
+            println!("[DnsSinkhole] 屏蔽 {} 个域名", matcher.host_count());
+        }
+    }
+
+    /// 启动时指定的监听地址
+    pub fn listen(&self) -> SocketAddr {
+        self.listen
+    }
+
+    pub fn upstream(&self) -> SocketAddr {
+        self.upstream
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// 接收查询：屏蔽的直接应答，其余交给固定数量的工作线程转发，退出前等待工作线程结束
+    fn serve(
+        socket: UdpSocket,
+        upstream: SocketAddr,
+        matcher: &RwLock<HostMatcher>,
+        running: &AtomicBool,
+    ) {
+        let socket = Arc::new(socket);
+        let (jobs, queue) = mpsc::sync_channel::<ForwardJob>(FORWARD_QUEUE_LEN);
+        let queue = Arc::new(Mutex::new(queue));
+        let workers: Vec<_> = (0..FORWARD_WORKERS)
+            .map(|_| {
+                let socket = Arc::clone(&socket);
+                let queue = Arc::clone(&queue);
+                thread::spawn(move || Self::forward_worker(&socket, &queue, upstream))
+            })
+            .collect();
+
+        let mut buf = [0u8; MAX_PACKET_LEN];
+        while running.load(Ordering::SeqCst) {
+            let (len, client) = match socket.recv_from(&mut buf) {
+                Ok(received) => received,
+                Err(_) => continue,
+            };
+            let packet = buf[..len].to_vec();
+            let Some(query) = DnsQuery::parse(&packet) else {
+                continue;
+            };
+
+            if matcher.read().unwrap().is_blocked(&query.name) {
+                let _ = socket.send_to(&query.blocked_response(&packet), client);
+                continue;
+            }
+
+            // 转发可能要等到超时，交给工作线程，不阻塞其他查询
+            if let Err(mpsc::TrySendError::Full((_, query, _))) =
+                jobs.try_send((packet, query, client))
+            {
+                println!("[DnsSinkhole] 转发队列已满，丢弃 {} 的查询", query.name);
+            }
+        }
+
+        drop(jobs);
+        for worker in workers {
+            let _ = worker.join();
+        }
+    }
+
+    /// 依次取出等待转发的查询，队列关闭时退出
+    fn forward_worker(
+        socket: &UdpSocket,
+        queue: &Mutex<Receiver<ForwardJob>>,
+        upstream: SocketAddr,
+    ) {
+        loop {
+            let job = queue.lock().unwrap().recv();
+            let Ok((packet, query, client)) = job else {
+                return;
+            };
+            match Self::forward(&packet, query.id, upstream) {
+                Ok(response) => {
+                    let _ = socket.send_to(&response, client);
+                }
+                Err(e) => println!("[DnsSinkhole] 转发 {} 失败: {}", query.name, e),
+            }
+        }
+    }
+
+    /// 停止监听并等待线程退出
+    pub fn stop(mut self) {
+        self.shutdown();
+        println!("[DnsSinkhole] 已停止");
+    }
+
+    fn shutdown(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+
+    /// 把查询原样发给上游并等待同 ID 的应答
+    fn forward(packet: &[u8], id: u16, upstream: SocketAddr) -> std::io::Result<Vec<u8>> {
+        let bind: SocketAddr = match upstream {
+            SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+        };
+        let socket = UdpSocket::bind(bind)?;
+        socket.connect(upstream)?;
+        socket.set_read_timeout(Some(UPSTREAM_TIMEOUT))?;
+        socket.send(packet)?;
+
+        let mut buf = [0u8; MAX_PACKET_LEN];
+        loop {
+            let len = socket.recv(&mut buf)?;
+            if response_id(&buf[..len]) == Some(id) {
+                return Ok(buf[..len].to_vec());
+            }
+        }
+    }
+
+    /// 解析上游地址：IP 或 IP:端口，省略端口时为 53
+    pub fn parse_upstream(value: &str) -> Option<SocketAddr> {
+        let value = value.trim();
+        value.parse::<SocketAddr>().ok().or_else(|| {
+            value
+                .parse::<IpAddr>()
+                .ok()
+                .map(|ip| SocketAddr::new(ip, 53))
+        })
+    }
+
+    /// 上游是否就是监听地址本身（127.0.0.1 与 ::1 视为同一台本机），转发给它会形成循环
+    pub fn loops_back(listen: SocketAddr, upstream: SocketAddr) -> bool {
+        let localhost = |ip: IpAddr| ip == Ipv4Addr::LOCALHOST || ip == Ipv6Addr::LOCALHOST;
+        upstream.port() == listen.port()
+            && (upstream.ip() == listen.ip()
+                || (localhost(upstream.ip()) && localhost(listen.ip())))
+    }
+
+    /// 从 /etc/resolv.conf 读取系统的上游 DNS，跳过本服务自身的监听地址
+    pub fn system_upstream(listen: SocketAddr) -> Option<SocketAddr> {
+        DnsResolver::system_nameservers()
+            .into_iter()
+            .find(|addr| !Self::loops_back(listen, *addr))
+    }
+}
+
+impl Drop for DnsSinkhole {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 对任何查询都回答 A 1.2.3.4 的上游
+    fn stub_upstream() -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut buf = [0u8; MAX_PACKET_LEN];
+            while let Ok((len, client)) = socket.recv_from(&mut buf) {
+                let mut response = buf[..len].to_vec();
+                response[2] |= 0x80;
+                response[7] = 1;
+                response.extend_from_slice(&[0xC0, 12, 0, 1, 0, 1, 0, 0, 0, 30, 0, 4, 1, 2, 3, 4]);
+                let _ = socket.send_to(&response, client);
+            }
+        });
+        addr
+    }
+
+    fn ask(server: SocketAddr, packet: &[u8]) -> Vec<u8> {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        socket.send_to(packet, server).unwrap();
+        let mut buf = [0u8; MAX_PACKET_LEN];
+        let (len, _) = socket.recv_from(&mut buf).unwrap();
+        buf[..len].to_vec()
+    }
+
+    #[test]
+    fn test_sinkhole_blocks_and_forwards() {
+        let upstream = stub_upstream();
+        let sinkhole = DnsSinkhole::start(
+            "127.0.0.1:0".parse().unwrap(),
+            upstream,
+            &SiteRules::Blocklist(vec!["bilibili.com".to_string()]),
+        )
+        .unwrap();
+        let server = sinkhole.local_addr();
+
+        // 屏蔽的子域名：A 返回 0.0.0.0，AAAA 返回 ::
+        let response = ask(server, &DnsQuery::encode(1, "live.bilibili.com", 1));
+        assert_eq!(response_id(&response), Some(1));
+        assert_eq!(&response[response.len() - 4..], &[0, 0, 0, 0]);
+        let response = ask(server, &DnsQuery::encode(2, "m.bilibili.com", 28));
+        assert_eq!(
+            &response[response.len() - 18..response.len() - 16],
+            &[0, 16]
+        );
+        // 其他记录类型返回 NXDOMAIN
+        let response = ask(server, &DnsQuery::encode(3, "bilibili.com", 16));
+        assert_eq!(response[3] & 0x0F, 3);
+
+        // 未屏蔽的域名转发给上游
+        let response = ask(server, &DnsQuery::encode(4, "notbilibili.com", 1));
+        assert_eq!(response_id(&response), Some(4));
+        assert_eq!(&response[response.len() - 4..], &[1, 2, 3, 4]);
+
+        sinkhole.stop();
+    }
+
+    #[test]
+    fn test_sinkhole_allowlist_forwards_only_allowed() {
+        let upstream = stub_upstream();
+        let sinkhole = DnsSinkhole::start(
+            "127.0.0.1:0".parse().unwrap(),
+            upstream,
+            &SiteRules::Allowlist(vec!["docs.rs".to_string()]),
+        )
+        .unwrap();
+        let server = sinkhole.local_addr();
+
+        let response = ask(server, &DnsQuery::encode(1, "static.docs.rs", 1));
+        assert_eq!(&response[response.len() - 4..], &[1, 2, 3, 4]);
+        let response = ask(server, &DnsQuery::encode(2, "bilibili.com", 1));
+        assert_eq!(&response[response.len() - 4..], &[0, 0, 0, 0]);
+
+        sinkhole.stop();
+    }
+
+    #[test]
+    fn test_set_rules_switches_between_forwarding_and_blocking() {
+        let upstream = stub_upstream();
+        let sinkhole = DnsSinkhole::start(
+            "127.0.0.1:0".parse().unwrap(),
+            upstream,
+            &SiteRules::default(),
+        )
+        .unwrap();
+        let server = sinkhole.local_addr();
+
+        let response = ask(server, &DnsQuery::encode(1, "bilibili.com", 1));
+        assert_eq!(&response[response.len() - 4..], &[1, 2, 3, 4]);
+
+        sinkhole.set_rules(&SiteRules::Blocklist(vec!["bilibili.com".to_string()]));
+        let response = ask(server, &DnsQuery::encode(2, "www.bilibili.com", 1));
+        assert_eq!(&response[response.len() - 4..], &[0, 0, 0, 0]);
+
+        // 专注结束后恢复为只转发
+        sinkhole.set_rules(&SiteRules::default());
+        let response = ask(server, &DnsQuery::encode(3, "www.bilibili.com", 1));
+        assert_eq!(&response[response.len() - 4..], &[1, 2, 3, 4]);
+
+        sinkhole.stop();
+    }
+
+    #[test]
+    fn test_parse_upstream() {
+        assert_eq!(
+            DnsSinkhole::parse_upstream("[2001:db8::1]:5353"),
+            Some("[2001:db8::1]:5353".parse().unwrap())
+        );
+        assert_eq!(
+            DnsSinkhole::parse_upstream("8.8.8.8"),
+            Some("8.8.8.8:53".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_loops_back_to_listen_address() {
+        let listen: SocketAddr = "127.0.0.1:53".parse().unwrap();
+        for upstream in ["127.0.0.1:53", "[::1]:53"] {
+            assert!(DnsSinkhole::loops_back(listen, upstream.parse().unwrap()));
+        }
+        for upstream in ["127.0.0.53:53", "127.0.0.1:5353", "8.8.8.8:53"] {
+            assert!(!DnsSinkhole::loops_back(listen, upstream.parse().unwrap()));
+        }
+    }
+}
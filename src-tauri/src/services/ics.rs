@@ -0,0 +1,665 @@
+use chrono::{
+    Datelike, Duration as ChronoDuration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday,
+};
+use std::fs;
+use std::path::Path;
+
+use crate::errors::AppError;
+
+/// 单个事件最多展开的天数，防止错误的 DTEND 产生海量日期
+const MAX_EVENT_DAYS: i64 = 366;
+/// 计算 COUNT 规则的最后一次日期时最多向后枚举的天数
+const MAX_RECURRENCE_DAYS: i64 = 366 * 20;
+
+/// 属性参数列表，如 `VALUE=DATE`、`TZID=Asia/Shanghai`
+type IcsParams = Vec<(String, String)>;
+
+/// iCalendar 中的时间：全天事件只有日期，其余统一换算为本地时间
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IcsTime {
+    Date(NaiveDate),
+    DateTime(NaiveDateTime),
+}
+
+impl IcsTime {
+    pub fn date(&self) -> NaiveDate {
+        match self {
+            IcsTime::Date(date) => *date,
+            IcsTime::DateTime(datetime) => datetime.date(),
+        }
+    }
+
+    pub fn is_all_day(&self) -> bool {
+        matches!(self, IcsTime::Date(_))
+    }
+}
+
+/// 解析出的 VEVENT，只保留专注调度需要的字段
+#[derive(Debug, Clone, PartialEq)]
+pub struct IcsEvent {
+    pub summary: String,
+    pub categories: Vec<String>,
+    pub start: IcsTime,
+    pub end: Option<IcsTime>,
+    pub rrule: Option<RecurrenceRule>,
+    pub exdates: Vec<IcsTime>,
+}
+
+impl IcsEvent {
+    /// 事件（或其某次重复）是否在给定日期开始
+    pub fn starts_on(&self, date: NaiveDate) -> bool {
+        let first = self.start.date();
+        let occurs = match &self.rrule {
+            Some(rule) => rule.occurs_on(first, date),
+            None => date == first,
+        };
+        occurs && !self.exdates.iter().any(|exdate| exdate.date() == date)
+    }
+
+    /// 事件在给定日期开始的那一次的起止时间；全天事件和零时长事件返回 None
+    pub fn occurrence_on(&self, date: NaiveDate) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        let IcsTime::DateTime(start) = self.start else {
+            return None;
+        };
+        let Some(IcsTime::DateTime(end)) = self.end else {
+            return None;
+        };
+        if end <= start || !self.starts_on(date) {
+            return None;
+        }
+
+        let occurrence_start = date.and_time(start.time());
+        Some((occurrence_start, occurrence_start + (end - start)))
+    }
+
+    /// 事件覆盖的日期；全天事件的 DTEND 不含当天
+    pub fn dates(&self) -> Vec<NaiveDate> {
+        let first = self.start.date();
+        let last = match self.end {
+            Some(IcsTime::Date(end)) => end - ChronoDuration::days(1),
+            Some(IcsTime::DateTime(end)) if end.time() == chrono::NaiveTime::MIN => {
+                end.date() - ChronoDuration::days(1)
+            }
+            Some(IcsTime::DateTime(end)) => end.date(),
+            None => first,
+        };
+        let last = last.max(first).min(first + ChronoDuration::days(MAX_EVENT_DAYS - 1));
+
+        first
+            .iter_days()
+            .take_while(|date| *date <= last)
+            .collect()
+    }
+}
+
+/// 本地 .ics 文件的最小解析器（RFC 5545 的 VEVENT 子集）
+#[derive(Debug, Clone, Default)]
+pub struct IcsCalendar {
+    pub events: Vec<IcsEvent>,
+}
+
+impl IcsCalendar {
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        let content = fs::read_to_string(path)?;
+        Self::parse(&content)
+    }
+
+    /// 解析日历文本；带 TZID 的时间按本地时间处理，UTC 时间（Z 结尾）换算为本地时间
+    pub fn parse(content: &str) -> Result<Self, AppError> {
+        let lines = Self::unfold(content);
+        if !lines.iter().any(|l| l.eq_ignore_ascii_case("BEGIN:VCALENDAR")) {
+            return Err(AppError::ConfigError("不是有效的 iCalendar 文件".to_string()));
+        }
+
+        let mut events = Vec::new();
+        let mut current: Option<EventBuilder> = None;
+        // VEVENT 内嵌套的组件（如 VALARM）的层数，其属性不属于事件本身
+        let mut nested = 0;
+
+        for line in &lines {
+            let Some((name, params, value)) = Self::split_property(line) else {
+                continue;
+            };
+
+            match (name.as_str(), current.is_some()) {
+                ("BEGIN", false) if value.eq_ignore_ascii_case("VEVENT") => {
+                    current = Some(EventBuilder::default());
+                    nested = 0;
+                }
+                ("BEGIN", true) => nested += 1,
+                ("END", true) if nested > 0 => nested -= 1,
+                ("END", true) if value.eq_ignore_ascii_case("VEVENT") => {
+                    if let Some(event) = current.take().and_then(EventBuilder::build) {
+                        events.push(event);
+                    }
+                }
+                (_, true) if nested == 0 => {
+                    if let Some(builder) = current.as_mut() {
+                        builder.set(&name, &params, &value);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(IcsCalendar { events })
+    }
+
+    /// 所有事件覆盖的日期及事件名称（用于导入节假日），按日期排序去重
+    ///
+    /// 重复事件（如每年的节日）展开到 until 为止
+    pub fn holiday_dates(&self, until: NaiveDate) -> Vec<(NaiveDate, String)> {
+        let mut dates: Vec<(NaiveDate, String)> = Vec::new();
+
+        for event in &self.events {
+            let span = event.dates().len() as i64;
+            let starts: Vec<NaiveDate> = if event.rrule.is_some() {
+                event
+                    .start
+                    .date()
+                    .iter_days()
+                    .take_while(|date| *date <= until)
+                    .filter(|date| event.starts_on(*date))
+                    .collect()
+            } else {
+                vec![event.start.date()]
+            };
+
+            for start in starts {
+                for offset in 0..span {
+                    dates.push((start + ChronoDuration::days(offset), event.summary.clone()));
+                }
+            }
+        }
+
+        dates.sort();
+        dates.dedup_by(|a, b| a.0 == b.0);
+        dates
+    }
+
+    /// 展开折行：以空格或制表符开头的行是上一行的延续
+    fn unfold(content: &str) -> Vec<String> {
+        let mut lines: Vec<String> = Vec::new();
+        for raw in content.lines() {
+            let raw = raw.trim_end_matches('\r');
+            if let Some(rest) = raw.strip_prefix([' ', '\t']) {
+                if let Some(last) = lines.last_mut() {
+                    last.push_str(rest);
+                    continue;
+                }
+            }
+            if !raw.is_empty() {
+                lines.push(raw.to_string());
+            }
+        }
+        lines
+    }
+
+    /// 拆分 `NAME;PARAM=VALUE:VALUE`，参数值中的引号内可以包含冒号
+    fn split_property(line: &str) -> Option<(String, IcsParams, String)> {
+        let mut in_quotes = false;
+        let colon = line.char_indices().find_map(|(i, c)| {
+            match c {
+                '"' => in_quotes = !in_quotes,
+                ':' if !in_quotes => return Some(i),
+                _ => {}
+            }
+            None
+        })?;
+
+        let (head, value) = (&line[..colon], &line[colon + 1..]);
+        let mut parts = head.split(';');
+        let name = parts.next()?.trim().to_ascii_uppercase();
+        let params = parts
+            .filter_map(|p| p.split_once('='))
+            .map(|(k, v)| (k.trim().to_ascii_uppercase(), v.trim_matches('"').to_string()))
+            .collect();
+
+        Some((name, params, value.to_string()))
+    }
+
+    fn parse_time(params: &[(String, String)], value: &str) -> Option<IcsTime> {
+        let value = value.trim();
+        let is_date = params.iter().any(|(k, v)| k == "VALUE" && v.eq_ignore_ascii_case("DATE"))
+            || value.len() == 8;
+
+        if is_date {
+            return NaiveDate::parse_from_str(value, "%Y%m%d").ok().map(IcsTime::Date);
+        }
+
+        if let Some(utc) = value.strip_suffix(['Z', 'z']) {
+            let naive = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+            let local = Utc.from_utc_datetime(&naive).with_timezone(&Local);
+            return Some(IcsTime::DateTime(local.naive_local()));
+        }
+
+        NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+            .ok()
+            .map(IcsTime::DateTime)
+    }
+
+    /// 解析 DURATION（如 `PT1H30M`、`P1D`、`P1W`），不支持负值
+    fn parse_duration(value: &str) -> Option<ChronoDuration> {
+        let rest = value.trim().strip_prefix('P')?;
+        let mut total = ChronoDuration::zero();
+        let mut number = String::new();
+        let mut in_time = false;
+
+        for c in rest.chars() {
+            match c {
+                'T' => in_time = true,
+                '0'..='9' => number.push(c),
+                unit => {
+                    let n: i64 = number.parse().ok()?;
+                    number.clear();
+                    total += match (unit, in_time) {
+                        ('W', false) => ChronoDuration::weeks(n),
+                        ('D', false) => ChronoDuration::days(n),
+                        ('H', true) => ChronoDuration::hours(n),
+                        ('M', true) => ChronoDuration::minutes(n),
+                        ('S', true) => ChronoDuration::seconds(n),
+                        _ => return None,
+                    };
+                }
+            }
+        }
+
+        number.is_empty().then_some(total)
+    }
+
+    fn unescape(value: &str) -> String {
+        let mut result = String::with_capacity(value.len());
+        let mut chars = value.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                result.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('n') | Some('N') => result.push('\n'),
+                Some(other) => result.push(other),
+                None => {}
+            }
+        }
+        result
+    }
+}
+
+/// RRULE 的重复频率
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// RRULE 的子集：FREQ、INTERVAL、UNTIL、COUNT、BYDAY（月重复支持 `1MO`、`-1FR` 这样的序号）和 BYMONTHDAY
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecurrenceRule {
+    pub frequency: Frequency,
+    pub interval: u32,
+    /// 最后一次可能的日期（UNTIL，或由 COUNT 推算）
+    pub until: Option<NaiveDate>,
+    /// (序号, 星期)，序号为 0 表示每个该星期
+    pub by_day: Vec<(i32, Weekday)>,
+    pub by_month_day: Vec<i32>,
+}
+
+impl RecurrenceRule {
+    /// 解析规则文本；FREQ 缺失或不受支持时返回 None
+    pub fn parse(rule: &str, first: NaiveDate) -> Option<Self> {
+        let mut frequency = None;
+        let mut interval = 1;
+        let mut until = None;
+        let mut count = None;
+        let mut by_day = Vec::new();
+        let mut by_month_day = Vec::new();
+
+        for part in rule.split(';') {
+            let Some((key, value)) = part.split_once('=') else {
+                continue;
+            };
+            match key.trim().to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.trim().to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return None,
+                    })
+                }
+                "INTERVAL" => interval = value.trim().parse().ok().filter(|n| *n > 0)?,
+                "UNTIL" => until = IcsCalendar::parse_time(&[], value).map(|t| t.date()),
+                "COUNT" => count = value.trim().parse::<u32>().ok(),
+                "BYDAY" => by_day = value.split(',').filter_map(Self::parse_by_day).collect(),
+                "BYMONTHDAY" => {
+                    by_month_day = value.split(',').filter_map(|d| d.trim().parse().ok()).collect()
+                }
+                _ => {}
+            }
+        }
+
+        let mut rule = RecurrenceRule {
+            frequency: frequency?,
+            interval,
+            until,
+            by_day,
+            by_month_day,
+        };
+
+        // COUNT 换算成最后一次的日期，之后只需比较日期
+        if let Some(count) = count {
+            let last = first
+                .iter_days()
+                .take(MAX_RECURRENCE_DAYS as usize)
+                .filter(|date| rule.matches(first, *date))
+                .take(count as usize)
+                .last();
+            rule.until = match (rule.until, last) {
+                (Some(until), Some(last)) => Some(until.min(last)),
+                (until, last) => until.or(last).or(Some(first - ChronoDuration::days(1))),
+            };
+        }
+
+        Some(rule)
+    }
+
+    /// 以 first 为首次日期，规则是否在 date 产生一次发生
+    pub fn occurs_on(&self, first: NaiveDate, date: NaiveDate) -> bool {
+        if self.until.is_some_and(|until| date > until) {
+            return false;
+        }
+        self.matches(first, date)
+    }
+
+    fn matches(&self, first: NaiveDate, date: NaiveDate) -> bool {
+        if date < first {
+            return false;
+        }
+        let interval = self.interval as i64;
+
+        match self.frequency {
+            Frequency::Daily => (date - first).num_days() % interval == 0,
+            Frequency::Weekly => {
+                let week_start = |d: NaiveDate| {
+                    d - ChronoDuration::days(d.weekday().num_days_from_monday() as i64)
+                };
+                let weeks = (week_start(date) - week_start(first)).num_days() / 7;
+                let weekday_matches = if self.by_day.is_empty() {
+                    date.weekday() == first.weekday()
+                } else {
+                    self.by_day.iter().any(|(_, day)| *day == date.weekday())
+                };
+                weeks % interval == 0 && weekday_matches
+            }
+            Frequency::Monthly => {
+                let months = (date.year() - first.year()) as i64 * 12 + date.month() as i64
+                    - first.month() as i64;
+                months % interval == 0 && self.matches_day_of_month(first, date)
+            }
+            Frequency::Yearly => {
+                (date.year() - first.year()) as i64 % interval == 0
+                    && date.month() == first.month()
+                    && self.matches_day_of_month(first, date)
+            }
+        }
+    }
+
+    fn matches_day_of_month(&self, first: NaiveDate, date: NaiveDate) -> bool {
+        if self.by_day.is_empty() && self.by_month_day.is_empty() {
+            return date.day() == first.day();
+        }
+
+        let days_in_month = Self::days_in_month(date) as i32;
+        let by_month_day = self.by_month_day.iter().any(|d| {
+            let day = if *d < 0 { days_in_month + d + 1 } else { *d };
+            day == date.day() as i32
+        });
+
+        let by_day = self.by_day.iter().any(|(ordinal, weekday)| {
+            if *weekday != date.weekday() {
+                return false;
+            }
+            let nth = (date.day() as i32 - 1) / 7 + 1;
+            let nth_from_end = -((days_in_month - date.day() as i32) / 7 + 1);
+            *ordinal == 0 || *ordinal == nth || *ordinal == nth_from_end
+        });
+
+        by_month_day || by_day
+    }
+
+    fn days_in_month(date: NaiveDate) -> u32 {
+        let (year, month) = if date.month() == 12 {
+            (date.year() + 1, 1)
+        } else {
+            (date.year(), date.month() + 1)
+        };
+        NaiveDate::from_ymd_opt(year, month, 1)
+            .map(|next| (next - ChronoDuration::days(1)).day())
+            .unwrap_or(31)
+    }
+
+    /// 解析 `MO`、`1MO`、`-1FR` 形式的 BYDAY 条目
+    fn parse_by_day(value: &str) -> Option<(i32, Weekday)> {
+        let value = value.trim().to_ascii_uppercase();
+        let (ordinal, day) = value.split_at(value.len().checked_sub(2)?);
+        let weekday = match day {
+            "MO" => Weekday::Mon,
+            "TU" => Weekday::Tue,
+            "WE" => Weekday::Wed,
+            "TH" => Weekday::Thu,
+            "FR" => Weekday::Fri,
+            "SA" => Weekday::Sat,
+            "SU" => Weekday::Sun,
+            _ => return None,
+        };
+        let ordinal = if ordinal.is_empty() {
+            0
+        } else {
+            ordinal.trim_start_matches('+').parse().ok()?
+        };
+        Some((ordinal, weekday))
+    }
+}
+
+#[derive(Default)]
+struct EventBuilder {
+    summary: String,
+    categories: Vec<String>,
+    start: Option<IcsTime>,
+    end: Option<IcsTime>,
+    duration: Option<ChronoDuration>,
+    rrule: Option<String>,
+    exdates: Vec<IcsTime>,
+}
+
+impl EventBuilder {
+    fn set(&mut self, name: &str, params: &[(String, String)], value: &str) {
+        match name {
+            "SUMMARY" => self.summary = IcsCalendar::unescape(value).trim().to_string(),
+            "CATEGORIES" => self.categories.extend(
+                value
+                    .split(',')
+                    .map(|c| IcsCalendar::unescape(c).trim().to_string())
+                    .filter(|c| !c.is_empty()),
+            ),
+            "DTSTART" => self.start = IcsCalendar::parse_time(params, value),
+            "DTEND" => self.end = IcsCalendar::parse_time(params, value),
+            "DURATION" => self.duration = IcsCalendar::parse_duration(value),
+            "RRULE" => self.rrule = Some(value.trim().to_string()),
+            "EXDATE" => self.exdates.extend(
+                value
+                    .split(',')
+                    .filter_map(|v| IcsCalendar::parse_time(params, v)),
+            ),
+            _ => {}
+        }
+    }
+
+    fn build(self) -> Option<IcsEvent> {
+        let start = self.start?;
+        let end = self.end.or_else(|| {
+            let duration = self.duration?;
+            Some(match start {
+                IcsTime::Date(date) => IcsTime::Date(date + duration),
+                IcsTime::DateTime(datetime) => IcsTime::DateTime(datetime + duration),
+            })
+        });
+
+        // 不支持的重复规则按单次事件处理
+        let rrule = self
+            .rrule
+            .and_then(|rule| RecurrenceRule::parse(&rule, start.date()));
+
+        Some(IcsEvent {
+            summary: self.summary,
+            categories: self.categories,
+            start,
+            end,
+            rrule,
+            exdates: self.exdates,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOLIDAYS: &str = include_str!("../../tests/fixtures/holidays.ics");
+    const FOCUS: &str = include_str!("../../tests/fixtures/focus.ics");
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_parse_events() {
+        let calendar = IcsCalendar::parse(HOLIDAYS).unwrap();
+        assert_eq!(calendar.events.len(), 3);
+
+        let national_day = &calendar.events[0];
+        assert_eq!(national_day.summary, "国庆节");
+        assert_eq!(national_day.start, IcsTime::Date(date("2026-10-01")));
+        assert!(national_day.start.is_all_day());
+
+        // 折行和转义的逗号，VALARM 里的属性不影响事件
+        let offsite = &calendar.events[2];
+        assert_eq!(offsite.summary, "Team offsite, all day");
+        assert_eq!(
+            offsite.end,
+            Some(IcsTime::DateTime(date("2026-11-20").and_hms_opt(18, 0, 0).unwrap()))
+        );
+    }
+
+    #[test]
+    fn test_holiday_dates() {
+        let calendar = IcsCalendar::parse(HOLIDAYS).unwrap();
+        let dates: Vec<NaiveDate> = calendar
+            .holiday_dates(date("2027-12-31"))
+            .into_iter()
+            .map(|(d, _)| d)
+            .collect();
+
+        // 全天事件的 DTEND 不含当天，缺省 DTEND 时只占一天
+        assert_eq!(
+            dates,
+            vec![
+                date("2026-10-01"),
+                date("2026-10-02"),
+                date("2026-10-03"),
+                date("2026-11-20"),
+                date("2027-01-01"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_duration_and_invalid_input() {
+        let content = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nDTSTART:20261019T090000\n\
+                       DURATION:PT1H30M\nSUMMARY:Focus\nEND:VEVENT\nEND:VCALENDAR\n";
+        let calendar = IcsCalendar::parse(content).unwrap();
+        assert_eq!(
+            calendar.events[0].end,
+            Some(IcsTime::DateTime(date("2026-10-19").and_hms_opt(10, 30, 0).unwrap()))
+        );
+
+        assert!(IcsCalendar::parse("not a calendar").is_err());
+    }
+
+    fn starts(event: &IcsEvent, from: &str, to: &str) -> Vec<NaiveDate> {
+        date(from)
+            .iter_days()
+            .take_while(|d| *d <= date(to))
+            .filter(|d| event.starts_on(*d))
+            .collect()
+    }
+
+    #[test]
+    fn test_weekly_rule_with_count_and_exdate() {
+        let calendar = IcsCalendar::parse(FOCUS).unwrap();
+        let weekly = &calendar.events[0];
+
+        // COUNT=4 包含被 EXDATE 排除的那一次
+        assert_eq!(
+            starts(weekly, "2026-10-01", "2026-12-31"),
+            vec![date("2026-10-19"), date("2026-10-26"), date("2026-10-28")]
+        );
+        assert_eq!(
+            weekly.occurrence_on(date("2026-10-26")),
+            Some((
+                date("2026-10-26").and_hms_opt(9, 0, 0).unwrap(),
+                date("2026-10-26").and_hms_opt(11, 0, 0).unwrap()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_monthly_and_daily_rules() {
+        let calendar = IcsCalendar::parse(FOCUS).unwrap();
+
+        // 每月最后一个周五，时长来自 DURATION
+        let monthly = &calendar.events[1];
+        assert_eq!(
+            starts(monthly, "2026-10-01", "2027-01-31"),
+            vec![
+                date("2026-10-30"),
+                date("2026-11-27"),
+                date("2026-12-25"),
+                date("2027-01-29"),
+            ]
+        );
+        let (start, end) = monthly.occurrence_on(date("2026-11-27")).unwrap();
+        assert_eq!(end - start, ChronoDuration::hours(2));
+
+        let daily = &calendar.events[2];
+        assert!(daily.starts_on(date("2027-03-01")));
+        assert!(!daily.starts_on(date("2026-10-18")));
+    }
+
+    #[test]
+    fn test_all_day_events_are_not_windows() {
+        let calendar = IcsCalendar::parse(FOCUS).unwrap();
+        let focus_day = &calendar.events[4];
+        assert!(focus_day.starts_on(date("2026-10-26")));
+        assert_eq!(focus_day.occurrence_on(date("2026-10-26")), None);
+    }
+
+    #[test]
+    fn test_parse_rule() {
+        let first = date("2026-01-31");
+        let rule =
+            RecurrenceRule::parse("FREQ=MONTHLY;BYMONTHDAY=-1;UNTIL=20260430", first).unwrap();
+        assert!(rule.occurs_on(first, date("2026-02-28")));
+        assert!(rule.occurs_on(first, date("2026-04-30")));
+        assert!(!rule.occurs_on(first, date("2026-05-31")));
+
+        let rule = RecurrenceRule::parse("FREQ=DAILY;INTERVAL=3", first).unwrap();
+        assert!(rule.occurs_on(first, date("2026-02-03")));
+        assert!(!rule.occurs_on(first, date("2026-02-04")));
+
+        assert_eq!(RecurrenceRule::parse("FREQ=HOURLY", first), None);
+    }
+}
@@ -1,16 +1,28 @@
+use std::collections::HashSet;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use tiny_http::{Response, Server};
 
-use crate::models::Config;
+use crate::services::{SiteBlocker, SiteRules};
 
 const DEFAULT_PORT: u16 = 27190;
 
 /// 共享状态，供 HTTP 服务读取
 pub struct ServerState {
     pub timer_running: Arc<AtomicBool>,
-    pub config: Arc<Mutex<Config>>,
+    /// 当前生效的网站规则：专注期间为开始时合并好的本次规则，其余时间为全局规则
+    pub site_blocker: Arc<Mutex<SiteBlocker>>,
+}
+
+/// 上次 /status 的应答，规则和专注状态不变时直接复用
+///
+/// 扩展每秒轮询，列表较长时每次都重新展开和序列化会占满 CPU
+#[derive(Default)]
+struct StatusCache {
+    focusing: bool,
+    rules: Option<SiteRules>,
+    body: String,
 }
 
 pub struct LocalServer;
@@ -36,14 +48,16 @@ impl LocalServer {
             }
         };
 
+        let mut cache = StatusCache::default();
         for request in server.incoming_requests() {
-            let response = Self::handle_request(&state, &request);
+            let response = Self::handle_request(&state, &mut cache, &request);
             let _ = request.respond(response);
         }
     }
 
     fn handle_request(
         state: &Arc<ServerState>,
+        cache: &mut StatusCache,
         request: &tiny_http::Request,
     ) -> Response<std::io::Cursor<Vec<u8>>> {
         let path = request.url();
@@ -54,24 +68,59 @@ impl LocalServer {
         }
 
         match path {
-            "/status" => Self::handle_status(state),
+            "/status" => Self::handle_status(state, cache),
             _ => Self::not_found_response(),
         }
     }
 
-    fn handle_status(state: &Arc<ServerState>) -> Response<std::io::Cursor<Vec<u8>>> {
+    /// 返回专注状态和网站规则
+    ///
+    /// mode 为 "blocklist" 时屏蔽 blocked_sites，为 "allowlist" 时只放行 allowed_sites；
+    /// 不认识 mode 的旧版扩展在白名单模式下只会看到空的 blocked_sites。
+    /// rules 是带路径和查询条件的完整规则（host、path_prefix、query），
+    /// 两个网站列表只含主机名，黑名单中带路径的规则不会出现在 blocked_sites 里
+    fn handle_status(
+        state: &Arc<ServerState>,
+        cache: &mut StatusCache,
+    ) -> Response<std::io::Cursor<Vec<u8>>> {
         let focusing = state.timer_running.load(Ordering::SeqCst);
-        let raw_sites = {
-            let config = state.config.lock().unwrap();
-            config.blocked_sites.clone()
+        let rules = {
+            let site_blocker = state.site_blocker.lock().unwrap();
+            if cache.focusing == focusing && cache.rules.as_ref() == Some(site_blocker.rules()) {
+                return Self::cors_response(&cache.body);
+            }
+            site_blocker.rules().clone()
+        };
+        cache.focusing = focusing;
+        cache.rules = Some(rules.clone());
+
+        let hostnames = Self::expand_hostnames(&rules.host_sites());
+        let site_rules = rules.parsed();
+        let (mode, blocked_sites, allowed_sites) = match rules {
+            SiteRules::Blocklist(_) => ("blocklist", hostnames, Vec::new()),
+            SiteRules::Allowlist(_) => ("allowlist", Vec::new(), hostnames),
         };
 
-        // 提取纯域名，并自动补充 www 前缀版本
-        let mut blocked_sites: Vec<String> = Vec::new();
-        for site in &raw_sites {
+        let json = serde_json::json!({
+            "focusing": focusing,
+            "mode": mode,
+            "blocked_sites": blocked_sites,
+            "allowed_sites": allowed_sites,
+            "rules": site_rules
+        });
+
+        cache.body = json.to_string();
+        Self::cors_response(&cache.body)
+    }
+
+    /// 提取纯域名，并自动补充 www 前缀版本
+    fn expand_hostnames(raw_sites: &[String]) -> Vec<String> {
+        let mut hostnames: Vec<String> = Vec::new();
+        let mut seen: HashSet<String> = HashSet::new();
+        for site in raw_sites {
             let hostname = Self::extract_hostname(site);
-            if !hostname.is_empty() && !blocked_sites.contains(&hostname) {
-                blocked_sites.push(hostname.clone());
+            if !hostname.is_empty() && seen.insert(hostname.clone()) {
+                hostnames.push(hostname.clone());
             }
             // 自动补充 www 前缀版本
             let www_variant = if hostname.starts_with("www.") {
@@ -79,17 +128,11 @@ impl LocalServer {
             } else {
                 format!("www.{}", hostname)
             };
-            if !blocked_sites.contains(&www_variant) {
-                blocked_sites.push(www_variant);
+            if seen.insert(www_variant.clone()) {
+                hostnames.push(www_variant);
             }
         }
-
-        let json = serde_json::json!({
-            "focusing": focusing,
-            "blocked_sites": blocked_sites
-        });
-
-        Self::cors_response(&json.to_string())
+        hostnames
     }
 
     /// 从 URL 或域名字符串中提取纯域名
@@ -1,9 +1,31 @@
 pub mod app_blocker;
 pub mod site_blocker;
+pub mod site_rules;
+pub mod site_block_backend;
+pub mod dns;
+pub mod dns_resolver;
+pub mod dns_sinkhole;
+pub mod block_watchdog;
 pub mod scheduler;
 pub mod local_server;
+pub mod commitment_lock;
+pub mod config_watcher;
+pub mod calendar_watcher;
+pub mod ics;
+pub mod blocklist_import;
 
 pub use app_blocker::*;
 pub use site_blocker::*;
+pub use site_rules::*;
+pub use site_block_backend::*;
+pub use dns::*;
+pub use dns_resolver::*;
+pub use dns_sinkhole::*;
+pub use block_watchdog::*;
 pub use scheduler::*;
 pub use local_server::*;
+pub use commitment_lock::*;
+pub use config_watcher::*;
+pub use calendar_watcher::*;
+pub use ics::*;
+pub use blocklist_import::*;
@@ -1,150 +1,493 @@
 use crate::errors::AppError;
-use crate::models::Schedule;
-use chrono::{Local, NaiveTime, Timelike};
+use crate::models::{ExceptionKind, Schedule, ScheduleException};
+use crate::services::IcsEvent;
+use chrono::{Datelike, Duration as ChronoDuration, Local, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Condvar, Mutex};
 use std::thread::{self, JoinHandle};
 use std::time::Duration;
 
-const CHECK_INTERVAL_SECS: u64 = 30;
+/// 调度线程单次休眠的上限。系统休眠期间单调时钟不前进，醒来后最多延迟这么久就会按墙钟重新计算
+const MAX_SLEEP_SECS: u64 = 60;
+/// 查找下一个开始时间、合并连续时间段时最多向后搜索的天数（起始日期可能在较远的将来）
+const MAX_LOOKAHEAD_DAYS: i64 = 400;
+
+/// 时间段的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OccurrenceSource {
+    /// 手写的定时计划
+    Schedule,
+    /// 日历来源中的事件
+    Calendar,
+    /// 例外日期加开的时间段
+    Exception,
+}
+
+/// 时间段在某一天的一次具体发生，跨午夜时 end 落在第二天
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduleOccurrence {
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    pub source: OccurrenceSource,
+    /// 在来源列表中的下标（schedules 或 schedule_exceptions），日历事件为 None
+    pub index: Option<usize>,
+    /// 显示用的名称：时间段文字、事件标题或例外备注
+    pub label: String,
+}
+
+/// 调度使用的时钟，测试中可以注入固定或手动推进的时间
+pub trait Clock: Send + Sync {
+    /// 当前本地时间
+    fn now(&self) -> NaiveDateTime;
+}
+
+/// 系统本地时间
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> NaiveDateTime {
+        Local::now().naive_local()
+    }
+}
+
+/// 提前唤醒调度线程（配置变化、停止）
+#[derive(Default)]
+pub struct SchedulerWaker {
+    notified: Mutex<bool>,
+    condvar: Condvar,
+}
+
+impl SchedulerWaker {
+    pub fn notify(&self) {
+        *self.notified.lock().unwrap() = true;
+        self.condvar.notify_all();
+    }
+
+    /// 休眠直到被唤醒或超时，返回是否被唤醒
+    pub fn wait(&self, timeout: Duration) -> bool {
+        let notified = self.notified.lock().unwrap();
+        let (mut notified, _) = self
+            .condvar
+            .wait_timeout_while(notified, timeout, |notified| !*notified)
+            .unwrap();
+        std::mem::replace(&mut *notified, false)
+    }
+}
+
+/// 调度线程的状态机：记录上次是否在时间段内，计算状态变化和下一次醒来的时间
+#[derive(Debug, Default)]
+pub struct ScheduleTracker {
+    was_in_schedule: bool,
+}
+
+impl ScheduleTracker {
+    /// was_in_schedule 为 true 时，已经处于的时间段不会再报告一次进入
+    pub fn new(was_in_schedule: bool) -> Self {
+        ScheduleTracker { was_in_schedule }
+    }
+
+    /// 返回状态变化（如有）以及距下一个边界的时长（不超过 MAX_SLEEP_SECS）
+    ///
+    /// 每次都按当前时间重新判断，系统休眠跨过整个时间段时不会补发进入/离开
+    pub fn tick(&mut self, scheduler: &Scheduler, now: NaiveDateTime) -> (Option<bool>, Duration) {
+        let is_in_schedule = scheduler.is_in_schedule_at(now);
+        let change = (is_in_schedule != self.was_in_schedule).then_some(is_in_schedule);
+        self.was_in_schedule = is_in_schedule;
+
+        let max_sleep = Duration::from_secs(MAX_SLEEP_SECS);
+        let sleep = scheduler
+            .next_boundary_at(now)
+            .and_then(|boundary| (boundary - now).to_std().ok())
+            .map_or(max_sleep, |until| until.min(max_sleep));
+
+        (change, sleep)
+    }
+}
 
 pub struct Scheduler {
     schedules: Vec<Schedule>,
+    exceptions: Vec<ScheduleException>,
+    /// 从日历来源读取并已按过滤条件筛选的事件
+    calendar_events: Vec<IcsEvent>,
 }
 
 impl Scheduler {
     pub fn new(schedules: Vec<Schedule>) -> Self {
-        Scheduler { schedules }
+        Scheduler {
+            schedules,
+            exceptions: Vec::new(),
+            calendar_events: Vec::new(),
+        }
     }
 
     pub fn update_schedules(&mut self, schedules: Vec<Schedule>) {
         self.schedules = schedules;
     }
 
+    pub fn update_exceptions(&mut self, exceptions: Vec<ScheduleException>) {
+        self.exceptions = exceptions;
+    }
+
+    pub fn update_calendar_events(&mut self, events: Vec<IcsEvent>) {
+        self.calendar_events = events;
+    }
+
     pub fn get_schedules(&self) -> &Vec<Schedule> {
         &self.schedules
     }
 
-    /// 启动调度轮询线程
-    pub fn start_polling<F>(
-        schedules: Vec<Schedule>,
+    /// 启动调度线程
+    ///
+    /// 线程计算下一个开始或结束边界并休眠到那一刻；每次醒来都重新读取共享的 Scheduler
+    /// 和时钟，配置变化或停止时通过 waker 提前唤醒
+    ///
+    /// initially_in_schedule 为 true 表示调用方已处理当前所在的时间段（启动补偿）；
+    /// on_tick 在每次醒来时以当前时间调用，用于记录调度器最近的运行时间
+    pub fn spawn<F, T>(
+        scheduler: Arc<Mutex<Scheduler>>,
+        clock: Arc<dyn Clock>,
         running_flag: Arc<AtomicBool>,
+        waker: Arc<SchedulerWaker>,
+        initially_in_schedule: bool,
         on_schedule_change: F,
+        on_tick: T,
     ) -> JoinHandle<()>
     where
         F: Fn(bool) + Send + 'static,
+        T: Fn(NaiveDateTime) + Send + 'static,
     {
         thread::spawn(move || {
-            println!("调度轮询线程已启动");
-            let mut was_in_schedule = false;
+            println!("调度线程已启动");
+            let mut tracker = ScheduleTracker::new(initially_in_schedule);
 
             while running_flag.load(Ordering::SeqCst) {
-                let is_in_schedule = Self::is_in_schedule_static(&schedules);
+                let now = clock.now();
+                let (change, sleep) = {
+                    let scheduler = scheduler.lock().unwrap();
+                    tracker.tick(&scheduler, now)
+                };
+                on_tick(now);
 
-                // 检测状态变化
-                if is_in_schedule != was_in_schedule {
+                if let Some(is_in_schedule) = change {
                     println!(
-                        "调度状态变化: {} -> {}",
-                        if was_in_schedule { "在时间段内" } else { "不在时间段内" },
-                        if is_in_schedule { "在时间段内" } else { "不在时间段内" }
+                        "调度状态变化: {}",
+                        if is_in_schedule { "进入时间段" } else { "离开时间段" }
                     );
                     on_schedule_change(is_in_schedule);
-                    was_in_schedule = is_in_schedule;
                 }
 
-                thread::sleep(Duration::from_secs(CHECK_INTERVAL_SECS));
+                if !running_flag.load(Ordering::SeqCst) {
+                    break;
+                }
+                waker.wait(sleep);
             }
 
-            println!("调度轮询线程已停止");
+            println!("调度线程已停止");
         })
     }
 
-    /// 检查当前时间是否在任一启用的时间段内（静态方法）
+    /// 给定时刻之后最近的边界：在时间段内为结束时间，否则为下一个开始时间
+    pub fn next_boundary_at(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        if self.is_in_schedule_at(now) {
+            self.current_schedule_end_at(now)
+        } else {
+            self.next_scheduled_start_at(now)
+        }
+    }
+
+    /// 检查当前时间是否在任一启用的时间段内（静态方法，不考虑例外日期）
     pub fn is_in_schedule_static(schedules: &[Schedule]) -> bool {
-        let now = Local::now();
-        let current_time = match NaiveTime::from_hms_opt(now.hour(), now.minute(), 0) {
-            Some(t) => t,
-            None => return false,
-        };
+        Scheduler::new(schedules.to_vec()).is_in_scheduled_time()
+    }
+
+    /// 检查给定时刻是否在任一启用的时间段内
+    pub fn is_in_schedule_at(&self, now: NaiveDateTime) -> bool {
+        // 跨午夜的时间段从前一天开始，因此从前一天算起
+        [now.date() - ChronoDuration::days(1), now.date()]
+            .into_iter()
+            .flat_map(|date| self.occurrences_on(date))
+            .any(|o| o.start <= now && now < o.end)
+    }
+
+    /// 检查当前时间是否在时间段内（实例方法）
+    pub fn is_in_scheduled_time(&self) -> bool {
+        self.is_in_schedule_at(Local::now().naive_local())
+    }
 
-        for schedule in schedules {
-            if !schedule.enabled {
-                continue;
+    /// 获取下一个计划开始时间（可能在明天或更晚）
+    pub fn get_next_scheduled_start(&self) -> Option<NaiveDateTime> {
+        self.next_scheduled_start_at(Local::now().naive_local())
+    }
+
+    /// 给定时刻之后最早的计划开始时间
+    pub fn next_scheduled_start_at(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        (0..=MAX_LOOKAHEAD_DAYS).find_map(|offset| {
+            self.occurrences_on(now.date() + ChronoDuration::days(offset))
+                .into_iter()
+                .map(|o| o.start)
+                .filter(|start| *start > now)
+                .min()
+        })
+    }
+
+    /// 从给定时刻起 days 天内（含今天）尚未结束的所有发生，按开始时间排序
+    pub fn upcoming_at(&self, now: NaiveDateTime, days: u32) -> Vec<ScheduleOccurrence> {
+        let mut occurrences: Vec<ScheduleOccurrence> = (-1..days as i64)
+            .flat_map(|offset| self.occurrences_on(now.date() + ChronoDuration::days(offset)))
+            .filter(|o| o.end > now)
+            .collect();
+        occurrences.sort_by_key(|o| (o.start, o.end));
+        occurrences
+    }
+
+    /// 给定时刻所在的时间段；多个时间段重叠时取最晚开始的一个
+    pub fn current_occurrence_at(&self, now: NaiveDateTime) -> Option<ScheduleOccurrence> {
+        [now.date() - ChronoDuration::days(1), now.date()]
+            .into_iter()
+            .flat_map(|date| self.occurrences_on(date))
+            .filter(|o| o.start <= now && now < o.end)
+            .max_by_key(|o| o.start)
+    }
+
+    /// 完整落在 [from, to] 之内的所有发生，按开始时间排序
+    pub fn occurrences_between(
+        &self,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+    ) -> Vec<ScheduleOccurrence> {
+        let days = (to.date() - from.date()).num_days();
+        let mut occurrences: Vec<ScheduleOccurrence> = (-1..=days)
+            .flat_map(|offset| self.occurrences_on(from.date() + ChronoDuration::days(offset)))
+            .filter(|o| o.start >= from && o.end <= to)
+            .collect();
+        occurrences.sort_by_key(|o| (o.start, o.end));
+        occurrences
+    }
+
+    /// 当前时间段剩余的秒数，不在任何时间段内时返回 None
+    pub fn seconds_until_current_end(&self) -> Option<u64> {
+        let now = Local::now().naive_local();
+        let end = self.current_schedule_end_at(now)?;
+        let remaining = end.signed_duration_since(now).num_seconds();
+        (remaining > 0).then_some(remaining as u64)
+    }
+
+    /// 获取当前时间段的结束时间（跨午夜时为第二天）
+    pub fn get_current_schedule_end(&self) -> Option<NaiveDateTime> {
+        self.current_schedule_end_at(Local::now().naive_local())
+    }
+
+    /// 给定时刻所在时间段的结束时间
+    ///
+    /// 首尾相接或重叠的时间段视为一段连续专注，返回合并后的结束时间
+    pub fn current_schedule_end_at(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut occurrences: Vec<ScheduleOccurrence> = (-1..=1)
+            .flat_map(|offset| self.occurrences_on(now.date() + ChronoDuration::days(offset)))
+            .collect();
+        let mut expanded_until = now.date() + ChronoDuration::days(1);
+
+        let mut end = occurrences
+            .iter()
+            .filter(|o| o.start <= now && now < o.end)
+            .map(|o| o.end)
+            .max()?;
+
+        loop {
+            // 合并后的结束时间超出已展开的日期时，继续展开后面的日期
+            while expanded_until < end.date() {
+                expanded_until += ChronoDuration::days(1);
+                occurrences.extend(self.occurrences_on(expanded_until));
             }
 
-            if let (Ok(start), Ok(end)) = (
-                Self::parse_time_static(&schedule.start),
-                Self::parse_time_static(&schedule.end),
-            ) {
-                if current_time >= start && current_time < end {
-                    return true;
-                }
+            let extended = occurrences
+                .iter()
+                .filter(|o| o.start <= end && o.end > end)
+                .map(|o| o.end)
+                .max();
+            match extended {
+                Some(next_end) => end = next_end,
+                None => break,
+            }
+            if end - now > ChronoDuration::days(MAX_LOOKAHEAD_DAYS) {
+                break;
             }
         }
 
-        false
+        Some(end)
     }
 
-    /// 检查当前时间是否在时间段内（实例方法）
-    pub fn is_in_scheduled_time(&self) -> bool {
-        Self::is_in_schedule_static(&self.schedules)
+    /// 给定日期开始的所有发生：启用的时间段和日历事件（跳过日除外），加上当天加开的时间段
+    fn occurrences_on(&self, date: NaiveDate) -> Vec<ScheduleOccurrence> {
+        let exceptions: Vec<(usize, &ScheduleException)> = self
+            .exceptions
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| Self::parse_date_static(&e.date).ok() == Some(date))
+            .collect();
+
+        let skipped = exceptions.iter().any(|(_, e)| e.kind == ExceptionKind::Skip);
+        let regular = self
+            .schedules
+            .iter()
+            .enumerate()
+            .filter(|(_, schedule)| {
+                !skipped && schedule.enabled && Self::applies_on(schedule, date)
+            })
+            .filter_map(|(index, schedule)| {
+                let (start, end) = Self::window_on(&schedule.start, &schedule.end, date)?;
+                Some(ScheduleOccurrence {
+                    start,
+                    end,
+                    source: OccurrenceSource::Schedule,
+                    index: Some(index),
+                    label: format!("{}-{}", schedule.start, schedule.end),
+                })
+            });
+
+        let calendar = self
+            .calendar_events
+            .iter()
+            .filter(|_| !skipped)
+            .filter_map(|event| {
+                let (start, end) = event.occurrence_on(date)?;
+                Some(ScheduleOccurrence {
+                    start,
+                    end,
+                    source: OccurrenceSource::Calendar,
+                    index: None,
+                    label: event.summary.clone(),
+                })
+            });
+
+        let added = exceptions
+            .iter()
+            .filter(|(_, e)| e.kind == ExceptionKind::Add)
+            .filter_map(|(index, e)| {
+                let (start, end) = Self::window_on(e.start.as_deref()?, e.end.as_deref()?, date)?;
+                Some(ScheduleOccurrence {
+                    start,
+                    end,
+                    source: OccurrenceSource::Exception,
+                    index: Some(*index),
+                    label: e.name.clone().unwrap_or_else(|| "加开".to_string()),
+                })
+            });
+
+        regular.chain(calendar).chain(added).collect()
     }
 
-    /// 获取下一个计划开始时间
-    pub fn get_next_scheduled_start(&self) -> Option<String> {
-        let now = Local::now();
-        let current_time = match NaiveTime::from_hms_opt(now.hour(), now.minute(), 0) {
-            Some(t) => t,
-            None => return None,
+    /// 时间段在给定日期的起止时间；结束时间不晚于开始时间时视为跨午夜
+    fn window_on(start: &str, end: &str, date: NaiveDate) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        let start_time = Self::parse_time_static(start).ok()?;
+        let end_time = Self::parse_time_static(end).ok()?;
+
+        let end_date = if end_time <= start_time {
+            date + ChronoDuration::days(1)
+        } else {
+            date
         };
 
-        let mut next_start: Option<NaiveTime> = None;
+        Some((date.and_time(start_time), end_date.and_time(end_time)))
+    }
 
-        for schedule in &self.schedules {
-            if !schedule.enabled {
-                continue;
+    /// 时间段在给定日期是否生效（星期和起止日期，跨午夜的时间段按开始日期计算）
+    fn applies_on(schedule: &Schedule, date: NaiveDate) -> bool {
+        if !schedule.weekdays.contains(&date.weekday().number_from_monday()) {
+            return false;
+        }
+        if let Some(Ok(from)) = schedule.valid_from.as_deref().map(Self::parse_date_static) {
+            if date < from {
+                return false;
             }
-
-            if let Ok(start) = Self::parse_time_static(&schedule.start) {
-                if start > current_time {
-                    match next_start {
-                        None => next_start = Some(start),
-                        Some(current_next) if start < current_next => {
-                            next_start = Some(start);
-                        }
-                        _ => {}
-                    }
-                }
+        }
+        if let Some(Ok(until)) = schedule.valid_until.as_deref().map(Self::parse_date_static) {
+            if date > until {
+                return false;
             }
         }
-
-        next_start.map(|t| format!("{:02}:{:02}", t.hour(), t.minute()))
+        true
     }
 
-    /// 获取当前时间段的结束时间
-    pub fn get_current_schedule_end(&self) -> Option<String> {
-        let now = Local::now();
-        let current_time = match NaiveTime::from_hms_opt(now.hour(), now.minute(), 0) {
-            Some(t) => t,
-            None => return None,
-        };
+    /// 校验时间段格式（HH:MM）、星期（1-7）和起止日期（YYYY-MM-DD）
+    pub fn validate_schedule(schedule: &Schedule) -> Result<(), AppError> {
+        let start = Self::parse_time_static(&schedule.start)?;
+        let end = Self::parse_time_static(&schedule.end)?;
+        if start == end {
+            return Err(AppError::ConfigError(format!(
+                "时间段 {}-{} 的开始和结束时间相同",
+                schedule.start, schedule.end
+            )));
+        }
+
+        if schedule.weekdays.is_empty() {
+            return Err(AppError::ConfigError(format!(
+                "时间段 {}-{} 至少需要选择一天",
+                schedule.start, schedule.end
+            )));
+        }
+        if let Some(day) = schedule.weekdays.iter().find(|d| !(1..=7).contains(*d)) {
+            return Err(AppError::ConfigError(format!("无效的星期: {}", day)));
+        }
+        if let Some(minutes) = schedule.work_minutes.filter(|m| !(1..=180).contains(m)) {
+            return Err(AppError::ConfigError(format!(
+                "时间段 {}-{} 的专注时长超出范围: {} 分钟",
+                schedule.start, schedule.end, minutes
+            )));
+        }
+        if let Some(minutes) = schedule.break_minutes.filter(|m| *m > 60) {
+            return Err(AppError::ConfigError(format!(
+                "时间段 {}-{} 的休息时长超出范围: {} 分钟",
+                schedule.start, schedule.end, minutes
+            )));
+        }
 
-        for schedule in &self.schedules {
-            if !schedule.enabled {
-                continue;
+        let from = schedule
+            .valid_from
+            .as_deref()
+            .map(Self::parse_date_static)
+            .transpose()?;
+        let until = schedule
+            .valid_until
+            .as_deref()
+            .map(Self::parse_date_static)
+            .transpose()?;
+        if let (Some(from), Some(until)) = (from, until) {
+            if from > until {
+                return Err(AppError::ConfigError(format!(
+                    "起始日期 {} 晚于截止日期 {}",
+                    from, until
+                )));
             }
+        }
 
-            if let (Ok(start), Ok(end)) = (
-                Self::parse_time_static(&schedule.start),
-                Self::parse_time_static(&schedule.end),
-            ) {
-                if current_time >= start && current_time < end {
-                    return Some(schedule.end.clone());
-                }
+        Ok(())
+    }
+
+    /// 校验例外日期：日期格式，加开时间段需要合法的开始和结束时间
+    pub fn validate_exception(exception: &ScheduleException) -> Result<(), AppError> {
+        Self::parse_date_static(&exception.date)?;
+        if exception.kind == ExceptionKind::Add {
+            let (Some(start), Some(end)) = (&exception.start, &exception.end) else {
+                return Err(AppError::ConfigError(format!(
+                    "{} 的加开时间段缺少开始或结束时间",
+                    exception.date
+                )));
+            };
+            if Self::parse_time_static(start)? == Self::parse_time_static(end)? {
+                return Err(AppError::ConfigError(format!(
+                    "{} 的加开时间段开始和结束时间相同",
+                    exception.date
+                )));
             }
         }
+        Ok(())
+    }
 
-        None
+    fn parse_date_static(date_str: &str) -> Result<NaiveDate, AppError> {
+        NaiveDate::parse_from_str(date_str, "%Y-%m-%d")
+            .map_err(|_| AppError::ConfigError(format!("无效的日期: {}", date_str)))
     }
 
     fn parse_time_static(time_str: &str) -> Result<NaiveTime, AppError> {
@@ -173,3 +516,391 @@ impl Default for Scheduler {
         Self::new(Vec::new())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{default_weekdays, CalendarSource, EmergencyPolicy};
+    use crate::services::IcsCalendar;
+
+    fn schedule(start: &str, end: &str, weekdays: &[u32]) -> Schedule {
+        Schedule {
+            enabled: true,
+            start: start.to_string(),
+            end: end.to_string(),
+            weekdays: weekdays.to_vec(),
+            valid_from: None,
+            valid_until: None,
+            work_minutes: None,
+            break_minutes: None,
+            profile: None,
+            emergency: EmergencyPolicy::Quota,
+        }
+    }
+
+    /// 2026-10-19 是周一，2026-10-24 是周六
+    fn at(date: &str, time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_weekdays_only_schedule() {
+        let workdays = Scheduler::new(vec![schedule("09:00", "12:00", &[1, 2, 3, 4, 5])]);
+
+        assert!(workdays.is_in_schedule_at(at("2026-10-19", "10:00")));
+        assert!(!workdays.is_in_schedule_at(at("2026-10-24", "10:00")));
+        assert!(!workdays.is_in_schedule_at(at("2026-10-25", "10:00")));
+        assert!(!workdays.is_in_schedule_at(at("2026-10-19", "12:00")));
+    }
+
+    #[test]
+    fn test_next_start_and_current_end_respect_weekdays() {
+        let scheduler = Scheduler::new(vec![
+            schedule("09:00", "12:00", &[1, 2, 3, 4, 5]),
+            schedule("14:00", "16:00", &[6, 7]),
+            schedule("19:00", "21:00", &default_weekdays()),
+        ]);
+
+        let monday = at("2026-10-19", "08:00");
+        assert_eq!(scheduler.next_scheduled_start_at(monday), Some(at("2026-10-19", "09:00")));
+        let monday = at("2026-10-19", "13:00");
+        assert_eq!(scheduler.next_scheduled_start_at(monday), Some(at("2026-10-19", "19:00")));
+
+        // 周五晚上之后，下一个开始是周六下午
+        let friday = at("2026-10-23", "21:30");
+        assert_eq!(scheduler.next_scheduled_start_at(friday), Some(at("2026-10-24", "14:00")));
+
+        let saturday = at("2026-10-24", "08:00");
+        assert_eq!(scheduler.next_scheduled_start_at(saturday), Some(at("2026-10-24", "14:00")));
+        assert_eq!(scheduler.current_schedule_end_at(saturday), None);
+        let saturday = at("2026-10-24", "15:30");
+        assert_eq!(scheduler.current_schedule_end_at(saturday), Some(at("2026-10-24", "16:00")));
+    }
+
+    #[test]
+    fn test_overnight_schedule() {
+        // 只在周五开始的 22:00-02:00，覆盖到周六凌晨
+        let scheduler = Scheduler::new(vec![schedule("22:00", "02:00", &[5])]);
+
+        assert!(!scheduler.is_in_schedule_at(at("2026-10-23", "21:59")));
+        assert!(scheduler.is_in_schedule_at(at("2026-10-23", "23:00")));
+        assert!(scheduler.is_in_schedule_at(at("2026-10-24", "01:30")));
+        assert!(!scheduler.is_in_schedule_at(at("2026-10-24", "02:00")));
+        // 周四开始的那一次不生效，所以周五凌晨不在时间段内
+        assert!(!scheduler.is_in_schedule_at(at("2026-10-23", "01:00")));
+
+        assert_eq!(
+            scheduler.current_schedule_end_at(at("2026-10-23", "23:00")),
+            Some(at("2026-10-24", "02:00"))
+        );
+        assert_eq!(
+            scheduler.next_scheduled_start_at(at("2026-10-24", "01:00")),
+            Some(at("2026-10-30", "22:00"))
+        );
+    }
+
+    #[test]
+    fn test_adjacent_schedules_merge_across_midnight() {
+        let scheduler = Scheduler::new(vec![
+            schedule("20:00", "00:00", &default_weekdays()),
+            schedule("00:00", "01:30", &default_weekdays()),
+        ]);
+
+        assert_eq!(
+            scheduler.current_schedule_end_at(at("2026-10-19", "21:00")),
+            Some(at("2026-10-20", "01:30"))
+        );
+        assert_eq!(
+            scheduler.next_scheduled_start_at(at("2026-10-20", "02:00")),
+            Some(at("2026-10-20", "20:00"))
+        );
+    }
+
+    #[test]
+    fn test_date_range_is_inclusive() {
+        let mut ranged = schedule("09:00", "12:00", &default_weekdays());
+        ranged.valid_from = Some("2026-10-20".to_string());
+        ranged.valid_until = Some("2026-10-22".to_string());
+        let scheduler = Scheduler::new(vec![ranged]);
+
+        assert!(!scheduler.is_in_schedule_at(at("2026-10-19", "10:00")));
+        assert!(scheduler.is_in_schedule_at(at("2026-10-20", "10:00")));
+        assert!(scheduler.is_in_schedule_at(at("2026-10-22", "10:00")));
+        assert!(!scheduler.is_in_schedule_at(at("2026-10-23", "10:00")));
+    }
+
+    #[test]
+    fn test_skip_and_add_exceptions() {
+        let mut scheduler = Scheduler::new(vec![schedule("09:00", "12:00", &[1, 2, 3, 4, 5])]);
+        scheduler.update_exceptions(vec![
+            ScheduleException {
+                date: "2026-10-20".to_string(),
+                kind: ExceptionKind::Skip,
+                start: None,
+                end: None,
+                name: Some("休假".to_string()),
+            },
+            ScheduleException {
+                date: "2026-10-24".to_string(),
+                kind: ExceptionKind::Add,
+                start: Some("10:00".to_string()),
+                end: Some("11:00".to_string()),
+                name: None,
+            },
+        ]);
+
+        assert!(scheduler.is_in_schedule_at(at("2026-10-19", "10:00")));
+        assert!(!scheduler.is_in_schedule_at(at("2026-10-20", "10:00")));
+        assert!(scheduler.is_in_schedule_at(at("2026-10-24", "10:30")));
+        assert_eq!(
+            scheduler.current_schedule_end_at(at("2026-10-24", "10:30")),
+            Some(at("2026-10-24", "11:00"))
+        );
+
+        // 周一结束后跳过周二，下一次是周三
+        assert_eq!(
+            scheduler.next_scheduled_start_at(at("2026-10-19", "13:00")),
+            Some(at("2026-10-21", "09:00"))
+        );
+        // 周五之后的下一次是周六加开的时间段
+        assert_eq!(
+            scheduler.next_scheduled_start_at(at("2026-10-23", "13:00")),
+            Some(at("2026-10-24", "10:00"))
+        );
+    }
+
+    #[test]
+    fn test_calendar_source_windows() {
+        let source = CalendarSource {
+            enabled: true,
+            path: String::new(),
+            summary_filter: Some("focus".to_string()),
+            category_filter: Some("focus".to_string()),
+        };
+        let calendar = IcsCalendar::parse(include_str!("../../tests/fixtures/focus.ics")).unwrap();
+        let events: Vec<IcsEvent> = calendar
+            .events
+            .into_iter()
+            .filter(|e| source.matches(e))
+            .collect();
+        let summaries: Vec<&str> = events.iter().map(|e| e.summary.as_str()).collect();
+        // Standup 既不匹配标题也不匹配分类
+        assert_eq!(summaries, vec!["Focus time", "Deep work", "Release night", "Focus day"]);
+
+        let mut scheduler = Scheduler::new(vec![schedule("14:00", "15:00", &[1])]);
+        scheduler.update_calendar_events(events);
+
+        assert!(scheduler.is_in_schedule_at(at("2026-10-19", "10:00")));
+        assert!(!scheduler.is_in_schedule_at(at("2026-10-19", "11:00")));
+        // 跨午夜的日历事件
+        assert!(scheduler.is_in_schedule_at(at("2026-10-25", "00:30")));
+        assert_eq!(
+            scheduler.current_schedule_end_at(at("2026-10-24", "23:00")),
+            Some(at("2026-10-25", "01:00"))
+        );
+        // 周一 09:00-11:00 结束后，下一个是手写的 14:00
+        assert_eq!(
+            scheduler.next_scheduled_start_at(at("2026-10-19", "11:00")),
+            Some(at("2026-10-19", "14:00"))
+        );
+        // 周三那次被 EXDATE 排除，下一个是周六晚上
+        assert_eq!(
+            scheduler.next_scheduled_start_at(at("2026-10-19", "15:00")),
+            Some(at("2026-10-24", "22:00"))
+        );
+    }
+
+    /// 手动推进的时钟
+    struct MockClock(Mutex<NaiveDateTime>);
+
+    impl MockClock {
+        fn set(&self, now: NaiveDateTime) {
+            *self.0.lock().unwrap() = now;
+        }
+    }
+
+    impl Clock for MockClock {
+        fn now(&self) -> NaiveDateTime {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    fn at_secs(date: &str, time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn test_tracker_sleeps_until_next_boundary() {
+        let scheduler = Scheduler::new(vec![schedule("09:00", "12:00", &default_weekdays())]);
+        let mut tracker = ScheduleTracker::default();
+
+        let (change, sleep) = tracker.tick(&scheduler, at_secs("2026-10-19", "08:59:15"));
+        assert_eq!(change, None);
+        assert_eq!(sleep, Duration::from_secs(45));
+
+        // 秒级精度：准点进入时间段
+        let (change, sleep) = tracker.tick(&scheduler, at_secs("2026-10-19", "09:00:00"));
+        assert_eq!(change, Some(true));
+        assert_eq!(sleep, Duration::from_secs(MAX_SLEEP_SECS));
+
+        let (change, sleep) = tracker.tick(&scheduler, at_secs("2026-10-19", "11:59:58"));
+        assert_eq!(change, None);
+        assert_eq!(sleep, Duration::from_secs(2));
+
+        let (change, _) = tracker.tick(&scheduler, at_secs("2026-10-19", "12:00:00"));
+        assert_eq!(change, Some(false));
+    }
+
+    #[test]
+    fn test_tracker_catches_up_after_suspend() {
+        let scheduler = Scheduler::new(vec![schedule("09:00", "12:00", &default_weekdays())]);
+        let mut tracker = ScheduleTracker::default();
+
+        tracker.tick(&scheduler, at("2026-10-19", "08:00"));
+        // 休眠到时间段中间才醒来：立即进入
+        assert_eq!(tracker.tick(&scheduler, at("2026-10-19", "10:30")).0, Some(true));
+        // 休眠跨过结束时间：立即离开
+        assert_eq!(tracker.tick(&scheduler, at("2026-10-20", "08:00")).0, Some(false));
+        // 休眠跨过整个时间段：不补发
+        assert_eq!(tracker.tick(&scheduler, at("2026-10-20", "13:00")).0, None);
+    }
+
+    #[test]
+    fn test_tracker_started_inside_window_only_reports_exit() {
+        let scheduler = Scheduler::new(vec![schedule("09:00", "12:00", &[1])]);
+        let mut tracker = ScheduleTracker::new(true);
+
+        assert_eq!(tracker.tick(&scheduler, at("2026-10-19", "10:30")).0, None);
+        assert_eq!(tracker.tick(&scheduler, at("2026-10-19", "12:00")).0, Some(false));
+    }
+
+    #[test]
+    fn test_occurrences_between_only_includes_complete_windows() {
+        let scheduler = Scheduler::new(vec![
+            schedule("09:00", "12:00", &[1, 2, 3, 4, 5]),
+            schedule("22:00", "01:00", &[1]),
+        ]);
+
+        // 周一 10:00 到周三 09:30：周一上午只错过一半，周三上午尚未结束
+        let missed = scheduler.occurrences_between(at("2026-10-19", "10:00"), at("2026-10-21", "09:30"));
+        let starts: Vec<NaiveDateTime> = missed.iter().map(|o| o.start).collect();
+        assert_eq!(starts, vec![at("2026-10-19", "22:00"), at("2026-10-20", "09:00")]);
+    }
+
+    #[test]
+    fn test_thread_wakes_on_change_and_stop() {
+        use std::sync::mpsc;
+
+        let scheduler = Arc::new(Mutex::new(Scheduler::new(vec![schedule(
+            "09:00",
+            "12:00",
+            &default_weekdays(),
+        )])));
+        let clock = Arc::new(MockClock(Mutex::new(at("2026-10-19", "08:00"))));
+        let running = Arc::new(AtomicBool::new(true));
+        let waker = Arc::new(SchedulerWaker::default());
+        let (tx, rx) = mpsc::channel();
+
+        let handle = Scheduler::spawn(
+            Arc::clone(&scheduler),
+            clock.clone(),
+            Arc::clone(&running),
+            Arc::clone(&waker),
+            false,
+            move |is_in| tx.send(is_in).unwrap(),
+            |_| {},
+        );
+        let timeout = Duration::from_secs(2);
+
+        // 没有唤醒时线程会休眠 MAX_SLEEP_SECS，能在超时内收到说明是被唤醒的
+        clock.set(at("2026-10-19", "09:00"));
+        waker.notify();
+        assert_eq!(rx.recv_timeout(timeout), Ok(true));
+
+        scheduler.lock().unwrap().update_schedules(Vec::new());
+        waker.notify();
+        assert_eq!(rx.recv_timeout(timeout), Ok(false));
+
+        running.store(false, Ordering::SeqCst);
+        waker.notify();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_upcoming_occurrences() {
+        let mut scheduler = Scheduler::new(vec![
+            schedule("09:00", "12:00", &[1, 2, 3, 4, 5]),
+            schedule("22:00", "01:00", &[7]),
+        ]);
+        scheduler.update_exceptions(vec![ScheduleException {
+            date: "2026-10-24".to_string(),
+            kind: ExceptionKind::Add,
+            start: Some("10:00".to_string()),
+            end: Some("11:00".to_string()),
+            name: Some("补课".to_string()),
+        }]);
+
+        // 周五 10:00 起 3 天：进行中的周五、周六加开、周日跨午夜
+        let upcoming = scheduler.upcoming_at(at("2026-10-23", "10:00"), 3);
+        let summary: Vec<(NaiveDateTime, OccurrenceSource, &str)> = upcoming
+            .iter()
+            .map(|o| (o.start, o.source, o.label.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (at("2026-10-23", "09:00"), OccurrenceSource::Schedule, "09:00-12:00"),
+                (at("2026-10-24", "10:00"), OccurrenceSource::Exception, "补课"),
+                (at("2026-10-25", "22:00"), OccurrenceSource::Schedule, "22:00-01:00"),
+            ]
+        );
+        assert_eq!(upcoming[2].end, at("2026-10-26", "01:00"));
+        assert_eq!(upcoming[2].index, Some(1));
+    }
+
+    #[test]
+    fn test_validate_schedule() {
+        assert!(Scheduler::validate_schedule(&schedule("09:00", "12:00", &[1, 7])).is_ok());
+        assert!(Scheduler::validate_schedule(&schedule("09:00", "12:00", &[])).is_err());
+        assert!(Scheduler::validate_schedule(&schedule("09:00", "12:00", &[0])).is_err());
+
+        let mut reversed = schedule("09:00", "12:00", &[1]);
+        reversed.valid_from = Some("2026-11-01".to_string());
+        reversed.valid_until = Some("2026-10-01".to_string());
+        assert!(Scheduler::validate_schedule(&reversed).is_err());
+
+        reversed.valid_until = Some("2026-13-01".to_string());
+        assert!(Scheduler::validate_schedule(&reversed).is_err());
+
+        assert!(Scheduler::validate_schedule(&schedule("22:00", "02:00", &[1])).is_ok());
+        assert!(Scheduler::validate_schedule(&schedule("09:00", "09:00", &[1])).is_err());
+
+        let mut rhythm = schedule("09:00", "12:00", &[1]);
+        rhythm.work_minutes = Some(50);
+        rhythm.break_minutes = Some(10);
+        assert!(Scheduler::validate_schedule(&rhythm).is_ok());
+        rhythm.work_minutes = Some(0);
+        assert!(Scheduler::validate_schedule(&rhythm).is_err());
+        rhythm.work_minutes = Some(50);
+        rhythm.break_minutes = Some(90);
+        assert!(Scheduler::validate_schedule(&rhythm).is_err());
+    }
+
+    #[test]
+    fn test_current_occurrence_prefers_latest_start() {
+        let scheduler = Scheduler::new(vec![
+            schedule("09:00", "12:00", &[1]),
+            schedule("11:00", "13:00", &[1]),
+            schedule("23:00", "01:00", &[1]),
+        ]);
+
+        let current = scheduler.current_occurrence_at(at("2026-10-19", "10:00")).unwrap();
+        assert_eq!(current.index, Some(0));
+        let current = scheduler.current_occurrence_at(at("2026-10-19", "11:30")).unwrap();
+        assert_eq!(current.index, Some(1));
+        // 跨午夜的时间段在第二天凌晨仍然生效
+        let current = scheduler.current_occurrence_at(at("2026-10-20", "00:30")).unwrap();
+        assert_eq!(current.index, Some(2));
+        assert!(scheduler.current_occurrence_at(at("2026-10-19", "13:00")).is_none());
+    }
+}
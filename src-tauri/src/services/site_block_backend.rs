@@ -0,0 +1,807 @@
+use crate::errors::AppError;
+use crate::paths;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+#[cfg(target_os = "macos")]
+use std::{
+    sync::{atomic::AtomicBool, Mutex},
+    time::{Duration, Instant},
+};
+
+const HOSTS_PATH: &str = "/etc/hosts";
+#[cfg(target_os = "macos")]
+const PF_ANCHOR_NAME: &str = "pomodoro-focus";
+#[cfg(target_os = "macos")]
+const PF_RULES_PATH: &str = "/tmp/pomodoro_pf_rules.conf";
+/// 查询 pf 状态时分隔 anchor 规则和 `pfctl -s info` 输出的标记行
+#[cfg(target_os = "macos")]
+const PF_INFO_SEPARATOR: &str = "--pomodoro-pf-info--";
+/// 查询 pf 需要管理员授权，两次查询之间至少间隔该时间，期间沿用上次的结果
+#[cfg(target_os = "macos")]
+const PF_CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const HOSTS_TEMP_PREFIX: &str = "pomodoro_hosts";
+#[cfg(target_os = "linux")]
+const NFT_TEMP_PREFIX: &str = "pomodoro_nft";
+/// nftables 中存放屏蔽规则的表，解除屏蔽时整表删除
+#[cfg(target_os = "linux")]
+const NFT_TABLE: &str = "inet pomodoro_focus";
+/// 新建临时文件时遇到同名文件的最多重试次数
+const TEMP_FILE_ATTEMPTS: u32 = 16;
+
+/// 设置后网站屏蔽只写入该目录下的 hosts 文件，不修改系统（开发调试用）
+pub const SANDBOX_ENV_VAR: &str = "POMODORO_FOCUS_SITE_SANDBOX";
+
+/// 随 hosts 一起应用的防火墙规则，只有 blocks_ips 的后端使用
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FirewallRules {
+    /// 屏蔽这些 IP 的网页流量
+    Block(HashSet<String>),
+    /// 只放行这些 IP 的网页流量（白名单模式），为空时屏蔽所有网页
+    AllowOnly(HashSet<String>),
+}
+
+/// 已应用的防火墙规则的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FirewallStatus {
+    /// 规则仍然生效，或没有防火墙规则
+    Intact,
+    /// 规则被清空或防火墙被停用
+    Tampered,
+    /// 无法查询（如未获得管理员授权），不能判断是否失效
+    Unknown,
+}
+
+impl FirewallRules {
+    /// 没有需要应用的规则
+    pub fn is_empty(&self) -> bool {
+        matches!(self, FirewallRules::Block(ips) if ips.is_empty())
+    }
+}
+
+/// 网站屏蔽中与平台相关的部分：写入 hosts、获取权限、防火墙规则和刷新 DNS 缓存
+///
+/// hosts 内容（屏蔽区块的增删）由 SiteBlocker 统一生成，后端只负责落盘和生效
+pub trait SiteBlockBackend: Send + Sync {
+    /// 用于日志的名称
+    fn name(&self) -> &'static str;
+
+    fn hosts_path(&self) -> PathBuf;
+
+    /// 屏蔽前 hosts 的备份位置
+    fn backup_path(&self) -> Result<PathBuf, AppError> {
+        Ok(paths::state_dir()?.join("hosts.backup"))
+    }
+
+    /// 是否需要解析域名的 IP 生成防火墙规则
+    fn blocks_ips(&self) -> bool {
+        false
+    }
+
+    /// 写入带屏蔽区块的 hosts，应用防火墙规则并刷新 DNS 缓存（可能弹出授权）
+    fn apply(&self, hosts: &str, firewall: &FirewallRules) -> Result<(), AppError>;
+
+    /// 写回去掉屏蔽区块的 hosts（None 表示 hosts 无需改动），移除防火墙规则
+    fn restore(&self, hosts: Option<&str>) -> Result<(), AppError>;
+
+    /// 只写入 hosts，不改动防火墙规则，也不刷新 DNS 缓存：
+    /// 屏蔽失败时写回屏蔽前的内容（可能带着仍在生效的屏蔽区块），或只有 hosts 区块失效时写回
+    fn write_hosts(&self, hosts: &str) -> Result<(), AppError>;
+
+    /// 已应用的防火墙规则的状态；没有防火墙规则时为 Intact
+    fn firewall_status(&self) -> FirewallStatus {
+        FirewallStatus::Intact
+    }
+}
+
+/// 当前平台的默认后端；设置了 SANDBOX_ENV_VAR 时使用沙盒后端
+pub fn default_backend() -> Arc<dyn SiteBlockBackend> {
+    if let Some(root) = std::env::var_os(SANDBOX_ENV_VAR).filter(|v| !v.is_empty()) {
+        return Arc::new(SandboxBackend::new(PathBuf::from(root)));
+    }
+    #[cfg(target_os = "macos")]
+    {
+        Arc::new(MacosBackend::default())
+    }
+    #[cfg(target_os = "linux")]
+    {
+        Arc::new(LinuxBackend)
+    }
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    {
+        Arc::new(UnsupportedBackend)
+    }
+}
+
+/// 写入临时文件（hosts 或防火墙规则），供提权后的命令复制或载入
+///
+/// 每次新建（O_EXCL）只有当前用户可读写的文件，不会跟随他人预先放置的同名文件或符号链接
+fn write_temp_file(prefix: &str, content: &str) -> Result<PathBuf, AppError> {
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+    let io_error = |e: std::io::Error| AppError::IoError(format!("写入临时文件失败: {}", e));
+
+    for _ in 0..TEMP_FILE_ATTEMPTS {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.subsec_nanos());
+        let path = std::env::temp_dir().join(format!(
+            "{}_{}_{}_{}",
+            prefix,
+            std::process::id(),
+            nanos,
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = match options.open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(io_error(e)),
+        };
+        if let Err(e) = file.write_all(content.as_bytes()) {
+            let _ = fs::remove_file(&path);
+            return Err(io_error(e));
+        }
+        return Ok(path);
+    }
+    Err(AppError::IoError(
+        "写入临时文件失败: 无法创建唯一的文件名".to_string(),
+    ))
+}
+
+/// 直接写回 hosts（apply 时已 chmod 646，无需授权）
+#[cfg(target_os = "macos")]
+fn write_hosts_directly(path: &str, hosts: &str) -> Result<(), AppError> {
+    fs::write(path, hosts).map_err(|e| AppError::IoError(format!("写回 hosts 失败: {}", e)))?;
+    println!("[SiteBlocker] hosts 文件恢复成功");
+    Ok(())
+}
+
+/// 生成 pf 规则文件内容，IPv6 地址使用 inet6 规则；按地址排序保证输出稳定
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn generate_pf_rules(firewall: &FirewallRules) -> String {
+    let mut rules = String::new();
+    rules.push_str("# Pomodoro Focus - Site Blocking Rules\n");
+
+    let (ips, action) = match firewall {
+        FirewallRules::Block(ips) => (ips, "block drop out"),
+        FirewallRules::AllowOnly(ips) => {
+            rules.push_str("pass out quick on lo0 all\n");
+            (ips, "pass out quick")
+        }
+    };
+    let mut addrs: Vec<IpAddr> = ips.iter().filter_map(|ip| ip.parse().ok()).collect();
+    addrs.sort();
+    for ip in addrs {
+        let family = if ip.is_ipv6() { "inet6" } else { "inet" };
+        rules.push_str(&format!(
+            "{} {} proto tcp from any to {} port {{80, 443}}\n",
+            action, family, ip
+        ));
+    }
+
+    // 白名单之外的网页流量（含 QUIC）全部丢弃
+    if let FirewallRules::AllowOnly(_) = firewall {
+        rules.push_str("block drop out proto tcp from any to any port {80, 443}\n");
+        rules.push_str("block drop out proto udp from any to any port 443\n");
+    }
+
+    rules
+}
+
+/// pf 规则的（动作, 协议, 目标地址）集合
+///
+/// pfctl 列出规则时会把 `port {80, 443}` 展开为多条、端口写成服务名并补上 `keep state` 等默认选项，
+/// 只比较这三项才能和生成的规则文件对上
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn pf_targets(rules: &str) -> HashSet<(String, String, String)> {
+    rules
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            let after = |keyword: &str, default: &str| {
+                tokens
+                    .iter()
+                    .position(|t| *t == keyword)
+                    .and_then(|i| tokens.get(i + 1))
+                    .map_or(default, |t| *t)
+                    .to_string()
+            };
+            (
+                tokens[0].to_string(),
+                after("proto", ""),
+                after("to", "all"),
+            )
+        })
+        .collect()
+}
+
+/// 根据 `pfctl -a <anchor> -s rules` 和 `pfctl -s info` 的输出判断写入的规则（generate_pf_rules 的结果）
+/// 是否仍然生效：anchor 被清空或改动、pf 被停用都算失效
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn pf_intact(expected: &str, anchor_rules: &str, info: &str) -> bool {
+    let enabled = !info
+        .lines()
+        .any(|line| line.trim_start().starts_with("Status: Disabled"));
+    enabled && pf_targets(anchor_rules) == pf_targets(expected)
+}
+
+/// 生成 nftables 规则：先建后删再重建表，载入时整体替换上次的规则；含义与 pf 规则相同
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn generate_nft_rules(table: &str, firewall: &FirewallRules) -> String {
+    let mut rules = format!(
+        "table {table}\n\
+         delete table {table}\n\
+         table {table} {{\n\
+         \tchain output {{\n\
+         \t\ttype filter hook output priority 0; policy accept;\n"
+    );
+
+    let (ips, verdict) = match firewall {
+        FirewallRules::Block(ips) => (ips, "drop"),
+        FirewallRules::AllowOnly(ips) => {
+            rules.push_str("\t\toifname \"lo\" accept\n");
+            (ips, "accept")
+        }
+    };
+    let mut addrs: Vec<IpAddr> = ips.iter().filter_map(|ip| ip.parse().ok()).collect();
+    addrs.sort();
+    for ip in addrs {
+        let family = if ip.is_ipv6() { "ip6" } else { "ip" };
+        rules.push_str(&format!(
+            "\t\t{} daddr {} tcp dport {{ 80, 443 }} {}\n",
+            family, ip, verdict
+        ));
+    }
+
+    // 白名单之外的网页流量（含 QUIC）全部丢弃
+    if let FirewallRules::AllowOnly(_) = firewall {
+        rules.push_str("\t\ttcp dport { 80, 443 } drop\n");
+        rules.push_str("\t\tudp dport 443 drop\n");
+    }
+
+    rules.push_str("\t}\n}\n");
+    rules
+}
+
+/// macOS：osascript 授权一次完成 hosts、pf 和 DNS 缓存刷新
+#[cfg(target_os = "macos")]
+#[derive(Default)]
+pub struct MacosBackend {
+    /// 上次查询 pf 的时间和结果
+    pf_checked: Mutex<Option<(Instant, FirewallStatus)>>,
+    /// 用户取消了查询 pf 的授权，之后不再查询
+    pf_check_denied: AtomicBool,
+}
+
+#[cfg(target_os = "macos")]
+impl MacosBackend {
+    /// 通过 osascript 以管理员权限执行命令（可能弹出授权），返回标准输出
+    fn run_privileged(shell_cmd: &str) -> Result<String, AppError> {
+        let applescript = format!(
+            "do shell script \"{}\" with administrator privileges without altering line endings",
+            shell_cmd
+        );
+
+        let output = Command::new("osascript")
+            .arg("-e")
+            .arg(&applescript)
+            .output()
+            .map_err(|e| AppError::PermissionDenied(format!("请求管理员权限失败: {}", e)))?;
+
+        if !output.status.success() {
+            let err = String::from_utf8_lossy(&output.stderr);
+            if err.contains("User cancelled") || err.contains("(-128)") {
+                return Err(AppError::PermissionDenied(
+                    "用户取消了管理员权限授权".to_string(),
+                ));
+            }
+            return Err(AppError::BlockerError(format!(
+                "管理员命令执行失败: {}",
+                err.trim()
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// 以管理员权限查询 anchor 规则和 pf 状态，与写入的规则文件比较
+    fn query_pf(expected: &str) -> Result<FirewallStatus, AppError> {
+        let shell_cmd = format!(
+            "pfctl -a {} -s rules 2>/dev/null; echo {}; pfctl -s info 2>/dev/null",
+            PF_ANCHOR_NAME, PF_INFO_SEPARATOR
+        );
+        let output = Self::run_privileged(&shell_cmd)?;
+        Ok(match output.split_once(PF_INFO_SEPARATOR) {
+            Some((rules, info)) if pf_intact(expected, rules, info) => FirewallStatus::Intact,
+            Some(_) => FirewallStatus::Tampered,
+            None => FirewallStatus::Unknown,
+        })
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl SiteBlockBackend for MacosBackend {
+    fn name(&self) -> &'static str {
+        "macOS"
+    }
+
+    fn hosts_path(&self) -> PathBuf {
+        PathBuf::from(HOSTS_PATH)
+    }
+
+    fn blocks_ips(&self) -> bool {
+        true
+    }
+
+    fn apply(&self, hosts: &str, firewall: &FirewallRules) -> Result<(), AppError> {
+        let temp_hosts = write_temp_file(HOSTS_TEMP_PREFIX, hosts)?;
+
+        if !firewall.is_empty() {
+            fs::write(PF_RULES_PATH, generate_pf_rules(firewall))
+                .map_err(|e| AppError::IoError(format!("写入 pf 规则文件失败: {}", e)))?;
+            println!("[SiteBlocker] pf 规则文件已生成");
+        }
+
+        // 合并所有 sudo 操作为一条命令：hosts 写入失败即整体失败，pf 和 DNS 缓存尽力而为；
+        // chmod 646 让 unblock 时无需 sudo
+        let shell_cmd = format!(
+            "cp {} {} && chmod 646 {} && {{ \
+             pfctl -a {} -f {} 2>/dev/null; \
+             pfctl -e 2>/dev/null; \
+             dscacheutil -flushcache; \
+             killall -HUP mDNSResponder; \
+             true; }}",
+            temp_hosts.display(),
+            HOSTS_PATH,
+            HOSTS_PATH,
+            PF_ANCHOR_NAME,
+            PF_RULES_PATH
+        );
+
+        println!("[SiteBlocker] 执行合并命令（一次授权）");
+
+        let result = Self::run_privileged(&shell_cmd);
+        let _ = fs::remove_file(&temp_hosts);
+        result?;
+
+        // 规则已重新载入，下次检查时重新查询
+        *self.pf_checked.lock().unwrap() = None;
+        println!("[SiteBlocker] 屏蔽命令执行成功");
+        Ok(())
+    }
+
+    fn restore(&self, hosts: Option<&str>) -> Result<(), AppError> {
+        println!("[SiteBlocker] 执行清理命令（无需密码）");
+
+        let pf_output = Command::new("pfctl")
+            .args(["-a", PF_ANCHOR_NAME, "-F", "all"])
+            .output();
+        match pf_output {
+            Ok(out) if out.status.success() => {
+                println!("[SiteBlocker] pf 规则清理成功");
+            }
+            Ok(out) => {
+                let err = String::from_utf8_lossy(&out.stderr);
+                println!("[SiteBlocker] pf 清理警告（可忽略）: {}", err);
+            }
+            Err(e) => {
+                println!("[SiteBlocker] pf 清理失败（可忽略）: {}", e);
+            }
+        }
+
+        // 写回失败时仍清理 pf 规则文件和 DNS 缓存，最后返回错误
+        let written = hosts.map_or(Ok(()), |hosts| {
+            write_hosts_directly(HOSTS_PATH, hosts).map(|()| {
+                let _ = Command::new("chmod").args(["644", HOSTS_PATH]).output();
+            })
+        });
+
+        // 刷新 DNS（不需要 sudo）
+        let _ = Command::new("dscacheutil").arg("-flushcache").output();
+        let _ = Command::new("killall")
+            .args(["-HUP", "mDNSResponder"])
+            .output();
+
+        let _ = fs::remove_file(PF_RULES_PATH);
+
+        println!("[SiteBlocker] 清理命令执行完成");
+        written
+    }
+
+    /// 只写回 hosts 并保持 646，写回后仍在生效的屏蔽区块还能无需授权解除
+    fn write_hosts(&self, hosts: &str) -> Result<(), AppError> {
+        write_hosts_directly(HOSTS_PATH, hosts)
+    }
+
+    /// 规则文件只在应用了防火墙规则时存在，内容即 generate_pf_rules 的结果
+    ///
+    /// 查询需要管理员授权，PF_CHECK_INTERVAL 内沿用上次的结果；用户取消授权后返回 Unknown、不再查询
+    fn firewall_status(&self) -> FirewallStatus {
+        let Ok(expected) = fs::read_to_string(PF_RULES_PATH) else {
+            return FirewallStatus::Intact;
+        };
+        if self.pf_check_denied.load(Ordering::SeqCst) {
+            return FirewallStatus::Unknown;
+        }
+
+        let mut checked = self.pf_checked.lock().unwrap();
+        if let Some((at, status)) = *checked {
+            if at.elapsed() < PF_CHECK_INTERVAL {
+                return status;
+            }
+        }
+        let status = match Self::query_pf(&expected) {
+            Ok(status) => status,
+            Err(e) => {
+                eprintln!("[SiteBlocker] 查询 pf 状态失败: {}", e);
+                if matches!(e, AppError::PermissionDenied(_)) {
+                    self.pf_check_denied.store(true, Ordering::SeqCst);
+                }
+                FirewallStatus::Unknown
+            }
+        };
+        *checked = Some((Instant::now(), status));
+        status
+    }
+}
+
+/// Linux：通过 pkexec（polkit）授权写入 hosts 和 nftables 规则，刷新 systemd-resolved 和 nscd 的缓存
+///
+/// hosts 始终保持 644，屏蔽和解除屏蔽都需要授权
+#[cfg(target_os = "linux")]
+pub struct LinuxBackend;
+
+#[cfg(target_os = "linux")]
+impl LinuxBackend {
+    /// 以 root 执行的 DNS 缓存刷新命令，未安装的服务直接跳过
+    const FLUSH_CMD: &'static str = "(resolvectl flush-caches 2>/dev/null || \
+         systemd-resolve --flush-caches 2>/dev/null || true) && \
+         (nscd -i hosts 2>/dev/null || true)";
+
+    /// pkexec 的退出码 126 表示用户取消授权，127 表示未获授权或没有认证代理
+    fn check_pkexec(output: &std::process::Output) -> Result<(), AppError> {
+        if output.status.success() {
+            return Ok(());
+        }
+        match output.status.code() {
+            Some(126) => Err(AppError::PermissionDenied(
+                "用户取消了管理员权限授权".to_string(),
+            )),
+            Some(127) => Err(AppError::PermissionDenied(
+                "未获得管理员权限（请确认已运行 polkit 认证代理）".to_string(),
+            )),
+            _ => Err(AppError::BlockerError(format!(
+                "管理员命令执行失败: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))),
+        }
+    }
+
+    /// 以 root 执行命令，结束后删除命令用到的临时文件
+    fn run_pkexec(shell_cmd: &str, temp_files: &[&PathBuf]) -> Result<(), AppError> {
+        let output = Command::new("pkexec")
+            .args(["sh", "-c", shell_cmd])
+            .output()
+            .map_err(|e| AppError::PermissionDenied(format!("无法运行 pkexec: {}", e)));
+
+        for path in temp_files {
+            let _ = fs::remove_file(path);
+        }
+        Self::check_pkexec(&output?)
+    }
+
+    /// 复制临时 hosts 到系统位置并保持 644 的命令
+    fn copy_hosts_cmd(temp_hosts: &std::path::Path) -> String {
+        format!(
+            "cp {} {} && chmod 644 {}",
+            temp_hosts.display(),
+            HOSTS_PATH,
+            HOSTS_PATH
+        )
+    }
+
+    /// 以 root 写入 hosts（权限保持 644）并刷新 DNS 缓存
+    fn install_hosts(hosts: &str) -> Result<(), AppError> {
+        let temp_hosts = write_temp_file(HOSTS_TEMP_PREFIX, hosts)?;
+        let shell_cmd = format!(
+            "{} && {}",
+            Self::copy_hosts_cmd(&temp_hosts),
+            Self::FLUSH_CMD
+        );
+        Self::run_pkexec(&shell_cmd, &[&temp_hosts])
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl SiteBlockBackend for LinuxBackend {
+    fn name(&self) -> &'static str {
+        "Linux"
+    }
+
+    fn hosts_path(&self) -> PathBuf {
+        PathBuf::from(HOSTS_PATH)
+    }
+
+    fn blocks_ips(&self) -> bool {
+        true
+    }
+
+    fn apply(&self, hosts: &str, firewall: &FirewallRules) -> Result<(), AppError> {
+        let temp_hosts = write_temp_file(HOSTS_TEMP_PREFIX, hosts)?;
+        let temp_rules =
+            match write_temp_file(NFT_TEMP_PREFIX, &generate_nft_rules(NFT_TABLE, firewall)) {
+                Ok(path) => path,
+                Err(e) => {
+                    let _ = fs::remove_file(&temp_hosts);
+                    return Err(e);
+                }
+            };
+
+        // 白名单模式在系统层只靠防火墙限制，nft 载入失败（如未安装 nftables）时整体失败；
+        // 黑名单模式已有 hosts 屏蔽，防火墙尽力而为
+        let nft_cmd = match firewall {
+            FirewallRules::AllowOnly(_) => format!("nft -f {}", temp_rules.display()),
+            FirewallRules::Block(_) => {
+                format!("{{ nft -f {} 2>/dev/null || true; }}", temp_rules.display())
+            }
+        };
+        let shell_cmd = format!(
+            "{} && {} && {}",
+            Self::copy_hosts_cmd(&temp_hosts),
+            nft_cmd,
+            Self::FLUSH_CMD
+        );
+
+        println!("[SiteBlocker] 通过 pkexec 执行屏蔽命令（一次授权）");
+        Self::run_pkexec(&shell_cmd, &[&temp_hosts, &temp_rules])?;
+        println!("[SiteBlocker] 屏蔽命令执行成功");
+        Ok(())
+    }
+
+    /// hosts 无需改动时不弹授权；启动时的残留清理因此不会每次都要求密码
+    fn restore(&self, hosts: Option<&str>) -> Result<(), AppError> {
+        let Some(hosts) = hosts else {
+            return Ok(());
+        };
+
+        let temp_hosts = write_temp_file(HOSTS_TEMP_PREFIX, hosts)?;
+        let shell_cmd = format!(
+            "{} && {{ nft delete table {} 2>/dev/null || true; }} && {}",
+            Self::copy_hosts_cmd(&temp_hosts),
+            NFT_TABLE,
+            Self::FLUSH_CMD
+        );
+
+        println!("[SiteBlocker] 通过 pkexec 写回 hosts 并移除防火墙规则");
+        Self::run_pkexec(&shell_cmd, &[&temp_hosts])?;
+        println!("[SiteBlocker] hosts 文件恢复成功");
+        Ok(())
+    }
+
+    fn write_hosts(&self, hosts: &str) -> Result<(), AppError> {
+        Self::install_hosts(hosts)
+    }
+
+    /// 列出 nftables 规则需要 root，不弹授权就无法检查
+    fn firewall_status(&self) -> FirewallStatus {
+        FirewallStatus::Unknown
+    }
+}
+
+/// 其他平台：不支持系统级网站屏蔽，屏蔽时返回错误；从未写入过 hosts，解除屏蔽无需操作
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+pub struct UnsupportedBackend;
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+impl UnsupportedBackend {
+    fn unsupported() -> AppError {
+        AppError::BlockerError(format!(
+            "当前平台（{}）不支持系统级网站屏蔽",
+            std::env::consts::OS
+        ))
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+impl SiteBlockBackend for UnsupportedBackend {
+    fn name(&self) -> &'static str {
+        "unsupported"
+    }
+
+    fn hosts_path(&self) -> PathBuf {
+        PathBuf::from(HOSTS_PATH)
+    }
+
+    fn apply(&self, _hosts: &str, _firewall: &FirewallRules) -> Result<(), AppError> {
+        Err(Self::unsupported())
+    }
+
+    fn restore(&self, _hosts: Option<&str>) -> Result<(), AppError> {
+        Ok(())
+    }
+
+    fn write_hosts(&self, _hosts: &str) -> Result<(), AppError> {
+        Err(Self::unsupported())
+    }
+}
+
+/// 只读写指定目录下的 hosts 文件，不需要权限、不修改系统，用于测试和调试
+pub struct SandboxBackend {
+    root: PathBuf,
+}
+
+impl SandboxBackend {
+    pub fn new(root: PathBuf) -> Self {
+        SandboxBackend { root }
+    }
+}
+
+impl SiteBlockBackend for SandboxBackend {
+    fn name(&self) -> &'static str {
+        "sandbox"
+    }
+
+    fn hosts_path(&self) -> PathBuf {
+        self.root.join("hosts")
+    }
+
+    fn backup_path(&self) -> Result<PathBuf, AppError> {
+        Ok(self.root.join("hosts.backup"))
+    }
+
+    fn apply(&self, hosts: &str, _firewall: &FirewallRules) -> Result<(), AppError> {
+        fs::create_dir_all(&self.root)?;
+        fs::write(self.hosts_path(), hosts)?;
+        Ok(())
+    }
+
+    fn restore(&self, hosts: Option<&str>) -> Result<(), AppError> {
+        match hosts {
+            Some(hosts) => self.write_hosts(hosts),
+            None => Ok(()),
+        }
+    }
+
+    fn write_hosts(&self, hosts: &str) -> Result<(), AppError> {
+        fs::write(self.hosts_path(), hosts)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ips(list: &[&str]) -> HashSet<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_pf_rules_cover_ipv4_and_ipv6() {
+        let firewall = FirewallRules::Block(ips(&[
+            "2606:2800:220:1::1946",
+            "93.184.216.34",
+            "not-an-ip",
+        ]));
+        let rules = generate_pf_rules(&firewall);
+        assert_eq!(
+            rules,
+            "# Pomodoro Focus - Site Blocking Rules\n\
+             block drop out inet proto tcp from any to 93.184.216.34 port {80, 443}\n\
+             block drop out inet6 proto tcp from any to 2606:2800:220:1::1946 port {80, 443}\n"
+        );
+    }
+
+    #[test]
+    fn test_pf_allowlist_rules_pass_then_block_all() {
+        let firewall = FirewallRules::AllowOnly(ips(&["2001:db8::1", "10.0.0.8"]));
+        let rules = generate_pf_rules(&firewall);
+        assert_eq!(
+            rules,
+            "# Pomodoro Focus - Site Blocking Rules\n\
+             pass out quick on lo0 all\n\
+             pass out quick inet proto tcp from any to 10.0.0.8 port {80, 443}\n\
+             pass out quick inet6 proto tcp from any to 2001:db8::1 port {80, 443}\n\
+             block drop out proto tcp from any to any port {80, 443}\n\
+             block drop out proto udp from any to any port 443\n"
+        );
+        assert!(!FirewallRules::AllowOnly(HashSet::new()).is_empty());
+        assert!(FirewallRules::Block(HashSet::new()).is_empty());
+    }
+
+    #[test]
+    fn test_nft_rules_replace_table() {
+        let table = "inet pomodoro_focus";
+        let block = generate_nft_rules(
+            table,
+            &FirewallRules::Block(ips(&["2606:2800:220:1::1946", "93.184.216.34"])),
+        );
+        assert_eq!(
+            block,
+            "table inet pomodoro_focus\n\
+             delete table inet pomodoro_focus\n\
+             table inet pomodoro_focus {\n\
+             \tchain output {\n\
+             \t\ttype filter hook output priority 0; policy accept;\n\
+             \t\tip daddr 93.184.216.34 tcp dport { 80, 443 } drop\n\
+             \t\tip6 daddr 2606:2800:220:1::1946 tcp dport { 80, 443 } drop\n\
+             \t}\n\
+             }\n"
+        );
+
+        let allow = generate_nft_rules(table, &FirewallRules::AllowOnly(ips(&["10.0.0.8"])));
+        assert!(allow.ends_with(
+            "\t\toifname \"lo\" accept\n\
+             \t\tip daddr 10.0.0.8 tcp dport { 80, 443 } accept\n\
+             \t\ttcp dport { 80, 443 } drop\n\
+             \t\tudp dport 443 drop\n\
+             \t}\n\
+             }\n"
+        ));
+    }
+
+    #[test]
+    fn test_pf_intact_detects_flush_and_disable() {
+        let expected = generate_pf_rules(&FirewallRules::Block(ips(&["93.184.216.34"])));
+        let rules = "block drop out inet proto tcp from any to 93.184.216.34 port = http\n\
+                     block drop out inet proto tcp from any to 93.184.216.34 port = https\n";
+        let enabled = "Status: Enabled for 0 days 00:10:02           Debug: Urgent\n";
+        let disabled = "Status: Disabled for 0 days 00:00:05          Debug: Urgent\n";
+        assert!(pf_intact(&expected, rules, enabled));
+        assert!(!pf_intact(&expected, "", enabled));
+        assert!(!pf_intact(&expected, rules, disabled));
+    }
+
+    #[test]
+    fn test_pf_intact_detects_rule_mismatch() {
+        let enabled = "Status: Enabled for 0 days 00:10:02           Debug: Urgent\n";
+        let expected = generate_pf_rules(&FirewallRules::AllowOnly(ips(&["10.0.0.8", "10.0.0.9"])));
+        let listed = "pass out quick on lo0 all flags S/SA keep state\n\
+                      pass out quick inet proto tcp from any to 10.0.0.8 port = http flags S/SA keep state\n\
+                      pass out quick inet proto tcp from any to 10.0.0.8 port = https flags S/SA keep state\n\
+                      pass out quick inet proto tcp from any to 10.0.0.9 port = http flags S/SA keep state\n\
+                      pass out quick inet proto tcp from any to 10.0.0.9 port = https flags S/SA keep state\n\
+                      block drop out proto tcp from any to any port = http\n\
+                      block drop out proto tcp from any to any port = https\n\
+                      block drop out proto udp from any to any port = https\n";
+        assert!(pf_intact(&expected, listed, enabled));
+
+        // 换掉放行的地址、删掉 QUIC 屏蔽或换成别的规则都算失效
+        let swapped = listed.replace("10.0.0.9", "203.0.113.7");
+        assert!(!pf_intact(&expected, &swapped, enabled));
+        let no_quic = listed.replace(
+            "block drop out proto udp from any to any port = https\n",
+            "",
+        );
+        assert!(!pf_intact(&expected, &no_quic, enabled));
+        let replaced = "pass out quick all flags S/SA keep state\n";
+        assert!(!pf_intact(&expected, replaced, enabled));
+    }
+
+    #[test]
+    fn test_temp_hosts_is_new_private_file() {
+        let first = write_temp_file(HOSTS_TEMP_PREFIX, "127.0.0.1 localhost\n").unwrap();
+        let second = write_temp_file(HOSTS_TEMP_PREFIX, "::1 localhost\n").unwrap();
+        assert_ne!(first, second);
+        assert_eq!(fs::read_to_string(&first).unwrap(), "127.0.0.1 localhost\n");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&first).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let _ = fs::remove_file(first);
+        let _ = fs::remove_file(second);
+    }
+}
@@ -1,35 +1,88 @@
 use crate::errors::AppError;
-use crate::models::Config;
+use crate::services::{
+    default_backend, DnsResolver, FirewallRules, FirewallStatus, ResolveFailure, SiteBlockBackend,
+    SiteRules,
+};
 use std::collections::HashSet;
 use std::fs;
-use std::path::PathBuf;
-use std::process::Command;
+use std::sync::Arc;
 
-const HOSTS_PATH: &str = "/etc/hosts";
 const BLOCK_MARKER_START: &str = "# === POMODORO FOCUS BLOCK START ===";
-const BLOCK_MARKER_END: &str = "# === POMODORO FOCUS BLOCK END ===";
-const PF_ANCHOR_NAME: &str = "pomodoro-focus";
-const PF_RULES_PATH: &str = "/tmp/pomodoro_pf_rules.conf";
+pub(crate) const BLOCK_MARKER_END: &str = "# === POMODORO FOCUS BLOCK END ===";
+/// 网站或域名超过该数量时视为导入的大列表：不补 www 变体，每个域名只写 0.0.0.0 和 :: 两行
+const LARGE_LIST_SITES: usize = 1000;
+/// 最多为这么多个域名解析 IP 生成防火墙规则；合并后的列表中用户条目在前，其余只由 hosts 和 DNS 层屏蔽
+const MAX_RESOLVE_DOMAINS: usize = 500;
+
+/// 专注期间发现的屏蔽失效
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockDrift {
+    /// hosts 中的屏蔽区块被删除或改动
+    HostsSection,
+    /// 防火墙规则被清空或防火墙被停用
+    Firewall,
+}
+
+impl BlockDrift {
+    pub fn description(&self) -> &'static str {
+        match self {
+            BlockDrift::HostsSection => "hosts 屏蔽区块被删除或改动",
+            BlockDrift::Firewall => "防火墙规则被清空或防火墙被停用",
+        }
+    }
+}
+
+/// 一次完整性检查的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntegrityReport {
+    pub drift: Vec<BlockDrift>,
+    /// 无法查询防火墙状态，只检查了 hosts
+    pub firewall_unknown: bool,
+}
 
 pub struct SiteBlocker {
-    blocked_sites: Vec<String>,
+    /// 本次专注的网站规则（黑名单或白名单）
+    rules: SiteRules,
+    /// 平台相关的 hosts 写入、授权和防火墙实现
+    backend: Arc<dyn SiteBlockBackend>,
 }
 
 impl SiteBlocker {
+    /// 黑名单规则，使用当前平台默认后端
     pub fn new(blocked_sites: Vec<String>) -> Self {
-        SiteBlocker { blocked_sites }
+        Self::from_rules(SiteRules::Blocklist(blocked_sites))
     }
 
-    pub fn update_blocked_sites(&mut self, sites: Vec<String>) {
-        self.blocked_sites = sites;
+    pub fn from_rules(rules: SiteRules) -> Self {
+        Self::with_rules(rules, default_backend())
     }
 
-    pub fn get_blocked_sites(&self) -> &Vec<String> {
-        &self.blocked_sites
+    pub fn with_backend(blocked_sites: Vec<String>, backend: Arc<dyn SiteBlockBackend>) -> Self {
+        Self::with_rules(SiteRules::Blocklist(blocked_sites), backend)
+    }
+
+    pub fn with_rules(rules: SiteRules, backend: Arc<dyn SiteBlockBackend>) -> Self {
+        SiteBlocker { rules, backend }
+    }
+
+    pub fn update_rules(&mut self, rules: SiteRules) {
+        self.rules = rules;
+    }
+
+    pub fn rules(&self) -> &SiteRules {
+        &self.rules
+    }
+
+    /// 黑名单中的网站，白名单模式下为空
+    pub fn get_blocked_sites(&self) -> Vec<String> {
+        match &self.rules {
+            SiteRules::Blocklist(sites) => sites.clone(),
+            SiteRules::Allowlist(_) => Vec::new(),
+        }
     }
 
     /// 清理域名：去掉协议前缀和末尾斜杠，只保留纯域名
-    fn clean_domain(site: &str) -> String {
+    pub fn clean_domain(site: &str) -> String {
         let mut domain = site.trim().to_string();
 
         // 去掉协议前缀
@@ -52,273 +105,287 @@ impl SiteBlocker {
         domain.to_lowercase()
     }
 
-    /// 使用 dig 命令解析域名获取 IP 地址
-    fn resolve_domain_ips(domain: &str) -> Vec<String> {
-        let output = Command::new("dig")
-            .args(["+short", domain])
-            .output();
-
-        match output {
-            Ok(out) => {
-                let stdout = String::from_utf8_lossy(&out.stdout);
-                stdout
-                    .lines()
-                    .filter(|line| {
-                        // 只保留 IPv4 地址
-                        line.parse::<std::net::Ipv4Addr>().is_ok()
-                    })
-                    .map(|s| s.to_string())
-                    .collect()
-            }
-            Err(e) => {
-                println!("[SiteBlocker] dig 解析 {} 失败: {}", domain, e);
-                Vec::new()
-            }
+    /// 检查是否为合法的域名（已经过 clean_domain 处理）
+    pub fn is_valid_domain(domain: &str) -> bool {
+        if domain.is_empty() || domain.len() > 253 || !domain.contains('.') {
+            return false;
         }
+
+        domain.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
     }
 
-    /// 生成 pf 规则文件内容
-    fn generate_pf_rules(ips: &HashSet<String>) -> String {
-        let mut rules = String::new();
-        rules.push_str("# Pomodoro Focus - Site Blocking Rules\n");
+    /// 屏蔽网站（使用 pf 防火墙 + hosts 双保险，一次授权）
+    ///
+    /// 返回解析 IP 失败的域名：黑名单中这些域名只有 hosts 屏蔽，白名单中这些域名会被防火墙拦截
+    pub fn block_sites(&self) -> Result<Vec<ResolveFailure>, AppError> {
+        println!("[SiteBlocker] 开始屏蔽网站（{}）...", self.backend.name());
+
+        // 系统级屏蔽只能看到主机名，路径规则由浏览器扩展执行
+        let host_sites = self.rules.host_sites();
+        let (domains, firewall, failures) = match &self.rules {
+            SiteRules::Blocklist(_) => {
+                let domains = Self::expand_domains(&host_sites);
+                if domains.is_empty() {
+                    return Ok(Vec::new());
+                }
+                // 需要防火墙规则的后端才解析 IP
+                let (ips, failures) = if self.backend.blocks_ips() {
+                    Self::resolve_ips(Self::resolve_limit(&domains))
+                } else {
+                    (HashSet::new(), Vec::new())
+                };
+                (domains, FirewallRules::Block(ips), failures)
+            }
+            // hosts 无法表达"只允许"，只写入空的屏蔽区块作为标记；系统级限制由防火墙只放行白名单 IP
+            SiteRules::Allowlist(_) => {
+                let (ips, failures) = if self.backend.blocks_ips() {
+                    Self::resolve_ips(Self::resolve_limit(&Self::expand_domains(&host_sites)))
+                } else {
+                    println!(
+                        "[SiteBlocker] {} 没有防火墙规则，白名单只由浏览器扩展和本地 DNS 转发限制",
+                        self.backend.name()
+                    );
+                    (HashSet::new(), Vec::new())
+                };
+                (Vec::new(), FirewallRules::AllowOnly(ips), failures)
+            }
+        };
 
-        for ip in ips {
-            rules.push_str(&format!(
-                "block drop out proto tcp from any to {} port {{80, 443}}\n",
-                ip
-            ));
+        // 备份去掉屏蔽区块后的 hosts，失败时据此回滚
+        let current_hosts = self.read_hosts()?;
+        let clean_hosts = self.clean_hosts(&current_hosts);
+        self.backup_hosts(&clean_hosts)?;
+        let new_hosts = Self::build_hosts(&clean_hosts, &domains);
+
+        // 一次性执行所有需要管理员权限的操作，写入后重新读取校验
+        let result = self.backend.apply(&new_hosts, &firewall).and_then(|()| {
+            let actual = self.read_hosts()?;
+            Self::check_blocked(&actual, &new_hosts)
+                .map_err(|e| AppError::BlockerError(format!("hosts 校验失败，已回滚: {}", e)))
+        });
+        if let Err(e) = result {
+            self.rollback(&current_hosts);
+            return Err(e);
         }
 
-        rules
+        println!("[SiteBlocker] 网站屏蔽完成");
+        Ok(failures)
     }
 
-    /// 屏蔽网站（使用 pf 防火墙 + hosts 双保险，一次授权）
-    pub fn block_sites(&self) -> Result<(), AppError> {
-        if self.blocked_sites.is_empty() {
-            return Ok(());
+    /// 需要解析 IP 的域名：只取前 MAX_RESOLVE_DOMAINS 个，避免大列表在开始专注时发出数万次查询
+    fn resolve_limit(domains: &[String]) -> &[String] {
+        if domains.len() <= MAX_RESOLVE_DOMAINS {
+            return domains;
         }
+        println!(
+            "[SiteBlocker] 共 {} 个域名，只为前 {} 个解析 IP",
+            domains.len(),
+            MAX_RESOLVE_DOMAINS
+        );
+        &domains[..MAX_RESOLVE_DOMAINS]
+    }
 
-        println!("[SiteBlocker] 开始屏蔽网站...");
+    /// 并发解析所有域名的 IP；没有可用的 DNS 服务器时全部记为失败
+    fn resolve_ips(domains: &[String]) -> (HashSet<String>, Vec<ResolveFailure>) {
+        let Some(resolver) = DnsResolver::system() else {
+            let failures = domains
+                .iter()
+                .map(|domain| ResolveFailure {
+                    domain: domain.clone(),
+                    reason: "未找到 DNS 服务器".to_string(),
+                })
+                .collect();
+            return (HashSet::new(), failures);
+        };
 
-        // 收集所有需要屏蔽的 IP 和域名
-        let mut all_ips: HashSet<String> = HashSet::new();
-        let mut clean_domains: Vec<String> = Vec::new();
+        let report = resolver.resolve_all(domains);
+        println!(
+            "[SiteBlocker] 解析到 {} 个 IP，{} 个域名失败",
+            report.ips.len(),
+            report.failures.len()
+        );
+        for failure in &report.failures {
+            println!("[SiteBlocker] 解析 {} 失败: {}", failure.domain, failure.reason);
+        }
+        let ips = report.ips.iter().map(|ip| ip.to_string()).collect();
+        (ips, report.failures)
+    }
 
-        for site in &self.blocked_sites {
+    /// 清理后的域名列表，自动补上 www 变体并去重；大列表（通常已含 www 条目）不补变体
+    fn expand_domains(sites: &[String]) -> Vec<String> {
+        let mut domains: Vec<String> = Vec::new();
+        let mut seen: HashSet<String> = HashSet::new();
+        let add_variants = sites.len() <= LARGE_LIST_SITES;
+
+        for site in sites {
             let clean_site = Self::clean_domain(site);
             if clean_site.is_empty() {
                 continue;
             }
-            clean_domains.push(clean_site.clone());
-            // 自动添加 www 变体
-            if clean_site.starts_with("www.") {
-                let bare = clean_site[4..].to_string();
-                if !clean_domains.contains(&bare) {
-                    clean_domains.push(bare);
-                }
-            } else {
-                let www = format!("www.{}", clean_site);
-                if !clean_domains.contains(&www) {
-                    clean_domains.push(www);
+            let variant = match clean_site.strip_prefix("www.") {
+                Some(bare) => bare.to_string(),
+                None => format!("www.{}", clean_site),
+            };
+            let variant = add_variants.then_some(variant);
+            for domain in std::iter::once(clean_site).chain(variant) {
+                if seen.insert(domain.clone()) {
+                    domains.push(domain);
                 }
             }
-
-            // 解析域名获取 IP
-            let ips = Self::resolve_domain_ips(&clean_site);
-            println!("[SiteBlocker] {} -> {:?}", clean_site, ips);
-            for ip in ips {
-                all_ips.insert(ip);
-            }
-        }
-
-        if clean_domains.is_empty() {
-            return Ok(());
         }
 
-        // 准备临时文件
-        self.prepare_block_files(&clean_domains, &all_ips)?;
-
-        // 一次性执行所有需要管理员权限的操作
-        self.execute_block_commands()?;
-
-        println!("[SiteBlocker] 网站屏蔽完成");
-        Ok(())
+        domains
     }
 
-    /// 准备屏蔽所需的临时文件（hosts 和 pf 规则）
-    fn prepare_block_files(
-        &self,
-        domains: &[String],
-        ips: &HashSet<String>,
-    ) -> Result<(), AppError> {
-        // 1. 备份并准备 hosts 文件
-        self.backup_hosts()?;
-
-        let current_hosts = fs::read_to_string(HOSTS_PATH)
-            .map_err(|e| AppError::IoError(format!("读取 hosts 失败: {}", e)))?;
-
-        // 移除旧的屏蔽记录
-        let clean_hosts = self.remove_block_section(&current_hosts);
-
-        // 构建新的 hosts 内容
-        let mut new_hosts = clean_hosts;
-        new_hosts.push_str("\n");
+    /// 在不含屏蔽区块的 hosts 开头插入屏蔽区块，原有内容逐字节保留在其后
+    ///
+    /// 放在开头不需要改动原文件末尾的换行，同一域名也以先出现的屏蔽记录为准
+    fn build_hosts(clean_hosts: &str, domains: &[String]) -> String {
+        let mut new_hosts = String::new();
         new_hosts.push_str(BLOCK_MARKER_START);
         new_hosts.push('\n');
+        let compact = domains.len() > LARGE_LIST_SITES;
         for domain in domains {
             new_hosts.push_str(&format!("0.0.0.0 {}\n", domain));
-            new_hosts.push_str(&format!("127.0.0.1 {}\n", domain));
+            if !compact {
+                new_hosts.push_str(&format!("127.0.0.1 {}\n", domain));
+            }
+            new_hosts.push_str(&format!(":: {}\n", domain));
         }
         new_hosts.push_str(BLOCK_MARKER_END);
         new_hosts.push('\n');
+        new_hosts.push_str(clean_hosts);
+        new_hosts
+    }
 
-        // 写入临时 hosts 文件
-        fs::write("/tmp/pomodoro_hosts_temp", &new_hosts).map_err(|e| {
-            AppError::IoError(format!("写入临时 hosts 文件失败: {}", e))
-        })?;
-
-        // 2. 准备 pf 规则文件
-        if !ips.is_empty() {
-            let rules = Self::generate_pf_rules(ips);
-            fs::write(PF_RULES_PATH, &rules).map_err(|e| {
-                AppError::IoError(format!("写入 pf 规则文件失败: {}", e))
-            })?;
-            println!("[SiteBlocker] pf 规则文件已生成");
+    /// 校验写入后的 hosts 与预期完全一致，不一致时说明是区块还是区块外的内容有误
+    fn check_blocked(actual: &str, expected: &str) -> Result<(), String> {
+        if actual == expected {
+            Ok(())
+        } else if Self::block_section(actual) != Self::block_section(expected) {
+            Err("屏蔽区块缺失或内容不符".to_string())
+        } else {
+            Err("屏蔽区块以外的内容被改动".to_string())
         }
-
-        Ok(())
     }
 
-    /// 一次性执行所有需要管理员权限的屏蔽命令
-    fn execute_block_commands(&self) -> Result<(), AppError> {
-        // 合并所有 sudo 操作为一条命令，末尾 chmod 646 让 unblock 时无需 sudo
-        let shell_cmd = format!(
-            "cp /tmp/pomodoro_hosts_temp {} && \
-             pfctl -a {} -f {} 2>/dev/null || true && \
-             pfctl -e 2>/dev/null || true && \
-             dscacheutil -flushcache && \
-             killall -HUP mDNSResponder && \
-             chmod 646 {}",
-            HOSTS_PATH, PF_ANCHOR_NAME, PF_RULES_PATH, HOSTS_PATH
-        );
-
-        println!("[SiteBlocker] 执行合并命令（一次授权）");
-
-        let applescript = format!(
-            "do shell script \"{}\" with administrator privileges",
-            shell_cmd
-        );
+    /// 屏蔽失败后恢复 hosts：内容未被改动时不处理，否则写回屏蔽前的内容
+    ///
+    /// 不使用 hosts.backup：备份已去掉屏蔽区块，重新屏蔽失败时会连同仍在生效的屏蔽一起删掉
+    fn rollback(&self, before: &str) {
+        if self.read_hosts().is_ok_and(|content| content == before) {
+            return;
+        }
 
-        let output = Command::new("osascript")
-            .arg("-e")
-            .arg(&applescript)
-            .output()
-            .map_err(|e| {
-                AppError::PermissionDenied(format!("请求管理员权限失败: {}", e))
-            })?;
+        println!("[SiteBlocker] 屏蔽失败，回滚 hosts");
+        if let Err(e) = self.backend.write_hosts(before) {
+            eprintln!("[SiteBlocker] 回滚 hosts 失败: {}", e);
+        }
+    }
 
-        // 清理临时文件
-        let _ = fs::remove_file("/tmp/pomodoro_hosts_temp");
+    /// 按当前规则应有的屏蔽区块；黑名单没有可屏蔽的域名时不写区块，返回 None
+    fn expected_section(&self) -> Option<String> {
+        match &self.rules {
+            SiteRules::Blocklist(_) => {
+                let domains = Self::expand_domains(&self.rules.host_sites());
+                (!domains.is_empty()).then(|| Self::build_hosts("", &domains))
+            }
+            SiteRules::Allowlist(_) => Some(Self::build_hosts("", &[])),
+        }
+    }
 
-        if !output.status.success() {
-            let err = String::from_utf8_lossy(&output.stderr);
-            if err.contains("User cancelled") || err.contains("(-128)") {
-                return Err(AppError::PermissionDenied(
-                    "用户取消了管理员权限授权".to_string(),
-                ));
+    /// 检查 hosts 屏蔽区块和防火墙规则是否仍与本次规则一致
+    pub fn check_integrity(&self) -> Result<IntegrityReport, AppError> {
+        let mut drift = Vec::new();
+        if let Some(expected) = self.expected_section() {
+            let actual = self.read_hosts()?;
+            if Self::block_section(&actual) != Some(expected.as_str()) {
+                drift.push(BlockDrift::HostsSection);
             }
-            println!("[SiteBlocker] 命令执行警告: {}", err);
-        } else {
-            println!("[SiteBlocker] 屏蔽命令执行成功");
         }
+        let firewall = self.backend.firewall_status();
+        if firewall == FirewallStatus::Tampered {
+            drift.push(BlockDrift::Firewall);
+        }
+        Ok(IntegrityReport {
+            drift,
+            firewall_unknown: firewall == FirewallStatus::Unknown,
+        })
+    }
 
-        Ok(())
+    /// 重新应用失效的屏蔽
+    ///
+    /// 只有 hosts 区块失效时只写回 hosts（macOS 屏蔽时已 chmod 646，无需授权；Linux 上通过 pkexec 授权写入），
+    /// 写入失败或防火墙也失效时走完整的屏蔽流程
+    pub fn repair(&self, drift: &[BlockDrift]) -> Result<Vec<ResolveFailure>, AppError> {
+        if drift == [BlockDrift::HostsSection] && self.rewrite_block_section().is_ok() {
+            println!("[SiteBlocker] 已直接写回 hosts 屏蔽区块");
+            return Ok(Vec::new());
+        }
+        self.block_sites()
     }
 
-    /// 恢复网站访问（清理 pf 规则和 hosts 记录，静默执行不弹密码框）
+    fn rewrite_block_section(&self) -> Result<(), AppError> {
+        let expected = self
+            .expected_section()
+            .ok_or_else(|| AppError::BlockerError("没有需要写入的屏蔽区块".to_string()))?;
+        let clean_hosts = self.clean_hosts(&self.read_hosts()?);
+        self.backup_hosts(&clean_hosts)?;
+        let new_hosts = format!("{}{}", expected, clean_hosts);
+        self.backend.write_hosts(&new_hosts)?;
+        Self::check_blocked(&self.read_hosts()?, &new_hosts).map_err(AppError::BlockerError)
+    }
+
+    fn read_hosts(&self) -> Result<String, AppError> {
+        fs::read_to_string(self.backend.hosts_path())
+            .map_err(|e| AppError::IoError(format!("读取 hosts 失败: {}", e)))
+    }
+
+    /// 恢复网站访问（清理防火墙规则和 hosts 记录；macOS 上静默执行不弹密码框，Linux 上需要 pkexec 授权）
     pub fn unblock_sites(&self) -> Result<(), AppError> {
         println!("[SiteBlocker] 开始清理屏蔽规则...");
 
-        // 读取并清理 hosts 内容
-        let hosts_content = match fs::read_to_string(HOSTS_PATH) {
+        let hosts_content = match fs::read_to_string(self.backend.hosts_path()) {
             Ok(content) => content,
             Err(_) => return Ok(()),
         };
 
-        let need_clean_hosts = hosts_content.contains(BLOCK_MARKER_START);
+        let cleaned = hosts_content
+            .contains(BLOCK_MARKER_START)
+            .then(|| self.clean_hosts(&hosts_content));
+        self.backend.restore(cleaned.as_deref())?;
 
-        // 准备清理后的 hosts 文件
-        if need_clean_hosts {
-            let cleaned = self.remove_block_section(&hosts_content);
-            fs::write("/tmp/pomodoro_hosts_clean", &cleaned).map_err(|e| {
-                AppError::IoError(format!("写入临时文件失败: {}", e))
-            })?;
+        if let Some(cleaned) = cleaned {
+            if self.read_hosts()? != cleaned {
+                return Err(AppError::BlockerError(
+                    "解除屏蔽后 hosts 校验失败，屏蔽区块可能仍然存在".to_string(),
+                ));
+            }
         }
 
-        // 一次性执行所有清理命令
-        self.execute_unblock_commands(need_clean_hosts)?;
-
         println!("[SiteBlocker] 屏蔽规则清理完成");
         Ok(())
     }
 
-    /// 一次性执行所有清理命令（无需 sudo，block 时已 chmod 646）
-    fn execute_unblock_commands(&self, need_clean_hosts: bool) -> Result<(), AppError> {
-        println!("[SiteBlocker] 执行清理命令（无需密码）");
-
-        // 清理 pf 规则
-        let pf_output = Command::new("pfctl")
-            .args(["-a", PF_ANCHOR_NAME, "-F", "all"])
-            .output();
-        match pf_output {
-            Ok(out) if out.status.success() => {
-                println!("[SiteBlocker] pf 规则清理成功");
-            }
-            Ok(out) => {
-                let err = String::from_utf8_lossy(&out.stderr);
-                println!("[SiteBlocker] pf 清理警告（可忽略）: {}", err);
-            }
-            Err(e) => {
-                println!("[SiteBlocker] pf 清理失败（可忽略）: {}", e);
-            }
-        }
-
-        // 清理 hosts 文件（block 时已 chmod 646，可直接写入）
-        if need_clean_hosts {
-            match fs::copy("/tmp/pomodoro_hosts_clean", HOSTS_PATH) {
-                Ok(_) => {
-                    println!("[SiteBlocker] hosts 文件恢复成功");
-                    // 恢复 hosts 权限为 644
-                    let _ = Command::new("chmod").args(["644", HOSTS_PATH]).output();
-                }
-                Err(e) => {
-                    println!("[SiteBlocker] hosts 恢复失败: {}", e);
-                }
-            }
+    /// hosts 文件中是否有屏蔽区块
+    pub fn has_block_section(&self) -> bool {
+        match fs::read_to_string(self.backend.hosts_path()) {
+            Ok(content) => content.contains(BLOCK_MARKER_START),
+            Err(_) => false,
         }
-
-        // 刷新 DNS（不需要 sudo）
-        let _ = Command::new("dscacheutil")
-            .arg("-flushcache")
-            .output();
-        let _ = Command::new("killall")
-            .args(["-HUP", "mDNSResponder"])
-            .output();
-
-        // 清理临时文件
-        let _ = fs::remove_file("/tmp/pomodoro_hosts_clean");
-        let _ = fs::remove_file(PF_RULES_PATH);
-
-        println!("[SiteBlocker] 清理命令执行完成");
-        Ok(())
     }
 
     /// 检查 hosts 文件中是否已有屏蔽记录
     pub fn is_blocking_active() -> bool {
-        match fs::read_to_string(HOSTS_PATH) {
-            Ok(content) => content.contains(BLOCK_MARKER_START),
-            Err(_) => false,
-        }
+        SiteBlocker::default().has_block_section()
     }
 
     /// 启动时检查并清理残留的屏蔽记录
@@ -334,9 +401,30 @@ impl SiteBlocker {
         Ok(())
     }
 
-    /// 备份 hosts 文件
-    fn backup_hosts(&self) -> Result<(), AppError> {
-        let backup_path = Self::backup_path()?;
+    /// 去掉屏蔽区块后的 hosts 内容
+    ///
+    /// 区块不完整（如写入中途崩溃丢了结束标记）时按标记删除会连同其后的用户记录一起删掉，
+    /// 此时改用上次屏蔽前保存的 hosts.backup
+    fn clean_hosts(&self, content: &str) -> String {
+        if content.contains(BLOCK_MARKER_START) && Self::block_section(content).is_none() {
+            if let Some(backup) = self.read_backup() {
+                println!("[SiteBlocker] 屏蔽区块不完整，从 hosts.backup 恢复");
+                return backup;
+            }
+        }
+        Self::remove_block_section(content)
+    }
+
+    /// 读取 hosts.backup；不存在或含有屏蔽标记（不是屏蔽前的内容）时返回 None
+    fn read_backup(&self) -> Option<String> {
+        let backup = fs::read_to_string(self.backend.backup_path().ok()?).ok()?;
+        let clean = !backup.contains(BLOCK_MARKER_START) && !backup.contains(BLOCK_MARKER_END);
+        clean.then_some(backup)
+    }
+
+    /// 备份去掉屏蔽区块后的 hosts 内容
+    fn backup_hosts(&self, clean_hosts: &str) -> Result<(), AppError> {
+        let backup_path = self.backend.backup_path()?;
 
         // 确保目录存在
         if let Some(parent) = backup_path.parent() {
@@ -345,32 +433,18 @@ impl SiteBlocker {
             })?;
         }
 
-        // 读取原始 hosts
-        let original_hosts = fs::read_to_string(HOSTS_PATH)
-            .map_err(|e| AppError::IoError(format!("读取 hosts 文件失败: {}", e)))?;
-
-        // 移除已有的屏蔽记录后再备份
-        let clean_hosts = self.remove_block_section(&original_hosts);
-
-        // 写入备份
         fs::write(&backup_path, clean_hosts)
             .map_err(|e| AppError::IoError(format!("写入备份文件失败: {}", e)))?;
 
         Ok(())
     }
 
-    /// 获取备份文件路径
-    fn backup_path() -> Result<PathBuf, AppError> {
-        let config_dir = Config::config_dir()?;
-        Ok(config_dir.join("hosts.backup"))
-    }
-
-    /// 移除屏蔽区块
-    fn remove_block_section(&self, content: &str) -> String {
-        let mut result = Vec::new();
+    /// 移除屏蔽区块（从开始标记行到结束标记行，含换行符），其余内容逐字节保留
+    fn remove_block_section(content: &str) -> String {
+        let mut result = String::with_capacity(content.len());
         let mut in_block = false;
 
-        for line in content.lines() {
+        for line in content.split_inclusive('\n') {
             if line.contains(BLOCK_MARKER_START) {
                 in_block = true;
                 continue;
@@ -380,11 +454,22 @@ impl SiteBlocker {
                 continue;
             }
             if !in_block {
-                result.push(line);
+                result.push_str(line);
             }
         }
 
-        result.join("\n")
+        result
+    }
+
+    /// 屏蔽区块（含两行标记），没有完整区块时返回 None
+    fn block_section(content: &str) -> Option<&str> {
+        let marker = content.find(BLOCK_MARKER_START)?;
+        let start = content[..marker].rfind('\n').map_or(0, |i| i + 1);
+        let end_marker = marker + content[marker..].find(BLOCK_MARKER_END)?;
+        let end = content[end_marker..]
+            .find('\n')
+            .map_or(content.len(), |i| end_marker + i + 1);
+        Some(&content[start..end])
     }
 }
 
@@ -393,3 +478,231 @@ impl Default for SiteBlocker {
         Self::new(Vec::new())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::SandboxBackend;
+    use crate::test_support::{hosts_sandbox, Fault, FaultyBackend};
+    use std::path::PathBuf;
+
+    fn sandbox(name: &str, hosts: &str) -> (PathBuf, Arc<dyn SiteBlockBackend>) {
+        let dir = hosts_sandbox("site_blocker", name, hosts);
+        (dir.clone(), Arc::new(SandboxBackend::new(dir)))
+    }
+
+    #[test]
+    fn test_expand_domains_adds_www_variants() {
+        let sites = vec![
+            "https://Example.com/path".to_string(),
+            "www.example.com".to_string(),
+            "www.video.org".to_string(),
+            " ".to_string(),
+        ];
+        assert_eq!(
+            SiteBlocker::expand_domains(&sites),
+            vec!["example.com", "www.example.com", "www.video.org", "video.org"]
+        );
+    }
+
+    #[test]
+    fn test_block_and_unblock_in_sandbox() {
+        let original = "127.0.0.1 localhost\n::1 localhost";
+        let (dir, backend) = sandbox("roundtrip", original);
+        let blocker = SiteBlocker::with_backend(vec!["example.com".to_string()], backend);
+
+        blocker.block_sites().unwrap();
+        let blocked = fs::read_to_string(dir.join("hosts")).unwrap();
+        assert!(blocked.starts_with(BLOCK_MARKER_START));
+        assert!(blocked.ends_with(original));
+        assert!(blocked.contains("0.0.0.0 example.com\n"));
+        assert!(blocked.contains("127.0.0.1 www.example.com\n"));
+        assert!(blocked.contains(":: example.com\n"));
+        assert!(blocked.contains(":: www.example.com\n"));
+        assert!(blocker.has_block_section());
+        assert_eq!(fs::read_to_string(dir.join("hosts.backup")).unwrap(), original);
+
+        // 重复屏蔽只保留一个区块
+        blocker.block_sites().unwrap();
+        let reblocked = fs::read_to_string(dir.join("hosts")).unwrap();
+        assert_eq!(reblocked.matches(BLOCK_MARKER_START).count(), 1);
+
+        blocker.unblock_sites().unwrap();
+        assert_eq!(fs::read_to_string(dir.join("hosts")).unwrap(), original);
+        assert!(!blocker.has_block_section());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_path_rules_stay_out_of_hosts() {
+        let (dir, backend) = sandbox("path-rules", "127.0.0.1 localhost\n");
+        let sites = vec!["youtube.com/shorts".to_string(), "douyin.com".to_string()];
+        let blocker = SiteBlocker::with_backend(sites, backend);
+
+        blocker.block_sites().unwrap();
+        let blocked = fs::read_to_string(dir.join("hosts")).unwrap();
+        assert!(blocked.contains("0.0.0.0 douyin.com\n"));
+        assert!(!blocked.contains("youtube.com"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_allowlist_writes_empty_block_section() {
+        let original = "127.0.0.1 localhost\n";
+        let (dir, backend) = sandbox("allowlist", original);
+        let rules = SiteRules::Allowlist(vec!["docs.rs".to_string()]);
+        let blocker = SiteBlocker::with_rules(rules, backend);
+
+        blocker.block_sites().unwrap();
+        let blocked = fs::read_to_string(dir.join("hosts")).unwrap();
+        assert_eq!(
+            blocked,
+            format!("{}\n{}\n{}", BLOCK_MARKER_START, BLOCK_MARKER_END, original)
+        );
+        assert!(blocker.has_block_section());
+        assert!(blocker.get_blocked_sites().is_empty());
+
+        blocker.unblock_sites().unwrap();
+        assert_eq!(fs::read_to_string(dir.join("hosts")).unwrap(), original);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_tampered_block_section_is_repaired() {
+        let original = "127.0.0.1 localhost\n";
+        let (dir, backend) = sandbox("tamper", original);
+        let blocker = SiteBlocker::with_backend(vec!["example.com".to_string()], backend);
+
+        blocker.block_sites().unwrap();
+        assert!(blocker.check_integrity().unwrap().drift.is_empty());
+
+        // 删掉一条屏蔽记录，并在区块外追加内容
+        let hosts = fs::read_to_string(dir.join("hosts")).unwrap();
+        let tampered = hosts.replace("0.0.0.0 www.example.com\n", "") + "10.0.0.2 nas\n";
+        fs::write(dir.join("hosts"), tampered).unwrap();
+        assert_eq!(
+            blocker.check_integrity().unwrap().drift,
+            vec![BlockDrift::HostsSection]
+        );
+
+        blocker.repair(&[BlockDrift::HostsSection]).unwrap();
+        assert!(blocker.check_integrity().unwrap().drift.is_empty());
+        let repaired = fs::read_to_string(dir.join("hosts")).unwrap();
+        assert_eq!(repaired, hosts + "10.0.0.2 nas\n");
+
+        // 整个区块被删除同样能检测到
+        fs::write(dir.join("hosts"), original).unwrap();
+        assert_eq!(
+            blocker.check_integrity().unwrap().drift,
+            vec![BlockDrift::HostsSection]
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_remove_block_section_preserves_bytes() {
+        let block = format!("{}\n0.0.0.0 a.com\n{}\n", BLOCK_MARKER_START, BLOCK_MARKER_END);
+        for original in [
+            "",
+            "127.0.0.1\tlocalhost",
+            "127.0.0.1 localhost\n\n\n",
+            "# comment\r\n127.0.0.1 localhost\r\n",
+        ] {
+            let blocked = SiteBlocker::build_hosts(original, &["a.com".to_string()]);
+            assert_eq!(SiteBlocker::remove_block_section(&blocked), original);
+
+            // 区块位于中间时前后内容都原样保留
+            let middle = format!("{}{}{}", original, block, original);
+            let expected = format!("{}{}", original, original);
+            if original.is_empty() || original.ends_with('\n') {
+                assert_eq!(SiteBlocker::remove_block_section(&middle), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_incomplete_block_is_restored_from_backup() {
+        let original = "127.0.0.1 localhost\n10.0.0.2 nas.local\n";
+        let (dir, backend) = sandbox("incomplete", original);
+        let blocker = SiteBlocker::with_backend(vec!["example.com".to_string()], backend);
+        blocker.block_sites().unwrap();
+
+        // 写入中途崩溃：结束标记丢失，按标记删除会连带删掉 nas.local
+        let hosts = fs::read_to_string(dir.join("hosts")).unwrap();
+        fs::write(dir.join("hosts"), hosts.replace(BLOCK_MARKER_END, "")).unwrap();
+        blocker.unblock_sites().unwrap();
+        assert_eq!(fs::read_to_string(dir.join("hosts")).unwrap(), original);
+
+        // 没有可用的备份时退回按标记删除
+        fs::write(dir.join("hosts.backup"), &hosts).unwrap();
+        fs::write(dir.join("hosts"), hosts.replace(BLOCK_MARKER_END, "")).unwrap();
+        blocker.unblock_sites().unwrap();
+        assert!(!blocker.has_block_section());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_legacy_appended_block_is_removed() {
+        // 旧版本去掉末尾换行后再追加 "\n" 和区块
+        let legacy = format!(
+            "127.0.0.1 localhost\n{}\n0.0.0.0 a.com\n{}\n",
+            BLOCK_MARKER_START, BLOCK_MARKER_END
+        );
+        assert_eq!(SiteBlocker::remove_block_section(&legacy), "127.0.0.1 localhost\n");
+    }
+
+    #[test]
+    fn test_failed_verification_rolls_back() {
+        let original = "127.0.0.1 localhost\n# 自定义\n10.0.0.2 nas.local";
+        let (dir, _) = sandbox("rollback", original);
+        let backend = Arc::new(FaultyBackend::new(dir.clone(), Fault::Truncate));
+        let blocker = SiteBlocker::with_backend(vec!["example.com".to_string()], backend);
+
+        let err = blocker.block_sites().unwrap_err();
+        assert!(matches!(err, AppError::BlockerError(_)));
+        assert_eq!(fs::read_to_string(dir.join("hosts")).unwrap(), original);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_failed_reblock_keeps_active_block() {
+        let (dir, backend) = sandbox("reblock", "127.0.0.1 localhost\n");
+        SiteBlocker::with_backend(vec!["example.com".to_string()], backend)
+            .block_sites()
+            .unwrap();
+        let blocked = fs::read_to_string(dir.join("hosts")).unwrap();
+
+        let backend = Arc::new(FaultyBackend::new(dir.clone(), Fault::FailAfterWrite));
+        let blocker = SiteBlocker::with_backend(vec!["example.org".to_string()], backend);
+        assert!(blocker.block_sites().is_err());
+        assert_eq!(fs::read_to_string(dir.join("hosts")).unwrap(), blocked);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_large_list_writes_compact_block() {
+        let (dir, backend) = sandbox("large", "127.0.0.1 localhost\n");
+        let sites: Vec<String> = (0..LARGE_LIST_SITES + 1)
+            .map(|i| format!("site{}.example", i))
+            .collect();
+        let blocker = SiteBlocker::with_backend(sites.clone(), backend);
+
+        blocker.block_sites().unwrap();
+        let blocked = fs::read_to_string(dir.join("hosts")).unwrap();
+        let section = SiteBlocker::block_section(&blocked).unwrap();
+        assert_eq!(section.lines().count(), sites.len() * 2 + 2);
+        assert!(section.contains("0.0.0.0 site0.example\n:: site0.example\n"));
+        assert!(!section.contains("www."));
+        assert!(blocker.check_integrity().unwrap().drift.is_empty());
+        assert_eq!(SiteBlocker::resolve_limit(&sites).len(), MAX_RESOLVE_DOMAINS);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,436 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::services::SiteBlocker;
+
+/// 白名单模式下始终放行的本机地址
+const LOOPBACK_HOSTS: &[&str] = &["localhost", "127.0.0.1", "::1", "[::1]"];
+
+/// 一次专注的网站规则：黑名单只屏蔽列出的网站，白名单只放行列出的网站
+///
+/// 两种语义分别由 blocklist_blocks 和 allowlist_allows 判断，互不复用；
+/// 条目可以带路径和查询参数，见 SiteRule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "mode", content = "sites", rename_all = "snake_case")]
+pub enum SiteRules {
+    Blocklist(Vec<String>),
+    Allowlist(Vec<String>),
+}
+
+impl Default for SiteRules {
+    fn default() -> Self {
+        SiteRules::Blocklist(Vec::new())
+    }
+}
+
+/// 单条网站规则：主机名，加上可选的路径前缀和查询参数条件
+///
+/// 条目写法如 `youtube.com/shorts`、`reddit.com/r/all`、`youtube.com/results?search_query`，
+/// 不区分大小写；主机名同时匹配其子域名，www 前缀的条目同时覆盖裸域名
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SiteRule {
+    /// 清理后的主机名（小写，不含协议）
+    pub host: String,
+    /// 以 / 开头、不含末尾的 /；为空时匹配整个网站
+    pub path_prefix: String,
+    /// `key` 或 `key=value`，全部满足时才匹配
+    pub query: Vec<String>,
+}
+
+impl SiteRule {
+    /// 解析用户填写的条目，主机名为空时返回 None
+    pub fn parse(site: &str) -> Option<Self> {
+        let site = site.trim().to_lowercase();
+        let site = site
+            .strip_prefix("https://")
+            .or_else(|| site.strip_prefix("http://"))
+            .unwrap_or(&site);
+        let site = site.split('#').next().unwrap_or("");
+        let (location, query) = site.split_once('?').unwrap_or((site, ""));
+        let (host, path) = match location.find('/') {
+            Some(pos) => location.split_at(pos),
+            None => (location, ""),
+        };
+
+        let host = SiteBlocker::clean_domain(host);
+        if host.is_empty() {
+            return None;
+        }
+        Some(SiteRule {
+            host,
+            path_prefix: path.trim_end_matches('/').to_string(),
+            query: query
+                .split('&')
+                .filter(|pair| !pair.is_empty())
+                .map(|pair| pair.to_string())
+                .collect(),
+        })
+    }
+
+    /// 只按主机名匹配，系统级屏蔽（hosts、防火墙、DNS）能完整执行
+    pub fn is_host_only(&self) -> bool {
+        self.path_prefix.is_empty() && self.query.is_empty()
+    }
+
+    /// 去掉 www. 前缀的主机名
+    fn base_host(&self) -> &str {
+        self.host.strip_prefix("www.").unwrap_or(&self.host)
+    }
+
+    fn matches_host(&self, host: &str) -> bool {
+        SiteRules::is_same_or_subdomain(host, self.base_host())
+    }
+
+    /// 路径按段匹配：/shorts 匹配 /shorts 和 /shorts/abc，不匹配 /shortsx
+    fn matches_path(&self, path: &str) -> bool {
+        let path = path.to_lowercase();
+        self.path_prefix.is_empty()
+            || path
+                .strip_prefix(&self.path_prefix)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+
+    fn matches_query(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        let pairs: Vec<&str> = query.split('&').collect();
+        self.query.iter().all(|pattern| {
+            if pattern.contains('=') {
+                pairs.contains(&pattern.as_str())
+            } else {
+                pairs
+                    .iter()
+                    .any(|pair| pair.split('=').next() == Some(pattern.as_str()))
+            }
+        })
+    }
+
+    /// 是否匹配 URL 的主机名、路径和查询串
+    pub fn matches(&self, host: &str, path: &str, query: &str) -> bool {
+        self.matches_host(host) && self.matches_path(path) && self.matches_query(query)
+    }
+
+    /// 本规则匹配的 URL 是否都被 other 匹配；主机名按字面比较，www.a.com 不覆盖 a.com
+    pub fn is_covered_by(&self, other: &SiteRule) -> bool {
+        SiteRules::is_same_or_subdomain(&self.host, &other.host)
+            && other.matches_path(&self.path_prefix)
+            && other
+                .query
+                .iter()
+                .all(|pattern| self.query.contains(pattern))
+    }
+}
+
+impl std::fmt::Display for SiteRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.host, self.path_prefix)?;
+        if !self.query.is_empty() {
+            write!(f, "?{}", self.query.join("&"))?;
+        }
+        Ok(())
+    }
+}
+
+/// 只按主机名判断的规则：创建时解析一次，查询时按标签后缀查表，供每个查询都要判断的 DNS 层使用
+///
+/// 与 SiteRules::is_blocked 的判断一致
+#[derive(Debug, Clone, Default)]
+pub struct HostMatcher {
+    allowlist: bool,
+    hosts: HashSet<String>,
+}
+
+impl HostMatcher {
+    pub fn is_allowlist(&self) -> bool {
+        self.allowlist
+    }
+
+    pub fn host_count(&self) -> usize {
+        self.hosts.len()
+    }
+
+    /// 主机名（可以带端口或末尾的点）是否被屏蔽
+    pub fn is_blocked(&self, host: &str) -> bool {
+        let host = SiteRules::normalize_host(host);
+        let listed = Self::suffixes(&host).any(|suffix| self.hosts.contains(suffix));
+        if self.allowlist {
+            !(listed || LOOPBACK_HOSTS.contains(&host.as_str()))
+        } else {
+            listed
+        }
+    }
+
+    /// 主机名本身及其各级父域名：a.b.com、b.com、com
+    fn suffixes(host: &str) -> impl Iterator<Item = &str> {
+        std::iter::once(host).chain(host.match_indices('.').map(move |(i, _)| &host[i + 1..]))
+    }
+}
+
+impl SiteRules {
+    pub fn is_allowlist(&self) -> bool {
+        matches!(self, SiteRules::Allowlist(_))
+    }
+
+    /// 规则中列出的网站（黑名单为屏蔽的网站，白名单为放行的网站）
+    pub fn sites(&self) -> &[String] {
+        match self {
+            SiteRules::Blocklist(sites) | SiteRules::Allowlist(sites) => sites,
+        }
+    }
+
+    /// 解析后的规则，忽略无法解析的条目
+    pub fn parsed(&self) -> Vec<SiteRule> {
+        self.sites()
+            .iter()
+            .filter_map(|site| SiteRule::parse(site))
+            .collect()
+    }
+
+    /// 系统级屏蔽使用的主机名
+    ///
+    /// 黑名单只取不带路径的规则，`youtube.com/shorts` 不会屏蔽整个 youtube.com；
+    /// 白名单取所有规则的主机名，路径限制交给浏览器扩展
+    pub fn host_sites(&self) -> Vec<String> {
+        let allowlist = self.is_allowlist();
+        let mut seen = HashSet::new();
+        self.parsed()
+            .into_iter()
+            .filter(|rule| allowlist || rule.is_host_only())
+            .map(|rule| rule.host)
+            .filter(|host| seen.insert(host.clone()))
+            .collect()
+    }
+
+    /// 编译为按主机名查表的规则，规则里的 www 前缀与 SiteRule 一样同时覆盖裸域名
+    pub fn host_matcher(&self) -> HostMatcher {
+        let allowlist = self.is_allowlist();
+        let hosts = self
+            .parsed()
+            .into_iter()
+            .filter(|rule| allowlist || rule.is_host_only())
+            .map(|rule| rule.base_host().to_string())
+            .filter(|host| !host.is_empty())
+            .collect();
+        HostMatcher { allowlist, hosts }
+    }
+
+    /// 专注期间是否在主机名级别屏蔽（可以带端口或末尾的点），供只看得到主机名的 DNS 层使用
+    pub fn is_blocked(&self, host: &str) -> bool {
+        let host = Self::normalize_host(host);
+        let rules = self.parsed();
+        match self {
+            SiteRules::Blocklist(_) => Self::blocklist_blocks(&rules, &host, None),
+            SiteRules::Allowlist(_) => !Self::allowlist_allows(&rules, &host, None),
+        }
+    }
+
+    /// 专注期间是否屏蔽该 URL，按主机名、路径和查询串完整匹配；非 http(s) 地址不屏蔽
+    pub fn is_url_blocked(&self, url: &str) -> bool {
+        let lower = url.trim().to_lowercase();
+        let Some(rest) = lower
+            .strip_prefix("https://")
+            .or_else(|| lower.strip_prefix("http://"))
+        else {
+            return false;
+        };
+        let rest = rest.split('#').next().unwrap_or("");
+        let (location, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let (host, path) = match location.find('/') {
+            Some(pos) => location.split_at(pos),
+            None => (location, ""),
+        };
+
+        let host = Self::normalize_host(host);
+        let rules = self.parsed();
+        let url = Some((path, query));
+        match self {
+            SiteRules::Blocklist(_) => Self::blocklist_blocks(&rules, &host, url),
+            SiteRules::Allowlist(_) => !Self::allowlist_allows(&rules, &host, url),
+        }
+    }
+
+    /// 黑名单：命中任一规则时屏蔽；只有主机名时只看不带路径的规则
+    fn blocklist_blocks(rules: &[SiteRule], host: &str, url: Option<(&str, &str)>) -> bool {
+        rules.iter().any(|rule| match url {
+            Some((path, query)) => rule.matches(host, path, query),
+            None => rule.is_host_only() && rule.matches_host(host),
+        })
+    }
+
+    /// 白名单：只放行命中规则的地址和本机地址；空白名单不放行任何网站
+    fn allowlist_allows(rules: &[SiteRule], host: &str, url: Option<(&str, &str)>) -> bool {
+        if LOOPBACK_HOSTS.contains(&host) {
+            return true;
+        }
+        rules.iter().any(|rule| match url {
+            Some((path, query)) => rule.matches(host, path, query),
+            None => rule.matches_host(host),
+        })
+    }
+
+    fn is_same_or_subdomain(host: &str, domain: &str) -> bool {
+        !domain.is_empty()
+            && (host == domain
+                || host
+                    .strip_suffix(domain)
+                    .is_some_and(|prefix| prefix.ends_with('.')))
+    }
+
+    /// 小写，去掉端口和末尾的点
+    fn normalize_host(host: &str) -> String {
+        let host = host.trim().to_lowercase();
+        let host = if host.starts_with('[') {
+            host.split(']')
+                .next()
+                .map(|h| format!("{}]", h))
+                .unwrap_or(host)
+        } else if host.matches(':').count() == 1 {
+            host.split(':').next().unwrap_or("").to_string()
+        } else {
+            host
+        };
+        host.trim_end_matches('.').to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sites(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_blocklist_blocks_listed_domains_and_subdomains() {
+        let rules = SiteRules::Blocklist(sites(&["https://www.Bilibili.com/", "douyin.com"]));
+        assert!(rules.is_blocked("bilibili.com"));
+        assert!(rules.is_blocked("live.bilibili.com:443"));
+        assert!(rules.is_blocked("www.douyin.com."));
+        assert!(!rules.is_blocked("notbilibili.com"));
+        assert!(!rules.is_blocked("docs.rs"));
+        assert!(!SiteRules::Blocklist(Vec::new()).is_blocked("bilibili.com"));
+    }
+
+    #[test]
+    fn test_allowlist_blocks_everything_else() {
+        let rules = SiteRules::Allowlist(sites(&["docs.rs", "www.canvas.edu"]));
+        assert!(!rules.is_blocked("docs.rs"));
+        assert!(!rules.is_blocked("static.docs.rs"));
+        assert!(!rules.is_blocked("canvas.edu"));
+        assert!(!rules.is_blocked("localhost:8080"));
+        assert!(!rules.is_blocked("[::1]:3000"));
+        assert!(rules.is_blocked("bilibili.com"));
+        assert!(rules.is_blocked("evildocs.rs"));
+        // 空白名单不放行任何网站
+        assert!(SiteRules::Allowlist(Vec::new()).is_blocked("docs.rs"));
+    }
+
+    #[test]
+    fn test_host_matcher_agrees_with_rules() {
+        let hosts = [
+            "bilibili.com",
+            "live.bilibili.com:443",
+            "www.douyin.com.",
+            "notbilibili.com",
+            "youtube.com",
+            "static.docs.rs",
+            "evildocs.rs",
+            "localhost:8080",
+            "com",
+        ];
+        let list = sites(&[
+            "https://www.Bilibili.com/",
+            "douyin.com",
+            "youtube.com/shorts",
+            "docs.rs",
+        ]);
+        for rules in [
+            SiteRules::Blocklist(list.clone()),
+            SiteRules::Allowlist(list),
+        ] {
+            let matcher = rules.host_matcher();
+            for host in hosts {
+                assert_eq!(
+                    matcher.is_blocked(host),
+                    rules.is_blocked(host),
+                    "{:?} {}",
+                    rules,
+                    host
+                );
+            }
+        }
+        assert!(!HostMatcher::default().is_blocked("bilibili.com"));
+    }
+
+    #[test]
+    fn test_same_list_has_opposite_meaning() {
+        let list = sites(&["example.com"]);
+        let block = SiteRules::Blocklist(list.clone());
+        let allow = SiteRules::Allowlist(list);
+        for host in ["example.com", "a.example.com", "other.org"] {
+            assert_ne!(block.is_blocked(host), allow.is_blocked(host), "{}", host);
+        }
+    }
+
+    #[test]
+    fn test_parse_rule() {
+        let rule = SiteRule::parse("https://www.YouTube.com/Shorts/?t=1#top").unwrap();
+        assert_eq!(rule.host, "www.youtube.com");
+        assert_eq!(rule.path_prefix, "/shorts");
+        assert_eq!(rule.query, vec!["t=1"]);
+        assert_eq!(rule.to_string(), "www.youtube.com/shorts?t=1");
+
+        let rule = SiteRule::parse("reddit.com/").unwrap();
+        assert!(rule.is_host_only());
+        assert_eq!(rule.to_string(), "reddit.com");
+        assert!(SiteRule::parse(" /path").is_none());
+    }
+
+    #[test]
+    fn test_path_and_query_rules() {
+        let rules = SiteRules::Blocklist(sites(&[
+            "youtube.com/shorts",
+            "reddit.com/r/all",
+            "youtube.com/results?search_query",
+        ]));
+        assert!(rules.is_url_blocked("https://www.youtube.com/shorts/abc"));
+        assert!(rules.is_url_blocked("https://m.youtube.com/Shorts"));
+        assert!(rules.is_url_blocked("https://reddit.com/r/all/?sort=new"));
+        assert!(rules.is_url_blocked("https://youtube.com/results?search_query=rust&sp=1"));
+        assert!(!rules.is_url_blocked("https://youtube.com/watch?v=1"));
+        assert!(!rules.is_url_blocked("https://youtube.com/shortsfoo"));
+        assert!(!rules.is_url_blocked("https://reddit.com/r/rust"));
+        assert!(!rules.is_url_blocked("https://youtube.com/results?q=rust"));
+        assert!(!rules.is_url_blocked("chrome://settings"));
+
+        // 系统级屏蔽只看主机名，带路径的规则不屏蔽整个网站
+        assert!(!rules.is_blocked("youtube.com"));
+        assert!(rules.host_sites().is_empty());
+
+        let rules = SiteRules::Allowlist(sites(&["docs.google.com/document"]));
+        assert!(!rules.is_url_blocked("https://docs.google.com/document/d/1"));
+        assert!(rules.is_url_blocked("https://docs.google.com/spreadsheets/d/1"));
+        assert!(!rules.is_blocked("docs.google.com"));
+        assert_eq!(rules.host_sites(), vec!["docs.google.com"]);
+    }
+
+    #[test]
+    fn test_rule_coverage() {
+        let rule = |site: &str| SiteRule::parse(site).unwrap();
+        assert!(rule("www.youtube.com/shorts/x").is_covered_by(&rule("youtube.com/shorts")));
+        assert!(rule("m.youtube.com").is_covered_by(&rule("youtube.com")));
+        assert!(rule("youtube.com/results?q=1&x").is_covered_by(&rule("youtube.com/results?x")));
+        assert!(!rule("youtube.com").is_covered_by(&rule("youtube.com/shorts")));
+        assert!(!rule("youtube.com").is_covered_by(&rule("www.youtube.com")));
+        assert!(!rule("youtube.com/results").is_covered_by(&rule("youtube.com/results?x")));
+    }
+
+    #[test]
+    fn test_serialized_form() {
+        let rules = SiteRules::Allowlist(sites(&["docs.rs"]));
+        assert_eq!(
+            serde_json::to_value(&rules).unwrap(),
+            serde_json::json!({ "mode": "allowlist", "sites": ["docs.rs"] })
+        );
+    }
+}
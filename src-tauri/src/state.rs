@@ -1,11 +1,15 @@
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::thread::JoinHandle;
 
 use tauri::AppHandle;
 
-use crate::models::{Config, TimerState, TimerStatus};
-use crate::services::{AppBlocker, Scheduler, SiteBlocker};
+use crate::errors::AppError;
+use crate::models::{Config, ScheduledWindow, SiteCategory, TimerState, TimerStatus};
+use crate::services::{
+    AppBlocker, BlockWatchdog, CalendarWatcher, CommitmentLock, DnsResolver, DnsSinkhole, IcsEvent,
+    ScheduleOccurrence, Scheduler, SchedulerWaker, SiteBlocker, SiteRules,
+};
 
 pub struct TimerThread {
     pub handle: Option<JoinHandle<()>>,
@@ -57,7 +61,9 @@ pub struct AppState {
     pub timer_running: Arc<AtomicBool>,
     pub blocker_running: AtomicBool,
     pub scheduler_running: AtomicBool,
-    pub scheduled_focus_active: AtomicBool,
+    /// 由调度器开始的专注所在的时间段，手动开始的专注为 None
+    pub scheduled_focus: Mutex<Option<ScheduledWindow>>,
+    pub active_profile: Arc<Mutex<Option<String>>>,
     pub emergency_remaining: AtomicU32,
     pub overlay_suppressed: Arc<AtomicBool>,
     pub suppress_generation: Arc<AtomicU32>,
@@ -65,8 +71,18 @@ pub struct AppState {
     pub app_blocker_thread: Mutex<AppBlockerThread>,
     pub scheduler_thread: Mutex<SchedulerThread>,
     pub app_blocker: Mutex<AppBlocker>,
-    pub site_blocker: Mutex<SiteBlocker>,
-    pub scheduler: Mutex<Scheduler>,
+    pub site_blocker: Arc<Mutex<SiteBlocker>>,
+    pub scheduler: Arc<Mutex<Scheduler>>,
+    /// 配置或日历变化、停止调度时唤醒调度线程
+    pub scheduler_waker: Arc<SchedulerWaker>,
+    /// 启动时等待用户确认是否加入的时间段（结束时间为合并后的结束时间）
+    pub pending_catch_up: Mutex<Option<ScheduleOccurrence>>,
+    /// 专注期间运行的本地 DNS 转发（配置启用时）
+    pub dns_sinkhole: Mutex<Option<DnsSinkhole>>,
+    /// 专注期间检查屏蔽是否被篡改的后台线程
+    pub block_watchdog: Mutex<Option<BlockWatchdog>>,
+    /// 数据目录中的网站分类列表，启动时读取；与 config 同时持有时先锁 config
+    pub site_categories: Arc<Mutex<Vec<SiteCategory>>>,
 }
 
 impl AppState {
@@ -75,8 +91,16 @@ impl AppState {
         let work_minutes = config.pomodoro.work_minutes;
         let break_minutes = config.pomodoro.break_minutes;
         let blocked_apps = config.blocked_apps.clone();
-        let blocked_sites = config.blocked_sites.clone();
-        let schedules = config.schedules.clone();
+        let site_categories = SiteCategory::load_all().unwrap_or_else(|e| {
+            eprintln!("读取网站分类失败: {}", e);
+            Vec::new()
+        });
+        let site_rules = config
+            .site_rules(None, &site_categories)
+            .unwrap_or_default();
+        let mut scheduler = Scheduler::new(config.schedules.clone());
+        scheduler.update_exceptions(config.schedule_exceptions.clone());
+        scheduler.update_calendar_events(CalendarWatcher::load_events(&config.calendar_sources));
 
         let timer_status = TimerStatus::new_with_config(work_minutes, break_minutes, emergency_limit);
 
@@ -86,7 +110,8 @@ impl AppState {
             timer_running: Arc::new(AtomicBool::new(false)),
             blocker_running: AtomicBool::new(false),
             scheduler_running: AtomicBool::new(false),
-            scheduled_focus_active: AtomicBool::new(false),
+            scheduled_focus: Mutex::new(None),
+            active_profile: Arc::new(Mutex::new(None)),
             emergency_remaining: AtomicU32::new(emergency_limit),
             overlay_suppressed: Arc::new(AtomicBool::new(false)),
             suppress_generation: Arc::new(AtomicU32::new(0)),
@@ -94,11 +119,117 @@ impl AppState {
             app_blocker_thread: Mutex::new(AppBlockerThread::default()),
             scheduler_thread: Mutex::new(SchedulerThread::default()),
             app_blocker: Mutex::new(AppBlocker::new(blocked_apps)),
-            site_blocker: Mutex::new(SiteBlocker::new(blocked_sites)),
-            scheduler: Mutex::new(Scheduler::new(schedules)),
+            site_blocker: Arc::new(Mutex::new(SiteBlocker::from_rules(site_rules))),
+            scheduler: Arc::new(Mutex::new(scheduler)),
+            scheduler_waker: Arc::new(SchedulerWaker::default()),
+            pending_catch_up: Mutex::new(None),
+            dns_sinkhole: Mutex::new(None),
+            block_watchdog: Mutex::new(None),
+            site_categories: Arc::new(Mutex::new(site_categories)),
         }
     }
 
+    /// 持久化新配置并同步到各拦截器和调度器
+    ///
+    /// 专注进行中时受承诺锁约束，只接受不削弱屏蔽的变更
+    pub fn apply_config(&self, config: Config) -> Result<(), AppError> {
+        self.replace_config(config, true)
+    }
+
+    /// 应用外部修改过的 config.json（不回写文件）
+    pub fn reload_config(&self, config: Config) -> Result<(), AppError> {
+        self.replace_config(config, false)
+    }
+
+    /// 在持有 config 锁时基于当前配置生成新配置并应用
+    ///
+    /// 生成和应用之间其他保存或热加载的修改不会被覆盖；plan 的第二个返回值原样返回
+    pub fn update_config<R>(
+        &self,
+        plan: impl FnOnce(&Config) -> (Config, R),
+    ) -> Result<R, AppError> {
+        let current_config = self.config.lock().unwrap();
+        let (config, result) = plan(&current_config);
+        // 日历来源未变时沿用调度器中已读取的事件，避免持锁读取 .ics 文件
+        let calendar_events = (config.calendar_sources != current_config.calendar_sources)
+            .then(|| CalendarWatcher::load_events(&config.calendar_sources));
+        self.commit_config(current_config, config, calendar_events, true)?;
+        Ok(result)
+    }
+
+    /// 校验并替换配置
+    fn replace_config(&self, config: Config, persist: bool) -> Result<(), AppError> {
+        // 读取 .ics 文件较慢，在加锁之前完成
+        let calendar_events = CalendarWatcher::load_events(&config.calendar_sources);
+        let current_config = self.config.lock().unwrap();
+        self.commit_config(current_config, config, Some(calendar_events), persist)
+    }
+
+    /// 持有 config 锁期间同步更新拦截器和调度器，失败时保持原状态；
+    /// calendar_events 为 None 时保留调度器中的日历事件
+    fn commit_config(
+        &self,
+        mut current_config: MutexGuard<Config>,
+        mut config: Config,
+        calendar_events: Option<Vec<IcsEvent>>,
+        persist: bool,
+    ) -> Result<(), AppError> {
+        config.validate()?;
+
+        // 紧急取消的已用次数和月份由后端维护，传入的配置（前端或手动编辑的文件）不能覆盖；
+        // 手动编辑改动了它们时写回文件，避免下次启动读到改过的值
+        let (old, new) = (&current_config.pomodoro, &mut config.pomodoro);
+        let usage_edited = new.emergency_used_count != old.emergency_used_count
+            || new.emergency_reset_month != old.emergency_reset_month;
+        new.emergency_used_count = old.emergency_used_count;
+        new.emergency_reset_month = old.emergency_reset_month.clone();
+        let persist = persist || usage_edited;
+
+        if self.timer_running.load(Ordering::SeqCst) {
+            let active_profile = self.active_profile.lock().unwrap().clone();
+            let violations =
+                CommitmentLock::check(&current_config, &config, active_profile.as_deref());
+            if !violations.is_empty() {
+                return Err(AppError::CommitmentLocked(violations));
+            }
+        }
+
+        if persist {
+            config.save()?;
+        }
+        *current_config = config.clone();
+
+        // 专注进行中时拦截器保持本次会话的黑名单，结束后再切回全局列表
+        if !self.timer_running.load(Ordering::SeqCst) {
+            let mut app_blocker = self.app_blocker.lock().unwrap();
+            app_blocker.update_blocked_apps(config.blocked_apps.clone());
+
+            let mut site_blocker = self.site_blocker.lock().unwrap();
+            let categories = self.site_categories.lock().unwrap();
+            site_blocker.update_rules(config.site_rules(None, &categories).unwrap_or_default());
+        }
+
+        let mut scheduler = self.scheduler.lock().unwrap();
+        scheduler.update_schedules(config.schedules);
+        scheduler.update_exceptions(config.schedule_exceptions);
+        if let Some(events) = calendar_events {
+            scheduler.update_calendar_events(events);
+        }
+        self.scheduler_waker.notify();
+        drop(scheduler);
+        drop(current_config);
+
+        Ok(())
+    }
+
+    /// 重新读取日历来源（.ics 文件在外部被修改后调用）
+    pub fn refresh_calendar_events(&self) {
+        let sources = self.config.lock().unwrap().calendar_sources.clone();
+        let events = CalendarWatcher::load_events(&sources);
+        self.scheduler.lock().unwrap().update_calendar_events(events);
+        self.scheduler_waker.notify();
+    }
+
     pub fn stop_timer_thread(&self) {
         let mut timer_thread = self.timer_thread.lock().unwrap();
         timer_thread.stop_signal.store(true, Ordering::SeqCst);
@@ -109,10 +240,36 @@ impl AppState {
         timer_thread.pause_signal.store(false, Ordering::SeqCst);
     }
 
+    /// 设置本次专注使用的屏蔽方案、App 黑名单和网站规则
+    pub fn set_session_blocklists(
+        &self,
+        profile: Option<String>,
+        blocked_apps: Vec<String>,
+        site_rules: SiteRules,
+    ) {
+        *self.active_profile.lock().unwrap() = profile;
+        self.app_blocker.lock().unwrap().update_blocked_apps(blocked_apps);
+        self.site_blocker.lock().unwrap().update_rules(site_rules);
+    }
+
+    /// 专注结束后恢复为全局黑名单和网站规则
+    pub fn reset_session_blocklists(&self) {
+        let (blocked_apps, site_rules) = {
+            let config = self.config.lock().unwrap();
+            let categories = self.site_categories.lock().unwrap();
+            (
+                config.blocked_apps.clone(),
+                config.site_rules(None, &categories).unwrap_or_default(),
+            )
+        };
+        self.set_session_blocklists(None, blocked_apps, site_rules);
+    }
+
     pub fn start_app_blocker(&self, app_handle: AppHandle) {
-        let config = self.config.lock().unwrap();
-        let blocked_apps = config.blocked_apps.clone();
-        drop(config);
+        let blocked_apps = {
+            let app_blocker = self.app_blocker.lock().unwrap();
+            app_blocker.get_blocked_apps().clone()
+        };
 
         let mut blocker_thread = self.app_blocker_thread.lock().unwrap();
 
@@ -143,9 +300,95 @@ impl AppState {
         self.blocker_running.store(false, Ordering::SeqCst);
     }
 
+    /// 本地 DNS 转发按本次专注的网站规则屏蔽
+    pub fn start_dns_sinkhole(&self) {
+        let rules = self.site_blocker.lock().unwrap().rules().clone();
+        self.run_dns_sinkhole(&rules);
+    }
+
+    /// 启用时运行本地 DNS 转发并使用给定规则，监听或上游地址变化时重启；未启用时停止
+    ///
+    /// 启动失败（如端口被占用或没有权限）只记录日志，hosts 屏蔽仍然生效
+    fn run_dns_sinkhole(&self, rules: &SiteRules) {
+        let settings = self.config.lock().unwrap().dns_sinkhole.clone();
+        let mut current = self.dns_sinkhole.lock().unwrap();
+        if !settings.enabled {
+            DnsResolver::set_system_override(None);
+            if let Some(sinkhole) = current.take() {
+                sinkhole.stop();
+            }
+            return;
+        }
+
+        let listen = settings.listen.parse().ok();
+        let upstream = match (&settings.upstream, listen) {
+            (Some(upstream), _) => DnsSinkhole::parse_upstream(upstream),
+            (None, Some(listen)) => DnsSinkhole::system_upstream(listen),
+            (None, None) => None,
+        };
+        let (Some(listen), Some(upstream)) = (listen, upstream) else {
+            if listen.is_none() {
+                eprintln!("[DnsSinkhole] 监听地址无效: {}", settings.listen);
+            } else {
+                eprintln!("[DnsSinkhole] 未找到上游 DNS，请在配置中指定 upstream");
+            }
+            DnsResolver::set_system_override(None);
+            if let Some(sinkhole) = current.take() {
+                sinkhole.stop();
+            }
+            return;
+        };
+
+        // 屏蔽时解析 IP 绕过本地转发，先查它的上游，再查系统中的其他服务器
+        let mut servers = vec![upstream];
+        servers.extend(
+            DnsResolver::system_nameservers()
+                .into_iter()
+                .filter(|addr| !DnsSinkhole::loops_back(listen, *addr) && *addr != upstream),
+        );
+        DnsResolver::set_system_override(Some(servers));
+
+        if let Some(sinkhole) = current
+            .as_ref()
+            .filter(|s| s.listen() == listen && s.upstream() == upstream)
+        {
+            sinkhole.set_rules(rules);
+            return;
+        }
+        if let Some(sinkhole) = current.take() {
+            sinkhole.stop();
+        }
+        match DnsSinkhole::start(listen, upstream, rules) {
+            Ok(sinkhole) => *current = Some(sinkhole),
+            Err(e) => eprintln!("[DnsSinkhole] 启动失败: {}", e),
+        }
+    }
+
+    /// 专注结束时停止本地 DNS 转发，屏蔽解析 IP 恢复使用系统 DNS
+    pub fn stop_dns_sinkhole(&self) {
+        DnsResolver::set_system_override(None);
+        if let Some(sinkhole) = self.dns_sinkhole.lock().unwrap().take() {
+            sinkhole.stop();
+        }
+    }
+
+    /// 按本次专注的网站规则启动屏蔽完整性检查
+    pub fn start_block_watchdog(&self) {
+        self.stop_block_watchdog();
+        let rules = self.site_blocker.lock().unwrap().rules().clone();
+        *self.block_watchdog.lock().unwrap() = Some(BlockWatchdog::start(rules));
+    }
+
+    pub fn stop_block_watchdog(&self) {
+        if let Some(watchdog) = self.block_watchdog.lock().unwrap().take() {
+            watchdog.stop();
+        }
+    }
+
     pub fn stop_scheduler(&self) {
         let mut scheduler_thread = self.scheduler_thread.lock().unwrap();
         scheduler_thread.running_flag.store(false, Ordering::SeqCst);
+        self.scheduler_waker.notify();
         if let Some(handle) = scheduler_thread.handle.take() {
             let _ = handle.join();
         }
@@ -157,12 +400,6 @@ impl AppState {
         config.mode == "scheduled"
     }
 
-    pub fn set_mode(&self, mode: &str) {
-        let mut config = self.config.lock().unwrap();
-        config.mode = mode.to_string();
-        let _ = config.save();
-    }
-
     pub fn cleanup_on_exit(&self) {
         let timer_state = {
             let status = self.timer_status.lock().unwrap();
@@ -172,6 +409,8 @@ impl AppState {
         // 停止所有后台线程
         self.stop_timer_thread();
         self.stop_app_blocker();
+        self.stop_dns_sinkhole();
+        self.stop_block_watchdog();
         self.stop_scheduler();
 
         // Working/Paused/Breaking 时保留 hosts 屏蔽，重启后可直接恢复
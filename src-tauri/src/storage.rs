@@ -0,0 +1,239 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::errors::AppError;
+
+static TMP_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// 原子写入：先写同目录临时文件并 fsync，再 rename 覆盖目标文件
+pub fn write_atomic(path: &Path, content: &[u8]) -> Result<(), AppError> {
+    let dir = path
+        .parent()
+        .ok_or_else(|| AppError::IoError(format!("无效的文件路径: {}", path.display())))?;
+    fs::create_dir_all(dir)?;
+
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let tmp_path = dir.join(format!(
+        ".{}.{}.{}.tmp",
+        file_name,
+        std::process::id(),
+        TMP_COUNTER.fetch_add(1, Ordering::SeqCst)
+    ));
+
+    let result = (|| -> Result<(), AppError> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&tmp_path)?;
+        file.write_all(content)?;
+        file.sync_all()?;
+        drop(file);
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+        return result;
+    }
+
+    // rename 本身也需要落盘，目录 fsync 失败不影响数据正确性
+    if let Ok(dir_file) = File::open(dir) {
+        let _ = dir_file.sync_all();
+    }
+
+    Ok(())
+}
+
+/// 第 index 份备份的路径（1 为最新）
+pub fn backup_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".bak.{}", index));
+    PathBuf::from(name)
+}
+
+/// 序列化为 JSON 并原子写入；写入前把当前完好的文件轮转进备份，保留最近 keep 份
+pub fn save_json<T>(path: &Path, value: &T, keep: usize) -> Result<(), AppError>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let content = serde_json::to_string_pretty(value)?;
+
+    if keep > 0 {
+        rotate_backups::<T>(path, keep);
+    }
+
+    write_atomic(path, content.as_bytes())
+}
+
+/// 读取 JSON 文件；主文件缺失或损坏时从最新的有效备份恢复并写回主文件
+///
+/// 主文件和备份都不存在时返回 `Ok(None)`
+pub fn load_json<T: DeserializeOwned>(path: &Path, keep: usize) -> Result<Option<T>, AppError> {
+    let backups: Vec<PathBuf> = (1..=keep)
+        .map(|index| backup_path(path, index))
+        .filter(|backup| backup.exists())
+        .collect();
+
+    let err = if path.exists() {
+        let content = fs::read_to_string(path)?;
+        let err = match serde_json::from_str::<T>(&content) {
+            Ok(value) => return Ok(Some(value)),
+            Err(e) => e,
+        };
+        eprintln!("[storage] {} 已损坏: {}，尝试从备份恢复", path.display(), err);
+        Some(err)
+    } else if backups.is_empty() {
+        return Ok(None);
+    } else {
+        // 轮转或写入中途崩溃、或主文件被误删时，不能当作首次运行而回到默认值
+        eprintln!("[storage] {} 不存在，尝试从备份恢复", path.display());
+        None
+    };
+
+    for backup in backups {
+        let Ok(backup_content) = fs::read_to_string(&backup) else {
+            continue;
+        };
+        if let Ok(value) = serde_json::from_str::<T>(&backup_content) {
+            println!("[storage] 已从备份恢复: {}", backup.display());
+            if let Err(e) = write_atomic(path, backup_content.as_bytes()) {
+                eprintln!("[storage] 写回恢复内容失败: {}", e);
+            }
+            return Ok(Some(value));
+        }
+    }
+
+    Err(match err {
+        Some(err) => err.into(),
+        None => AppError::IoError(format!("{} 不存在且备份均无法解析", path.display())),
+    })
+}
+
+/// 删除主文件及所有备份
+pub fn remove_with_backups(path: &Path, keep: usize) {
+    let _ = fs::remove_file(path);
+    for index in 1..=keep {
+        let _ = fs::remove_file(backup_path(path, index));
+    }
+}
+
+/// 仅当当前主文件能被正确解析时才轮转，避免损坏内容挤掉完好的备份
+fn rotate_backups<T: DeserializeOwned>(path: &Path, keep: usize) {
+    let Ok(current) = fs::read_to_string(path) else {
+        return;
+    };
+    if serde_json::from_str::<T>(&current).is_err() {
+        return;
+    }
+
+    for index in (1..keep).rev() {
+        let from = backup_path(path, index);
+        if from.exists() {
+            let _ = fs::rename(&from, backup_path(path, index + 1));
+        }
+    }
+
+    if let Err(e) = write_atomic(&backup_path(path, 1), current.as_bytes()) {
+        eprintln!("[storage] 写入备份失败: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Sample {
+        value: u32,
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "pomodoro_storage_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_save_rotates_backups() {
+        let dir = temp_dir("rotate");
+        let path = dir.join("config.json");
+
+        for value in 1..=5 {
+            save_json(&path, &Sample { value }, 3).unwrap();
+        }
+
+        let load = |p: &Path| -> Sample {
+            serde_json::from_str(&fs::read_to_string(p).unwrap()).unwrap()
+        };
+        assert_eq!(load(&path), Sample { value: 5 });
+        assert_eq!(load(&backup_path(&path, 1)), Sample { value: 4 });
+        assert_eq!(load(&backup_path(&path, 3)), Sample { value: 2 });
+        assert!(!backup_path(&path, 4).exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_load_recovers_from_backup() {
+        let dir = temp_dir("recover");
+        let path = dir.join("config.json");
+
+        save_json(&path, &Sample { value: 1 }, 3).unwrap();
+        save_json(&path, &Sample { value: 2 }, 3).unwrap();
+        fs::write(&path, "{\"value\": ").unwrap();
+
+        let loaded: Option<Sample> = load_json(&path, 3).unwrap();
+        assert_eq!(loaded, Some(Sample { value: 1 }));
+        // 主文件已被写回
+        let reloaded: Option<Sample> = load_json(&path, 0).unwrap();
+        assert_eq!(reloaded, Some(Sample { value: 1 }));
+
+        // 损坏的主文件不会被轮转进备份
+        fs::write(&path, "garbage").unwrap();
+        save_json(&path, &Sample { value: 3 }, 3).unwrap();
+        let backup: Sample =
+            serde_json::from_str(&fs::read_to_string(backup_path(&path, 1)).unwrap()).unwrap();
+        assert_eq!(backup, Sample { value: 1 });
+
+        // 主文件缺失时同样从最新的备份恢复
+        fs::remove_file(&path).unwrap();
+        let loaded: Option<Sample> = load_json(&path, 3).unwrap();
+        assert_eq!(loaded, Some(Sample { value: 1 }));
+        assert!(path.exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_load_missing_and_unrecoverable() {
+        let dir = temp_dir("missing");
+        let path = dir.join("session.json");
+
+        assert_eq!(load_json::<Sample>(&path, 3).unwrap(), None);
+
+        fs::write(&path, "not json").unwrap();
+        assert!(load_json::<Sample>(&path, 3).is_err());
+
+        // 只剩无法解析的备份时报错，不回退到默认值
+        fs::remove_file(&path).unwrap();
+        fs::write(backup_path(&path, 1), "not json").unwrap();
+        assert!(load_json::<Sample>(&path, 3).is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
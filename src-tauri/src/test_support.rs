@@ -0,0 +1,104 @@
+//! 测试共用的临时目录和沙盒屏蔽后端
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::errors::AppError;
+use crate::services::site_blocker::BLOCK_MARKER_END;
+use crate::services::{FirewallRules, FirewallStatus, SandboxBackend, SiteBlockBackend};
+
+/// 新建空的临时目录 `pomodoro_<prefix>_<name>_<pid>`，已存在时先清空
+pub fn temp_dir(prefix: &str, name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "pomodoro_{}_{}_{}",
+        prefix,
+        name,
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// 写好 hosts 文件的临时目录，配合 `SandboxBackend` 使用
+pub fn hosts_sandbox(prefix: &str, name: &str, hosts: &str) -> PathBuf {
+    let dir = temp_dir(prefix, name);
+    fs::write(dir.join("hosts"), hosts).unwrap();
+    dir
+}
+
+/// `FaultyBackend` 重新应用时的故障
+pub enum Fault {
+    /// 写入时丢掉结束标记，模拟复制不完整
+    Truncate,
+    /// 写入 hosts 后报错，模拟 hosts 已复制但后续命令失败
+    FailAfterWrite,
+    /// 不写入，直接返回给定的错误
+    Fail(fn() -> AppError),
+}
+
+/// 在沙盒后端上注入故障，并记录 `apply` 的调用次数
+pub struct FaultyBackend {
+    inner: SandboxBackend,
+    fault: Fault,
+    firewall: FirewallStatus,
+    pub applied: AtomicUsize,
+}
+
+impl FaultyBackend {
+    pub fn new(root: PathBuf, fault: Fault) -> Self {
+        FaultyBackend {
+            inner: SandboxBackend::new(root),
+            fault,
+            firewall: FirewallStatus::Intact,
+            applied: AtomicUsize::new(0),
+        }
+    }
+
+    /// 检查时报告给定的防火墙状态
+    pub fn with_firewall(mut self, status: FirewallStatus) -> Self {
+        self.firewall = status;
+        self
+    }
+}
+
+impl SiteBlockBackend for FaultyBackend {
+    fn name(&self) -> &'static str {
+        "faulty"
+    }
+
+    fn hosts_path(&self) -> PathBuf {
+        self.inner.hosts_path()
+    }
+
+    fn backup_path(&self) -> Result<PathBuf, AppError> {
+        self.inner.backup_path()
+    }
+
+    fn apply(&self, hosts: &str, firewall: &FirewallRules) -> Result<(), AppError> {
+        self.applied.fetch_add(1, Ordering::SeqCst);
+        match self.fault {
+            Fault::Truncate => self
+                .inner
+                .apply(&hosts.replace(BLOCK_MARKER_END, ""), firewall),
+            Fault::FailAfterWrite => {
+                self.inner.apply(hosts, firewall)?;
+                Err(AppError::BlockerError("屏蔽命令执行失败".to_string()))
+            }
+            Fault::Fail(error) => Err(error()),
+        }
+    }
+
+    fn restore(&self, hosts: Option<&str>) -> Result<(), AppError> {
+        self.inner.restore(hosts)
+    }
+
+    fn write_hosts(&self, hosts: &str) -> Result<(), AppError> {
+        self.inner.write_hosts(hosts)
+    }
+
+    fn firewall_status(&self) -> FirewallStatus {
+        self.firewall
+    }
+}
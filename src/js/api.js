@@ -44,10 +44,21 @@ export const API = {
   // 配置管理
   getConfig: () => safeInvoke('get_config'),
   saveConfig: (config) => safeInvoke('save_config', { config }),
+  setMode: (mode) => safeInvoke('set_mode', { mode }),
+  importHolidaysIcs: (path) => safeInvoke('import_holidays_ics', { path }),
+  getUpcomingSchedules: (days) => safeInvoke('get_upcoming_schedules', { days }),
+  getPendingCatchUp: () => safeInvoke('get_pending_catch_up'),
+  resolveCatchUp: (join) => safeInvoke('resolve_catch_up', { join }),
+  getHistoryStats: (days) => safeInvoke('get_history_stats', { days }),
   getConfigPath: () => safeInvoke('get_config_path'),
 
+  // 配置包导入/导出
+  exportConfigBundle: (path, sections) => safeInvoke('export_config_bundle', { path, sections }),
+  previewConfigBundle: (path, options) => safeInvoke('preview_config_bundle', { path, options }),
+  importConfigBundle: (path, options) => safeInvoke('import_config_bundle', { path, options }),
+
   // 计时器控制
-  startFocus: (minutes, seconds) => safeInvoke('start_focus', { minutes, seconds }),
+  startFocus: (minutes, seconds, profile) => safeInvoke('start_focus', { minutes, seconds, profile }),
   pauseFocus: () => safeInvoke('pause_focus'),
   resumeFocus: () => safeInvoke('resume_focus'),
   stopFocus: () => safeInvoke('stop_focus'),
@@ -67,6 +78,8 @@ export const API = {
   blockSites: () => safeInvoke('block_sites'),
   unblockSites: () => safeInvoke('unblock_sites'),
   getBlockedSites: () => safeInvoke('get_blocked_sites'),
+  getSiteCategories: () => safeInvoke('get_site_categories'),
+  importBlocklist: (path, name) => safeInvoke('import_blocklist', { path, name }),
 
   // 背景图片
   setBackground: (sourcePath) => safeInvoke('set_background', { sourcePath }),
@@ -9,10 +9,16 @@ let config = null;
 let installedApps = []; // 缓存已安装 App 列表
 let appIconCache = {}; // 图标缓存：{ appName: base64String }
 let selectedSuggestionIndex = -1; // 当前键盘选中的下拉项索引
+let configListenerBound = false; // config-changed 监听只注册一次
+let importListenerBound = false; // 屏蔽列表导入结果监听只注册一次
+let siteCategories = []; // 数据目录中的网站分类
 
 // DOM 元素
 const elements = {};
 
+// 定时计划的星期标签，下标 + 1 即后端的星期编号（1 = 周一）
+const WEEKDAY_LABELS = ['一', '二', '三', '四', '五', '六', '日'];
+
 /**
  * 初始化设置页面
  */
@@ -33,13 +39,30 @@ export async function init() {
   elements.blockedSitesList = document.getElementById('blocked-sites-list');
   elements.newSiteInput = document.getElementById('new-site-input');
   elements.btnAddSite = document.getElementById('btn-add-site');
+  elements.sitesCardTitle = document.getElementById('sites-card-title');
+  elements.siteModeSelect = document.getElementById('site-mode-select');
+  elements.siteCategories = document.getElementById('site-categories');
+  elements.btnImportBlocklist = document.getElementById('btn-import-blocklist');
 
   elements.schedulesList = document.getElementById('schedules-list');
   elements.btnAddSchedule = document.getElementById('btn-add-schedule');
+  elements.upcomingList = document.getElementById('upcoming-list');
+  elements.historyStats = document.getElementById('history-stats');
+  elements.exceptionsList = document.getElementById('exceptions-list');
+  elements.newExceptionDate = document.getElementById('new-exception-date');
+  elements.newExceptionKind = document.getElementById('new-exception-kind');
+  elements.newExceptionStart = document.getElementById('new-exception-start');
+  elements.newExceptionEnd = document.getElementById('new-exception-end');
+  elements.btnAddException = document.getElementById('btn-add-exception');
+  elements.btnImportHolidays = document.getElementById('btn-import-holidays');
+  elements.calendarSourcesList = document.getElementById('calendar-sources-list');
+  elements.btnAddCalendar = document.getElementById('btn-add-calendar');
 
   elements.modeToggle = document.getElementById('mode-toggle');
+  elements.catchUpSelect = document.getElementById('catch-up-select');
   elements.modeDescription = document.getElementById('mode-description');
   elements.soundToggle = document.getElementById('sound-toggle');
+  elements.dnsSinkholeToggle = document.getElementById('dns-sinkhole-toggle');
 
   elements.configPath = document.getElementById('config-path');
 
@@ -80,6 +103,8 @@ export async function init() {
   });
 
   elements.btnAddSite.addEventListener('click', handleAddSite);
+  elements.siteModeSelect.addEventListener('change', handleSiteModeChange);
+  elements.btnImportBlocklist.addEventListener('click', handleImportBlocklist);
   elements.newSiteInput.addEventListener('keypress', (e) => {
     if (e.key === 'Enter') handleAddSite();
   });
@@ -87,17 +112,35 @@ export async function init() {
   // 绑定事件 - 计划添加
   elements.btnAddSchedule.addEventListener('click', handleAddSchedule);
 
+  // 绑定事件 - 例外日期
+  elements.btnAddException.addEventListener('click', handleAddException);
+  elements.newExceptionKind.addEventListener('change', updateExceptionTimeInputs);
+  elements.btnImportHolidays.addEventListener('click', handleImportHolidays);
+
+  // 绑定事件 - 日历来源
+  elements.btnAddCalendar.addEventListener('click', handleAddCalendarSource);
+  updateExceptionTimeInputs();
+
   // 绑定事件 - 模式切换
   elements.modeToggle.addEventListener('change', handleModeToggle);
+  elements.catchUpSelect.addEventListener('change', handleCatchUpChange);
 
   // 绑定事件 - 完成提醒音开关
   elements.soundToggle.addEventListener('change', handleSoundToggle);
 
+  // 绑定事件 - 本地 DNS 子域名屏蔽开关
+  elements.dnsSinkholeToggle.addEventListener('change', handleDnsSinkholeToggle);
+
   // 绑定事件 - 背景图片
   elements.btnChooseBg.addEventListener('click', handleChooseBg);
   elements.btnClearBg.addEventListener('click', handleClearBg);
 
   // 加载数据
+  try {
+    siteCategories = await API.getSiteCategories();
+  } catch (error) {
+    console.error('加载网站分类失败:', error);
+  }
   try {
     config = await API.getConfig();
     const configPath = await API.getConfigPath();
@@ -107,6 +150,28 @@ export async function init() {
     console.error('加载设置失败:', error);
   }
 
+  // 监听外部修改 config.json 后的热加载
+  if (!configListenerBound && window.__TAURI__?.event?.listen) {
+    configListenerBound = true;
+    window.__TAURI__.event.listen('config-changed', (event) => {
+      console.log('配置文件已在外部修改，重新渲染');
+      config = event.payload;
+      render();
+    });
+  }
+
+  // 屏蔽列表在后台导入，完成后通过事件返回结果
+  if (!importListenerBound && window.__TAURI__?.event?.listen) {
+    importListenerBound = true;
+    window.__TAURI__.event.listen('blocklist-import-finished', (event) => {
+      handleBlocklistImported(event.payload);
+    });
+    window.__TAURI__.event.listen('blocklist-import-failed', (event) => {
+      elements.btnImportBlocklist.disabled = false;
+      alert(`导入屏蔽列表失败：${event.payload}`);
+    });
+  }
+
   // 加载背景预览
   loadBgPreview();
 
@@ -144,13 +209,21 @@ export function render() {
 
   // 渲染定时计划
   renderSchedulesList();
+  renderExceptionsList();
+  renderCalendarSourcesList();
+  renderUpcomingList();
+  renderHistoryStats();
 
   // 渲染模式切换
   elements.modeToggle.checked = config.mode === 'scheduled';
+  elements.catchUpSelect.value = config.catch_up || 'join';
   updateModeDescription();
 
   // 渲染完成提醒音开关
   elements.soundToggle.checked = config.play_completion_sound !== false;
+
+  // 渲染本地 DNS 开关
+  elements.dnsSinkholeToggle.checked = !!(config.dns_sinkhole && config.dns_sinkhole.enabled);
 }
 
 /**
@@ -188,19 +261,41 @@ function renderAppsList() {
 }
 
 /**
- * 渲染网站黑名单
+ * 当前屏蔽方式下编辑的网站列表（黑名单或白名单）
+ */
+function activeSiteList() {
+  if (config.site_mode === 'allowlist') {
+    config.allowed_sites = config.allowed_sites || [];
+    return config.allowed_sites;
+  }
+  return config.blocked_sites;
+}
+
+/**
+ * 渲染网站黑名单 / 白名单
  */
 function renderSitesList() {
+  const allowlist = config.site_mode === 'allowlist';
+  const sites = activeSiteList();
+  elements.siteModeSelect.value = allowlist ? 'allowlist' : 'blocklist';
+  elements.sitesCardTitle.textContent = allowlist ? '🌐 网站白名单' : '🌐 网站黑名单';
+  elements.newSiteInput.placeholder = allowlist
+    ? '输入专注期间允许访问的域名（如 docs.rs）'
+    : '输入域名或路径（如 youtube.com、youtube.com/shorts）';
   elements.blockedSitesList.innerHTML = '';
+  renderSiteCategories(allowlist);
 
-  if (config.blocked_sites.length === 0) {
-    elements.blockedSitesList.setAttribute('data-empty-text', '暂无屏蔽网站，点击下方添加');
+  if (sites.length === 0) {
+    elements.blockedSitesList.setAttribute(
+      'data-empty-text',
+      allowlist ? '白名单为空，专注期间将屏蔽所有网站' : '暂无屏蔽网站，点击下方添加'
+    );
     return;
   }
 
   elements.blockedSitesList.removeAttribute('data-empty-text');
 
-  config.blocked_sites.forEach((site, index) => {
+  sites.forEach((site, index) => {
     const item = document.createElement('div');
     item.className = 'list-item';
     item.innerHTML = `
@@ -216,6 +311,24 @@ function renderSitesList() {
   });
 }
 
+/**
+ * 渲染网站分类开关，白名单模式下分类不生效，隐藏
+ */
+function renderSiteCategories(allowlist) {
+  const enabled = config.enabled_categories || [];
+  elements.siteCategories.style.display = allowlist || siteCategories.length === 0 ? 'none' : '';
+  elements.siteCategories.innerHTML = siteCategories.map(category => `
+    <label class="site-category" title="${escapeAttr(category.sites.join('\n'))}">
+      <input type="checkbox" data-id="${escapeAttr(category.id)}" ${enabled.includes(category.id) ? 'checked' : ''}>
+      <span>${escapeHtml(category.name)}（${category.sites.length}）</span>
+    </label>
+  `).join('');
+
+  elements.siteCategories.querySelectorAll('input').forEach(checkbox => {
+    checkbox.addEventListener('change', handleSiteCategoryToggle);
+  });
+}
+
 /**
  * 渲染定时计划列表
  */
@@ -243,6 +356,34 @@ function renderSchedulesList() {
         <input type="time" value="${schedule.end}" data-index="${index}" data-field="end">
       </div>
       <button class="btn-remove" data-index="${index}" data-type="schedule">&times;</button>
+      <div class="schedule-weekdays">
+        ${WEEKDAY_LABELS.map((label, i) => `
+          <label class="schedule-weekday">
+            <input type="checkbox" data-index="${index}" data-day="${i + 1}" ${(schedule.weekdays || []).includes(i + 1) ? 'checked' : ''}>
+            <span>${label}</span>
+          </label>
+        `).join('')}
+      </div>
+      <div class="schedule-date-inputs">
+        <input type="date" value="${schedule.valid_from || ''}" data-index="${index}" data-field="valid_from" title="起始日期（可选）">
+        <span class="schedule-separator">至</span>
+        <input type="date" value="${schedule.valid_until || ''}" data-index="${index}" data-field="valid_until" title="截止日期（可选）">
+      </div>
+      <div class="schedule-session-inputs">
+        <input type="number" min="1" max="180" placeholder="专注" value="${schedule.work_minutes ?? ''}" data-index="${index}" data-field="work_minutes" title="专注分钟数（留空则专注到时间段结束）">
+        <span class="schedule-separator">/</span>
+        <input type="number" min="0" max="60" placeholder="休息" value="${schedule.break_minutes ?? ''}" data-index="${index}" data-field="break_minutes" title="休息分钟数（留空使用全局设置）">
+        <select data-index="${index}" data-field="profile" title="屏蔽方案">
+          <option value="">全局黑名单</option>
+          ${(config.profiles || []).map(p => `
+            <option value="${escapeAttr(p.name)}" ${schedule.profile === p.name ? 'selected' : ''}>${escapeHtml(p.name)}</option>
+          `).join('')}
+        </select>
+        <label class="schedule-emergency">
+          <input type="checkbox" data-index="${index}" ${schedule.emergency === 'disabled' ? 'checked' : ''}>
+          <span>禁止紧急取消</span>
+        </label>
+      </div>
     `;
     elements.schedulesList.appendChild(item);
   });
@@ -256,11 +397,180 @@ function renderSchedulesList() {
     input.addEventListener('change', handleScheduleTimeChange);
   });
 
+  elements.schedulesList.querySelectorAll('.schedule-weekday input').forEach(checkbox => {
+    checkbox.addEventListener('change', handleScheduleWeekdayChange);
+  });
+
+  elements.schedulesList.querySelectorAll('.schedule-date-inputs input').forEach(input => {
+    input.addEventListener('change', handleScheduleDateChange);
+  });
+
+  elements.schedulesList.querySelectorAll('.schedule-session-inputs input[type="number"], .schedule-session-inputs select').forEach(input => {
+    input.addEventListener('change', handleScheduleSessionChange);
+  });
+
+  elements.schedulesList.querySelectorAll('.schedule-emergency input').forEach(checkbox => {
+    checkbox.addEventListener('change', handleScheduleEmergencyChange);
+  });
+
   elements.schedulesList.querySelectorAll('.btn-remove').forEach(btn => {
     btn.addEventListener('click', handleRemoveSchedule);
   });
 }
 
+/**
+ * 渲染近 30 天错过和跳过的定时时间段以及屏蔽被篡改次数的统计
+ */
+async function renderHistoryStats() {
+  let stats = null;
+  try {
+    stats = await API.getHistoryStats(30);
+  } catch (e) {
+    console.error('获取历史统计失败:', e);
+  }
+
+  if (!stats || (stats.missed_windows === 0 && stats.skipped_windows === 0 && stats.tamper_events === 0)) {
+    elements.historyStats.textContent = '';
+    return;
+  }
+  elements.historyStats.textContent =
+    `近 30 天错过 ${stats.missed_windows} 个时间段（${stats.missed_minutes} 分钟），` +
+    `跳过 ${stats.skipped_windows} 个（${stats.skipped_minutes} 分钟）` +
+    (stats.tamper_events ? `，屏蔽被篡改 ${stats.tamper_events} 次` : '');
+}
+
+/**
+ * 渲染未来 7 天的专注时间段（由后端按计划、日历和例外日期计算）
+ */
+async function renderUpcomingList() {
+  let upcoming = [];
+  try {
+    upcoming = await API.getUpcomingSchedules(7);
+  } catch (e) {
+    console.error('获取未来时间段失败:', e);
+  }
+
+  elements.upcomingList.innerHTML = '';
+  if (upcoming.length === 0) {
+    elements.upcomingList.setAttribute('data-empty-text', '未来 7 天没有专注时间段');
+    return;
+  }
+  elements.upcomingList.removeAttribute('data-empty-text');
+
+  const sourceLabels = { schedule: '计划', calendar: '日历', exception: '加开' };
+  upcoming.forEach(focus => {
+    const start = new Date(focus.start);
+    const end = new Date(focus.end);
+    const day = `${start.getMonth() + 1}/${start.getDate()} 周${WEEKDAY_LABELS[(start.getDay() + 6) % 7]}`;
+    const time = `${formatClock(start)}-${formatClock(end)}`;
+    let status = '';
+    if (focus.in_progress) {
+      status = '进行中';
+    } else if (!focus.will_start_session) {
+      status = config.mode === 'scheduled' ? '接续上一段' : '手动模式不会自动开始';
+    }
+
+    const item = document.createElement('div');
+    item.className = 'list-item';
+    item.innerHTML = `
+      <div class="list-item-content">${day} ${time} · ${sourceLabels[focus.source] || ''} ${escapeHtml(focus.label)}</div>
+      <span class="upcoming-status">${status}</span>
+    `;
+    elements.upcomingList.appendChild(item);
+  });
+}
+
+function formatClock(date) {
+  return `${String(date.getHours()).padStart(2, '0')}:${String(date.getMinutes()).padStart(2, '0')}`;
+}
+
+/**
+ * 渲染例外日期列表
+ */
+function renderExceptionsList() {
+  elements.exceptionsList.innerHTML = '';
+  const exceptions = config.schedule_exceptions || [];
+
+  if (exceptions.length === 0) {
+    elements.exceptionsList.setAttribute('data-empty-text', '暂无例外日期');
+    return;
+  }
+
+  elements.exceptionsList.removeAttribute('data-empty-text');
+
+  exceptions.forEach((exception, index) => {
+    const label = exception.kind === 'add'
+      ? `加开 ${exception.start}-${exception.end}`
+      : '跳过';
+    const name = exception.name ? ` · ${escapeHtml(exception.name)}` : '';
+    const item = document.createElement('div');
+    item.className = 'list-item';
+    item.innerHTML = `
+      <div class="list-item-content">${escapeHtml(exception.date)} ${label}${name}</div>
+      <button class="btn-remove" data-index="${index}" data-type="exception">&times;</button>
+    `;
+    elements.exceptionsList.appendChild(item);
+  });
+
+  elements.exceptionsList.querySelectorAll('.btn-remove').forEach(btn => {
+    btn.addEventListener('click', handleRemoveException);
+  });
+}
+
+/**
+ * 渲染日历来源列表
+ */
+function renderCalendarSourcesList() {
+  elements.calendarSourcesList.innerHTML = '';
+  const sources = config.calendar_sources || [];
+
+  if (sources.length === 0) {
+    elements.calendarSourcesList.setAttribute('data-empty-text', '暂无日历来源');
+    return;
+  }
+
+  elements.calendarSourcesList.removeAttribute('data-empty-text');
+
+  sources.forEach((source, index) => {
+    const item = document.createElement('div');
+    item.className = 'schedule-item calendar-source-item';
+    item.innerHTML = `
+      <label class="schedule-toggle">
+        <input type="checkbox" ${source.enabled ? 'checked' : ''} data-index="${index}">
+        <span class="schedule-toggle-slider"></span>
+      </label>
+      <div class="list-item-content calendar-source-path" title="${escapeAttr(source.path)}">${escapeHtml(source.path)}</div>
+      <button class="btn-remove" data-index="${index}" data-type="calendar">&times;</button>
+      <div class="calendar-source-filters">
+        <input type="text" placeholder="标题包含" value="${escapeAttr(source.summary_filter || '')}" data-index="${index}" data-field="summary_filter">
+        <input type="text" placeholder="分类" value="${escapeAttr(source.category_filter || '')}" data-index="${index}" data-field="category_filter">
+      </div>
+    `;
+    elements.calendarSourcesList.appendChild(item);
+  });
+
+  elements.calendarSourcesList.querySelectorAll('.schedule-toggle input').forEach(checkbox => {
+    checkbox.addEventListener('change', handleCalendarSourceToggle);
+  });
+
+  elements.calendarSourcesList.querySelectorAll('.calendar-source-filters input').forEach(input => {
+    input.addEventListener('change', handleCalendarFilterChange);
+  });
+
+  elements.calendarSourcesList.querySelectorAll('.btn-remove').forEach(btn => {
+    btn.addEventListener('click', handleRemoveCalendarSource);
+  });
+}
+
+/**
+ * 只有“加开”需要填写时间段
+ */
+function updateExceptionTimeInputs() {
+  const isAdd = elements.newExceptionKind.value === 'add';
+  elements.newExceptionStart.hidden = !isAdd;
+  elements.newExceptionEnd.hidden = !isAdd;
+}
+
 /**
  * 更新模式描述
  */
@@ -454,24 +764,45 @@ async function handleAddSite() {
     siteName = siteName.replace(/^(www\.)?/, '');
   }
 
-  if (config.blocked_sites.includes(siteName)) {
+  const sites = activeSiteList();
+  if (sites.includes(siteName)) {
     alert('该网站已存在');
     return;
   }
 
-  config.blocked_sites.push(siteName);
+  sites.push(siteName);
   elements.newSiteInput.value = '';
 
   renderSitesList();
   await saveConfig();
 }
 
+/**
+ * 处理屏蔽方式切换（黑名单 / 白名单）
+ */
+async function handleSiteModeChange(e) {
+  config.site_mode = e.target.value;
+
+  renderSitesList();
+  await saveConfig();
+}
+
 /**
  * 处理删除网站
  */
+async function handleSiteCategoryToggle(e) {
+  const id = e.target.dataset.id;
+  const enabled = (config.enabled_categories || []).filter(c => c !== id);
+  if (e.target.checked) {
+    enabled.push(id);
+  }
+  config.enabled_categories = enabled;
+  await saveConfig();
+}
+
 async function handleRemoveSite(e) {
   const index = parseInt(e.currentTarget.dataset.index);
-  config.blocked_sites.splice(index, 1);
+  activeSiteList().splice(index, 1);
 
   renderSitesList();
   await saveConfig();
@@ -485,6 +816,13 @@ async function handleAddSchedule() {
     enabled: true,
     start: '09:00',
     end: '12:00',
+    weekdays: [1, 2, 3, 4, 5, 6, 7],
+    valid_from: null,
+    valid_until: null,
+    work_minutes: null,
+    break_minutes: null,
+    profile: null,
+    emergency: 'quota',
   });
 
   renderSchedulesList();
@@ -513,6 +851,63 @@ async function handleScheduleTimeChange(e) {
   await saveConfig();
 }
 
+/**
+ * 处理计划星期变更
+ */
+async function handleScheduleWeekdayChange(e) {
+  const index = parseInt(e.target.dataset.index);
+  const day = parseInt(e.target.dataset.day);
+  const weekdays = new Set(config.schedules[index].weekdays || []);
+
+  if (e.target.checked) {
+    weekdays.add(day);
+  } else {
+    weekdays.delete(day);
+  }
+  config.schedules[index].weekdays = [...weekdays].sort((a, b) => a - b);
+
+  await saveConfig();
+}
+
+/**
+ * 处理计划起止日期变更（清空表示不限）
+ */
+async function handleScheduleDateChange(e) {
+  const index = parseInt(e.target.dataset.index);
+  const field = e.target.dataset.field;
+
+  config.schedules[index][field] = e.target.value || null;
+
+  await saveConfig();
+}
+
+/**
+ * 处理计划的专注节奏和屏蔽方案变更（留空使用全局设置）
+ */
+async function handleScheduleSessionChange(e) {
+  const index = parseInt(e.target.dataset.index);
+  const field = e.target.dataset.field;
+  const value = e.target.value;
+
+  if (field === 'profile') {
+    config.schedules[index].profile = value || null;
+  } else {
+    config.schedules[index][field] = value === '' ? null : parseInt(value);
+  }
+
+  await saveConfig();
+}
+
+/**
+ * 处理计划的紧急取消策略变更
+ */
+async function handleScheduleEmergencyChange(e) {
+  const index = parseInt(e.target.dataset.index);
+  config.schedules[index].emergency = e.target.checked ? 'disabled' : 'quota';
+
+  await saveConfig();
+}
+
 /**
  * 处理删除计划
  */
@@ -524,6 +919,164 @@ async function handleRemoveSchedule(e) {
   await saveConfig();
 }
 
+/**
+ * 处理添加例外日期
+ */
+async function handleAddException() {
+  const date = elements.newExceptionDate.value;
+  if (!date) return;
+
+  const kind = elements.newExceptionKind.value;
+  const exception = { date, kind, start: null, end: null, name: null };
+  if (kind === 'add') {
+    exception.start = elements.newExceptionStart.value;
+    exception.end = elements.newExceptionEnd.value;
+  }
+
+  config.schedule_exceptions = [...(config.schedule_exceptions || []), exception]
+    .sort((a, b) => a.date.localeCompare(b.date));
+  elements.newExceptionDate.value = '';
+
+  renderExceptionsList();
+  await saveConfig();
+}
+
+/**
+ * 处理删除例外日期
+ */
+async function handleRemoveException(e) {
+  const index = parseInt(e.currentTarget.dataset.index);
+  config.schedule_exceptions.splice(index, 1);
+
+  renderExceptionsList();
+  await saveConfig();
+}
+
+/**
+ * 从本地 .ics 文件导入节假日（导入为跳过日期）
+ */
+async function handleImportHolidays() {
+  try {
+    const selected = await window.__TAURI__.dialog.open({
+      filters: [{ name: 'iCalendar', extensions: ['ics'] }],
+      multiple: false,
+    });
+    if (!selected) return;
+    const report = await API.importHolidaysIcs(selected);
+    config = await API.getConfig();
+    render();
+    alert(`已导入 ${report.added} 个节假日${report.duplicates ? `，${report.duplicates} 个已存在` : ''}`);
+  } catch (e) {
+    console.error('导入节假日失败:', e);
+    alert(`导入节假日失败：${formatError(e)}`);
+  }
+}
+
+/**
+ * 选择 hosts 或 adblock 格式的列表文件，导入到指定名称的分类
+ */
+async function handleImportBlocklist() {
+  try {
+    const selected = await window.__TAURI__.dialog.open({
+      filters: [
+        { name: '屏蔽列表', extensions: ['txt', 'hosts', 'list'] },
+        { name: '所有文件', extensions: ['*'] },
+      ],
+      multiple: false,
+    });
+    if (!selected) return;
+    const name = prompt('导入到哪个列表？（已有同名自定义列表时合并）', '导入的列表');
+    if (!name || !name.trim()) return;
+    elements.btnImportBlocklist.disabled = true;
+    await API.importBlocklist(selected, name.trim());
+  } catch (e) {
+    elements.btnImportBlocklist.disabled = false;
+    console.error('导入屏蔽列表失败:', e);
+    alert(`导入屏蔽列表失败：${formatError(e)}`);
+  }
+}
+
+async function handleBlocklistImported(report) {
+  elements.btnImportBlocklist.disabled = false;
+  try {
+    siteCategories = await API.getSiteCategories();
+  } catch (e) {
+    console.error('加载网站分类失败:', e);
+  }
+  renderSitesList();
+
+  const lines = [
+    `已导入到「${report.list_name}」：新增 ${report.added} 个域名，共 ${report.total} 个`
+  ];
+  if (report.duplicates) lines.push(`${report.duplicates} 个重复`);
+  if (report.skipped_count) {
+    lines.push(`跳过 ${report.skipped_count} 行：`);
+    report.skipped.slice(0, 10).forEach(s => lines.push(`  第 ${s.line} 行 ${s.reason}：${s.text}`));
+    if (report.skipped_count > 10) lines.push('  ……');
+  }
+  const enabled = (config.enabled_categories || []).includes(report.list_id);
+  if (!enabled) lines.push('在上方勾选该列表后生效');
+  alert(lines.join('\n'));
+}
+
+/**
+ * 添加日历来源（默认只取标题包含 focus 的事件）
+ */
+async function handleAddCalendarSource() {
+  try {
+    const selected = await window.__TAURI__.dialog.open({
+      filters: [{ name: 'iCalendar', extensions: ['ics'] }],
+      multiple: false,
+    });
+    if (!selected) return;
+
+    config.calendar_sources = [...(config.calendar_sources || []), {
+      enabled: true,
+      path: selected,
+      summary_filter: 'focus',
+      category_filter: null,
+    }];
+
+    renderCalendarSourcesList();
+    await saveConfig();
+  } catch (e) {
+    console.error('添加日历来源失败:', e);
+  }
+}
+
+/**
+ * 处理日历来源开关切换
+ */
+async function handleCalendarSourceToggle(e) {
+  const index = parseInt(e.target.dataset.index);
+  config.calendar_sources[index].enabled = e.target.checked;
+
+  await saveConfig();
+}
+
+/**
+ * 处理日历过滤条件变更（清空表示不限）
+ */
+async function handleCalendarFilterChange(e) {
+  const index = parseInt(e.target.dataset.index);
+  const field = e.target.dataset.field;
+
+  config.calendar_sources[index][field] = e.target.value.trim() || null;
+
+  await saveConfig();
+}
+
+/**
+ * 处理删除日历来源
+ */
+async function handleRemoveCalendarSource(e) {
+  const index = parseInt(e.currentTarget.dataset.index);
+  config.calendar_sources.splice(index, 1);
+
+  renderCalendarSourcesList();
+  await saveConfig();
+}
+
 /**
  * 处理完成提醒音开关
  */
@@ -532,6 +1085,14 @@ async function handleSoundToggle(e) {
   await saveConfig();
 }
 
+/**
+ * 处理本地 DNS 子域名屏蔽开关（下次开始专注时生效，转发只在专注期间运行）
+ */
+async function handleDnsSinkholeToggle(e) {
+  config.dns_sinkhole = { ...(config.dns_sinkhole || {}), enabled: e.target.checked };
+  await saveConfig();
+}
+
 /**
  * 处理模式切换
  */
@@ -542,6 +1103,15 @@ async function handleModeToggle(e) {
   await saveConfig();
 }
 
+/**
+ * 处理启动时已在时间段内的处理方式变更
+ */
+async function handleCatchUpChange(e) {
+  config.catch_up = e.target.value;
+
+  await saveConfig();
+}
+
 /**
  * 选择背景图片
  */
@@ -616,10 +1186,32 @@ async function saveConfig() {
   try {
     await API.saveConfig(config);
     console.log('配置已自动保存');
+    renderUpcomingList();
   } catch (error) {
     console.error('保存配置失败:', error);
-    alert('保存配置失败: ' + error);
+    if (error && error.CommitmentLocked) {
+      // 专注期间被承诺锁拒绝：提示原因并回滚到后端的配置
+      const details = error.CommitmentLocked.map(v => v.detail).join('\n');
+      alert('专注进行中，不能削弱屏蔽：\n' + details);
+      config = await API.getConfig();
+      render();
+      return;
+    }
+    alert('保存配置失败: ' + formatError(error));
+  }
+}
+
+/**
+ * 将后端 AppError（如 { ConfigError: "..." }）转为可读文本
+ */
+function formatError(error) {
+  if (error && typeof error === 'object') {
+    const values = Object.values(error);
+    if (values.length === 1 && typeof values[0] === 'string') {
+      return values[0];
+    }
   }
+  return String(error);
 }
 
 /**
@@ -651,6 +1243,13 @@ function escapeHtml(text) {
   return div.innerHTML;
 }
 
+/**
+ * 转义用于 HTML 属性值的文本
+ */
+function escapeAttr(text) {
+  return escapeHtml(text).replace(/"/g, '&quot;');
+}
+
 /**
  * 获取当前配置
  */
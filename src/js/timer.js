@@ -114,6 +114,10 @@ export async function init() {
     // 加载背景图片
     loadTimerBackground();
 
+    // 事件可能在页面加载前就已发出，主动查询一次
+    const pendingCatchUp = await API.getPendingCatchUp();
+    if (pendingCatchUp) showCatchUpDialog(pendingCatchUp);
+
     isInitialized = true;
     console.log('计时器页面初始化完成');
   } catch (error) {
@@ -149,7 +153,23 @@ async function setupEventListeners() {
       playSound();
     });
 
-    unlistenFuncs = [unlisten1, unlisten2, unlisten3];
+    // 监听启动时的定时时间段询问
+    const unlisten4 = await listen('schedule-catch-up', (event) => {
+      showCatchUpDialog(event.payload);
+    });
+
+    // 监听网站 IP 解析失败（黑名单中这些网站防火墙不拦截，白名单中这些网站会被防火墙拦截）
+    const unlisten5 = await listen('site-resolve-failed', (event) => {
+      const { allowlist, failures } = event.payload;
+      const domains = failures.map(f => `${f.domain}（${f.reason}）`).join('、');
+      console.warn('部分网站解析 IP 失败:', failures);
+      const message = allowlist
+        ? `以下白名单网站未能解析 IP，防火墙不会放行，专注期间无法访问：${domains}`
+        : `以下网站未能解析 IP，防火墙无法拦截，只由 hosts 和浏览器扩展屏蔽：${domains}`;
+      showNotification('番茄专注', message);
+    });
+
+    unlistenFuncs = [unlisten1, unlisten2, unlisten3, unlisten4, unlisten5];
     console.log('事件监听器设置完成');
   } catch (error) {
     console.error('设置事件监听器失败:', error);
@@ -532,13 +552,15 @@ function commitDigits() {
 /**
  * 显示自定义确认弹窗
  */
-function showConfirmDialog(message, onConfirm) {
+function showConfirmDialog(message, onConfirm, { yesText = '确定取消', noText = '继续专注', onCancel = null } = {}) {
   if (!elements.confirmOverlay) return;
 
-  // 设置消息
+  // 设置消息和按钮文字
   if (elements.confirmMessage) {
     elements.confirmMessage.textContent = message;
   }
+  elements.confirmYes.textContent = yesText;
+  elements.confirmNo.textContent = noText;
 
   // 显示弹窗
   elements.confirmOverlay.style.display = 'flex';
@@ -552,9 +574,24 @@ function showConfirmDialog(message, onConfirm) {
   // 绑定取消按钮
   elements.confirmNo.onclick = () => {
     elements.confirmOverlay.style.display = 'none';
+    if (onCancel) onCancel();
   };
 }
 
+/**
+ * 启动时处于定时时间段且设置为询问时，确认是否加入
+ */
+function showCatchUpDialog(focus) {
+  const end = focus.end.slice(11, 16);
+  showConfirmDialog(`当前处于定时专注时间段「${focus.label}」（至 ${end}），是否加入？`, () => {
+    API.resolveCatchUp(true).catch(error => alert('加入专注失败: ' + error));
+  }, {
+    yesText: '加入专注',
+    noText: '跳过本次',
+    onCancel: () => API.resolveCatchUp(false).catch(error => console.error('跳过时间段失败:', error)),
+  });
+}
+
 /**
  * 播放完成提示音（Web Audio API，880Hz 正弦波，3 次）
  */